@@ -1,4 +1,5 @@
 use crate::settings::toml::{Target, TargetType};
+use crate::sites::HeaderRules;
 use crate::terminal::message::{Message, StdErr};
 use crate::terminal::styles;
 use crate::wranglerjs;
@@ -12,6 +13,11 @@ use anyhow::{anyhow, Result};
 // Internal build logic, called by both `build` and `publish`
 // TODO: return a struct containing optional build info and construct output at command layer
 pub fn build_target(target: &Target) -> Result<String> {
+    // catch bad header rules before the site's files are uploaded
+    if let Some(site) = &target.site {
+        HeaderRules::new(site)?;
+    }
+
     let target_type = &target.target_type;
     match target_type {
         TargetType::JavaScript => match &target.build {
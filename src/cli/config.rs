@@ -1,9 +1,71 @@
+use super::{Cli, WorkerSelection};
 use crate::commands;
 use crate::settings::global_user::GlobalUser;
-use crate::terminal::message::{Message, StdOut};
+use crate::settings::toml::{find_config, Manifest};
+use crate::terminal::message::{Message, Output, StdOut};
 use crate::terminal::{interactive, styles};
 
 use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum ConfigSubcommand {
+    /// Check your wrangler.toml for problems without contacting Cloudflare
+    Validate,
+    /// Rewrite deprecated keys in your wrangler.toml into their current forms
+    Migrate {
+        /// Show the changes that would be made without writing them
+        #[structopt(long = "dry-run")]
+        dry_run: bool,
+    },
+    /// Print your configuration as wrangler reads it, with `extends` and variables resolved
+    Show {
+        /// Print the settings your worker ends up with in the selected environment
+        #[structopt(long)]
+        resolved: bool,
+
+        /// Resolve the preview target used by `wrangler preview` and `wrangler dev`
+        #[structopt(long, requires = "resolved")]
+        preview: bool,
+
+        /// Print the configuration as TOML or as JSON
+        #[structopt(long, default_value = "toml", possible_values = &["toml", "json"])]
+        format: String,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
+    },
+}
+
+pub fn config(subcommand: ConfigSubcommand, cli_params: &Cli) -> Result<()> {
+    match subcommand {
+        ConfigSubcommand::Validate => commands::config::validate(&find_config(&cli_params.config)),
+        ConfigSubcommand::Migrate { dry_run } => {
+            commands::config::migrate(&find_config(&cli_params.config), dry_run)
+        }
+        ConfigSubcommand::Show {
+            resolved,
+            preview,
+            format,
+            workers,
+        } => {
+            let env = cli_params.environment.as_deref();
+            let manifest = Manifest::new(&cli_params.config)?;
+            let output = if format == "json" {
+                Output::Json
+            } else {
+                Output::PlainText
+            };
+            if resolved {
+                let manifest = workers.select_one(manifest, env, "config show --resolved")?;
+                commands::config::show_resolved(&manifest, env, preview, output)
+            } else {
+                commands::config::show(&manifest, output)
+            }
+        }
+    }
+}
 
 pub fn configure(api_key: bool, no_verify: bool) -> Result<()> {
     let user: GlobalUser = if !api_key {
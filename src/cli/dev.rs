@@ -1,28 +1,42 @@
 use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
 
-use super::Cli;
-use crate::commands::{self, dev::Protocol};
+use super::{Cli, WorkerSelection};
+use crate::commands::{
+    self,
+    dev::{LocalRuntime, Protocol},
+};
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 
 use anyhow::Result;
 
+#[allow(clippy::too_many_arguments)]
 pub fn dev(
     host: Option<String>,
     mut ip: Option<IpAddr>,
     mut port: Option<u16>,
     mut local_protocol: Option<Protocol>,
     mut upstream_protocol: Option<Protocol>,
+    test_scheduled: bool,
+    run_crons: bool,
+    local: bool,
+    kv_persist: Option<PathBuf>,
+    workers: WorkerSelection,
     cli_params: &Cli,
 ) -> Result<()> {
     log::info!("Starting dev server");
     let manifest = Manifest::new(&cli_params.config)?;
+    let manifest = workers.select_one(manifest, cli_params.environment.as_deref(), "dev")?;
+
+    let mut local_runtime = None;
 
     // Check if arg not given but present in wrangler.toml
-    if let Some(d) = &manifest.dev {
+    if let Some(d) = manifest.get_dev(cli_params.environment.as_deref())? {
         ip = ip.or(d.ip);
         port = port.or(d.port);
         local_protocol = local_protocol.or(d.local_protocol);
         upstream_protocol = upstream_protocol.or(d.upstream_protocol);
+        local_runtime = d.local_runtime;
     }
 
     let ip = ip.unwrap_or_else(|| Ipv4Addr::new(127, 0, 0, 1).into());
@@ -30,11 +44,38 @@ pub fn dev(
     let local_protocol = local_protocol.unwrap_or(Protocol::Http);
     let upstream_protocol = upstream_protocol.unwrap_or(Protocol::Https);
 
-    let deployments = manifest.get_deployments(cli_params.environment.as_deref())?;
     let target = manifest.get_target(cli_params.environment.as_deref(), true)?;
-    let user = GlobalUser::new().ok();
 
-    let server_config = commands::dev::ServerConfig::new(host, ip, port, upstream_protocol)?;
+    let server_config = commands::dev::ServerConfig::new(
+        host,
+        ip,
+        port,
+        upstream_protocol,
+        test_scheduled,
+        run_crons,
+    )?;
+
+    if local {
+        // a local session has no routes, so only the crons matter
+        let crons = manifest
+            .get_triggers(cli_params.environment.as_deref())?
+            .map(|(triggers, _)| triggers.crons.clone())
+            .unwrap_or_default();
+        let runtime = LocalRuntime::configured(local_runtime)?;
+
+        return commands::dev::dev_local(
+            target,
+            crons,
+            server_config,
+            local_protocol,
+            runtime,
+            kv_persist,
+            cli_params.verbose,
+        );
+    }
+
+    let deployments = manifest.get_deployments(cli_params.environment.as_deref())?;
+    let user = GlobalUser::new().ok();
 
     commands::dev::dev(
         target,
@@ -1,5 +1,6 @@
+use super::Cli;
 use crate::commands;
-use crate::settings::toml::TargetType;
+use crate::settings::toml::{ConfigFormat, TargetType};
 
 use anyhow::Result;
 
@@ -8,6 +9,7 @@ pub fn generate(
     site: bool,
     template: Option<String>,
     target_type: Option<TargetType>,
+    cli_params: &Cli,
 ) -> Result<()> {
     const DEFAULT_TEMPLATE: &str = "https://github.com/cloudflare/worker-template";
     const RUST_TEMPLATE: &str = "https://github.com/cloudflare/rustwasm-worker-template";
@@ -29,5 +31,6 @@ pub fn generate(
         name
     );
 
-    commands::generate(&name, template, target_type, site)
+    let format = ConfigFormat::from_path(&cli_params.config);
+    commands::generate(&name, template, target_type, site, format)
 }
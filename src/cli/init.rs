@@ -1,7 +1,14 @@
-use crate::{commands, settings::toml::TargetType};
+use super::Cli;
+use crate::commands;
+use crate::settings::toml::{ConfigFormat, TargetType};
 use anyhow::Result;
 
-pub fn init(name: Option<String>, site: bool, target_type: Option<TargetType>) -> Result<()> {
+pub fn init(
+    name: Option<String>,
+    site: bool,
+    target_type: Option<TargetType>,
+    cli_params: &Cli,
+) -> Result<()> {
     let target_type = if site {
         // Workers Sites projects are always webpack for now
         Some(TargetType::Webpack)
@@ -9,5 +16,6 @@ pub fn init(name: Option<String>, site: bool, target_type: Option<TargetType>) -
         target_type
     };
 
-    commands::init(name.as_deref(), target_type, site)
+    let format = ConfigFormat::from_path(&cli_params.config);
+    commands::init(name.as_deref(), target_type, site, format)
 }
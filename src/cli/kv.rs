@@ -2,8 +2,14 @@ use std::path::PathBuf;
 
 use super::Cli;
 use crate::commands;
-use crate::commands::kv::key::{parse_metadata, KVMetaData};
+use crate::commands::kv::bulk::InputFormat;
+use crate::commands::kv::key::{
+    parse_metadata, GetOptions, KVMetaData, ListFormat, ListOptions, MetadataFilter,
+};
+use crate::commands::kv::namespace::Location;
+use crate::kv::archive::Format;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
+use crate::terminal::message::Output;
 
 use anyhow::{anyhow, Result};
 use clap::ArgGroup;
@@ -28,6 +34,42 @@ pub enum KvNamespace {
     },
     /// List all namespaces on your Cloudflare account
     List,
+    /// Copy every key-value pair in one namespace to another
+    Copy {
+        #[structopt(flatten)]
+        namespace: Namespace,
+
+        #[structopt(flatten)]
+        destination: Destination,
+
+        /// Only copy keys that start with this prefix
+        #[structopt(name = "prefix", long, short = "p")]
+        prefix: Option<String>,
+
+        /// Print a summary of what was copied as JSON
+        #[structopt(long, possible_value = "json")]
+        output: Option<String>,
+    },
+    /// Make a namespace match another, writing only the key-value pairs that differ
+    Sync {
+        #[structopt(flatten)]
+        namespace: Namespace,
+
+        #[structopt(flatten)]
+        destination: Destination,
+
+        /// Only sync keys that start with this prefix
+        #[structopt(name = "prefix", long, short = "p")]
+        prefix: Option<String>,
+
+        /// Delete keys from the destination that aren't in the source
+        #[structopt(long)]
+        delete_extraneous: bool,
+
+        /// Print a summary of what was synced as JSON
+        #[structopt(long, possible_value = "json")]
+        output: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, StructOpt)]
@@ -51,6 +93,32 @@ pub struct Namespace {
     pub namespace_id: Option<String>,
 }
 
+// The namespace `kv:namespace copy` and `kv:namespace sync` write to, which can be in
+// another environment or account than the one they read from
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(group = ArgGroup::with_name("destination-specifier").required(true))]
+pub struct Destination {
+    /// The binding of the namespace to write to
+    #[structopt(name = "to-binding", long, group = "destination-specifier")]
+    pub to_binding: Option<String>,
+
+    /// Writes to the preview namespace when combined with --to-binding
+    #[structopt(long, requires = "to-binding")]
+    pub to_preview: bool,
+
+    /// The ID of the namespace to write to
+    #[structopt(name = "to-namespace-id", long, group = "destination-specifier")]
+    pub to_namespace_id: Option<String>,
+
+    /// The environment --to-binding is configured in, if not the one given by --env
+    #[structopt(long)]
+    pub to_env: Option<String>,
+
+    /// The account the namespace to write to belongs to, if not the one configured for it
+    #[structopt(long)]
+    pub to_account_id: Option<String>,
+}
+
 #[derive(Debug, Clone, StructOpt)]
 #[structopt(rename_all = "lower")]
 pub enum KvKey {
@@ -92,6 +160,19 @@ pub enum KvKey {
         /// Key whose value to get
         #[structopt(name = "key", index = 1)]
         key: String,
+
+        /// Write the value to a file, byte for byte, rather than to stdout
+        #[structopt(long, short = "o", conflicts_with = "json")]
+        output: Option<PathBuf>,
+
+        /// Print the key's metadata to stderr, after the value
+        #[structopt(long)]
+        with_metadata: bool,
+
+        /// Print the value, metadata and expiration as JSON. Values that are not UTF-8 are
+        /// base64 encoded
+        #[structopt(long)]
+        json: bool,
     },
     /// Delete a key and its value from a namespace
     Delete {
@@ -102,7 +183,7 @@ pub enum KvKey {
         #[structopt(name = "key", index = 1)]
         key: String,
     },
-    /// List all keys in a namespace. Produces JSON output
+    /// List all keys in a namespace. Produces JSON output unless --format is given
     List {
         #[structopt(flatten)]
         namespace: Namespace,
@@ -110,6 +191,35 @@ pub enum KvKey {
         /// The prefix for filtering listed keys
         #[structopt(name = "prefix", long, short = "p")]
         prefix: Option<String>,
+
+        /// The most keys to read from the namespace, at least 10. Filters apply to these keys
+        #[structopt(long)]
+        limit: Option<usize>,
+
+        /// Continue an earlier listing that stopped at its --limit
+        #[structopt(long)]
+        cursor: Option<String>,
+
+        /// Also get each key's value. Values that aren't UTF-8 are base64 encoded
+        #[structopt(long)]
+        with_values: bool,
+
+        /// Only list keys that expire before this many seconds since the UNIX epoch
+        #[structopt(long)]
+        expires_before: Option<i64>,
+
+        /// Only list keys that expire after this many seconds since the UNIX epoch
+        #[structopt(long)]
+        expires_after: Option<i64>,
+
+        /// Only list keys whose metadata has a value at this path, like `owner.name`, or
+        /// this value there, like `owner.name=alice`. May be given more than once
+        #[structopt(long = "filter-metadata", number_of_values = 1)]
+        metadata_filters: Vec<MetadataFilter>,
+
+        /// How to print the keys
+        #[structopt(long, short = "f", default_value = "json", possible_values = &["json", "ndjson", "csv", "table"])]
+        format: ListFormat,
     },
 }
 
@@ -121,9 +231,19 @@ pub enum KvBulk {
         #[structopt(flatten)]
         namespace: Namespace,
 
-        /// The JSON file of key-value pairs to upload, in form [{\"key\":..., \"value\":...}\"...]
+        /// The JSON file of key-value pairs to upload, in form [{\"key\":..., \"value\":...}\"...],
+        /// an NDJSON or CSV file of them, or a directory whose files are each uploaded as a key
         #[structopt(index = 1)]
         path: PathBuf,
+
+        /// The format of the file. Defaults to ndjson for paths ending in .ndjson or .jsonl,
+        /// csv for .csv, and json otherwise
+        #[structopt(long, possible_values = &["json", "ndjson", "csv"])]
+        format: Option<InputFormat>,
+
+        /// Base64 encode the files in a directory, so they needn't be UTF-8 text
+        #[structopt(long)]
+        base64: bool,
     },
     /// Delete multiple keys and their values from a namespace
     Delete {
@@ -136,6 +256,38 @@ pub enum KvBulk {
     },
 }
 
+#[derive(Debug, Clone, StructOpt)]
+pub struct KvExport {
+    #[structopt(flatten)]
+    namespace: Namespace,
+
+    /// The archive to write every key-value pair, expiration and metadata in the namespace to
+    #[structopt(index = 1)]
+    path: PathBuf,
+
+    /// The archive format. Defaults to tar for paths ending in .tar, and ndjson otherwise
+    #[structopt(long, possible_values = &["ndjson", "tar"])]
+    format: Option<Format>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct KvImport {
+    #[structopt(flatten)]
+    namespace: Namespace,
+
+    /// The archive written by `wrangler kv:export` to upload to the namespace
+    #[structopt(index = 1)]
+    path: PathBuf,
+
+    /// The archive format. Defaults to tar for paths ending in .tar, and ndjson otherwise
+    #[structopt(long, possible_values = &["ndjson", "tar"])]
+    format: Option<Format>,
+
+    /// Show which keys would be added or changed, without writing anything
+    #[structopt(long)]
+    dry_run: bool,
+}
+
 pub fn kv_namespace(namespace: KvNamespace, cli_params: &Cli) -> Result<()> {
     let user = GlobalUser::new()?;
     let manifest = Manifest::new(&cli_params.config)?;
@@ -160,6 +312,90 @@ pub fn kv_namespace(namespace: KvNamespace, cli_params: &Cli) -> Result<()> {
             let target = manifest.get_target(env, false)?;
             commands::kv::namespace::list(&target, &user)
         }
+        KvNamespace::Copy {
+            namespace,
+            destination,
+            prefix,
+            output,
+        } => {
+            let source = source_location(&manifest, env, namespace)?;
+            let destination = destination_location(&manifest, env, destination)?;
+            commands::kv::namespace::copy(
+                &user,
+                &source,
+                &destination,
+                prefix.as_deref(),
+                output_format(output),
+            )
+        }
+        KvNamespace::Sync {
+            namespace,
+            destination,
+            prefix,
+            delete_extraneous,
+            output,
+        } => {
+            let source = source_location(&manifest, env, namespace)?;
+            let destination = destination_location(&manifest, env, destination)?;
+            commands::kv::namespace::sync(
+                &user,
+                &source,
+                &destination,
+                prefix.as_deref(),
+                delete_extraneous,
+                output_format(output),
+            )
+        }
+    }
+}
+
+fn source_location(
+    manifest: &Manifest,
+    env: Option<&str>,
+    namespace: Namespace,
+) -> Result<Location> {
+    let target = manifest.get_target(env, namespace.preview)?;
+    let namespace_id = if let Some(binding) = namespace.binding {
+        commands::kv::get_namespace_id(&target, &binding)?
+    } else {
+        namespace
+            .namespace_id
+            .expect("Namespace ID is required if binding isn't supplied")
+    };
+    Ok(Location {
+        target,
+        namespace_id,
+    })
+}
+
+fn destination_location(
+    manifest: &Manifest,
+    env: Option<&str>,
+    destination: Destination,
+) -> Result<Location> {
+    let env = destination.to_env.as_deref().or(env);
+    let mut target = manifest.get_target(env, destination.to_preview)?;
+    if let Some(account_id) = destination.to_account_id {
+        target.account_id = Some(account_id).into();
+    }
+    let namespace_id = if let Some(binding) = destination.to_binding {
+        commands::kv::get_namespace_id(&target, &binding)?
+    } else {
+        destination
+            .to_namespace_id
+            .expect("Namespace ID is required if binding isn't supplied")
+    };
+    Ok(Location {
+        target,
+        namespace_id,
+    })
+}
+
+fn output_format(output: Option<String>) -> Output {
+    if output.as_deref() == Some("json") {
+        Output::Json
+    } else {
+        Output::PlainText
     }
 }
 
@@ -181,9 +417,20 @@ pub fn kv_key(key: KvKey, cli_params: &Cli) -> Result<()> {
     };
 
     match key {
-        KvKey::Get { namespace, key } => {
+        KvKey::Get {
+            namespace,
+            key,
+            output,
+            with_metadata,
+            json,
+        } => {
             let (target, namespace_id) = target_and_namespace(namespace)?;
-            commands::kv::key::get(&target, &user, &namespace_id, &key)
+            let options = GetOptions {
+                output,
+                with_metadata,
+                json,
+            };
+            commands::kv::key::get(&target, &user, &namespace_id, &key, options)
         }
         KvKey::Put {
             namespace,
@@ -218,9 +465,33 @@ pub fn kv_key(key: KvKey, cli_params: &Cli) -> Result<()> {
             let (target, namespace_id) = target_and_namespace(namespace)?;
             commands::kv::key::delete(&target, &user, &namespace_id, &key)
         }
-        KvKey::List { namespace, prefix } => {
+        KvKey::List {
+            namespace,
+            prefix,
+            limit,
+            cursor,
+            with_values,
+            expires_before,
+            expires_after,
+            metadata_filters,
+            format,
+        } => {
             let (target, namespace_id) = target_and_namespace(namespace)?;
-            commands::kv::key::list(&target, &user, &namespace_id, prefix.as_deref())
+            commands::kv::key::list(
+                &target,
+                &user,
+                &namespace_id,
+                ListOptions {
+                    prefix,
+                    limit,
+                    cursor,
+                    with_values,
+                    expires_before,
+                    expires_after,
+                    metadata_filters,
+                    format,
+                },
+            )
         }
     }
 }
@@ -244,9 +515,14 @@ pub fn kv_bulk(bulk: KvBulk, cli_params: &Cli) -> Result<()> {
     };
 
     match bulk {
-        KvBulk::Put { namespace, path } => {
+        KvBulk::Put {
+            namespace,
+            path,
+            format,
+            base64,
+        } => {
             let (target, namespace_id) = target_and_namespace(namespace)?;
-            commands::kv::bulk::put(&target, &user, &namespace_id, &path)
+            commands::kv::bulk::put(&target, &user, &namespace_id, &path, format, base64)
         }
         KvBulk::Delete { namespace, path } => {
             let (target, namespace_id) = target_and_namespace(namespace)?;
@@ -254,3 +530,50 @@ pub fn kv_bulk(bulk: KvBulk, cli_params: &Cli) -> Result<()> {
         }
     }
 }
+
+pub fn kv_export(export: KvExport, cli_params: &Cli) -> Result<()> {
+    let manifest = Manifest::new(&cli_params.config)?;
+    let user = GlobalUser::new()?;
+    let env = cli_params.environment.as_deref();
+
+    let KvExport {
+        namespace,
+        path,
+        format,
+    } = export;
+    let target = manifest.get_target(env, namespace.preview)?;
+    let namespace_id = if let Some(binding) = namespace.binding {
+        commands::kv::get_namespace_id(&target, &binding)?
+    } else {
+        namespace
+            .namespace_id
+            .expect("Namespace ID is required if binding isn't supplied")
+    };
+
+    let format = Format::new(format, &path);
+    commands::kv::archive::export(&target, &user, &namespace_id, &path, format)
+}
+
+pub fn kv_import(import: KvImport, cli_params: &Cli) -> Result<()> {
+    let manifest = Manifest::new(&cli_params.config)?;
+    let user = GlobalUser::new()?;
+    let env = cli_params.environment.as_deref();
+
+    let KvImport {
+        namespace,
+        path,
+        format,
+        dry_run,
+    } = import;
+    let target = manifest.get_target(env, namespace.preview)?;
+    let namespace_id = if let Some(binding) = namespace.binding {
+        commands::kv::get_namespace_id(&target, &binding)?
+    } else {
+        namespace
+            .namespace_id
+            .expect("Namespace ID is required if binding isn't supplied")
+    };
+
+    let format = Format::new(format, &path);
+    commands::kv::archive::import(&target, &user, &namespace_id, &path, format, dry_run)
+}
@@ -8,25 +8,32 @@ pub mod preview;
 pub mod publish;
 pub mod route;
 pub mod secret;
+pub mod sites;
 pub mod subdomain;
 pub mod tail;
+pub mod triggers;
 pub mod whoami;
 
 pub mod exec {
     pub use super::build::build;
+    pub use super::config::config;
     pub use super::config::configure;
     pub use super::dev::dev;
     pub use super::generate::generate;
     pub use super::init::init;
     pub use super::kv::kv_bulk;
+    pub use super::kv::kv_export;
+    pub use super::kv::kv_import;
     pub use super::kv::kv_key;
     pub use super::kv::kv_namespace;
     pub use super::preview::preview;
     pub use super::publish::publish;
     pub use super::route::route;
     pub use super::secret::secret;
+    pub use super::sites::sites;
     pub use super::subdomain::subdomain;
     pub use super::tail::tail;
+    pub use super::triggers::triggers;
     pub use super::whoami::whoami;
 }
 
@@ -38,8 +45,9 @@ use crate::preview::HttpMethod;
 use crate::settings::toml::migrations::{
     DurableObjectsMigration, Migration, MigrationConfig, Migrations, RenameClass, TransferClass,
 };
-use crate::settings::toml::TargetType;
+use crate::settings::toml::{deployment_order, Manifest, TargetType};
 
+use anyhow::Result;
 use clap::AppSettings;
 use structopt::StructOpt;
 use url::Url;
@@ -83,10 +91,26 @@ pub enum Command {
     #[structopt(name = "kv:bulk", setting = AppSettings::SubcommandRequiredElseHelp)]
     KvBulk(kv::KvBulk),
 
+    /// Write every key-value pair in a namespace to an NDJSON or tar archive
+    #[structopt(name = "kv:export")]
+    KvExport(kv::KvExport),
+
+    /// Upload the key-value pairs in an archive from `wrangler kv:export` to a namespace
+    #[structopt(name = "kv:import")]
+    KvImport(kv::KvImport),
+
     /// List or delete worker routes.
     #[structopt(name = "route", setting = AppSettings::SubcommandRequiredElseHelp)]
     Route(route::Route),
 
+    /// Review the cron triggers of your worker
+    #[structopt(name = "triggers", setting = AppSettings::SubcommandRequiredElseHelp)]
+    Triggers(triggers::Triggers),
+
+    /// Review the files of your Workers Site
+    #[structopt(name = "sites", setting = AppSettings::SubcommandRequiredElseHelp)]
+    Sites(sites::Sites),
+
     /// Generate a secret that can be referenced in the worker script
     #[structopt(name = "secret", setting = AppSettings::SubcommandRequiredElseHelp)]
     Secret(secret::Secret),
@@ -175,6 +199,29 @@ pub enum Command {
         /// but can be set to http
         #[structopt(name = "upstream-protocol")]
         upstream_protocol: Option<Protocol>,
+
+        /// Dispatch a scheduled event to your worker on requests for
+        /// /__scheduled?cron=<cron>
+        #[structopt(long = "test-scheduled")]
+        test_scheduled: bool,
+
+        /// Fire the crons in [triggers] on their schedule while wrangler dev runs.
+        /// Implies --test-scheduled
+        #[structopt(long = "run-crons")]
+        run_crons: bool,
+
+        /// Run your worker on this machine instead of on Cloudflare, using the runtime in
+        /// `local_runtime` under [dev] or in WRANGLER_LOCAL_RUNTIME
+        #[structopt(long)]
+        local: bool,
+
+        /// Keep the KV namespaces of a --local session in this directory, so they last
+        /// between sessions
+        #[structopt(long = "kv-persist", requires = "local", parse(from_os_str))]
+        kv_persist: Option<PathBuf>,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
     },
 
     /// Publish your worker to the orange cloud
@@ -187,8 +234,16 @@ pub enum Command {
         #[structopt(possible_value = "json")]
         output: Option<String>,
 
+        /// Build, and show which files of your Workers Site would be added, changed and
+        /// removed, without publishing. Fails when there's a site and none would be
+        #[structopt(long)]
+        dry_run: bool,
+
         #[structopt(flatten)]
         migration: AdhocMigration,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
     },
 
     /// Authenticate Wrangler with a Cloudflare API Token or Global API Key
@@ -201,6 +256,9 @@ pub enum Command {
         /// Do not verify provided credentials before writing out Wrangler config file
         #[structopt(name = "no-verify", long)]
         no_verify: bool,
+
+        #[structopt(subcommand)]
+        subcommand: Option<config::ConfigSubcommand>,
     },
 
     /// Configure your workers.dev subdomain
@@ -229,6 +287,9 @@ pub enum Command {
         /// Provides endpoint for cloudflared metrics. Used to retrieve tunnel url
         #[structopt(long = "metrics")]
         metrics_port: Option<u16>,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
     },
 
     /// Authenticate Wrangler with your Cloudflare username and password
@@ -318,6 +379,52 @@ impl AdhocMigration {
     }
 }
 
+#[derive(Debug, Clone, Default, StructOpt)]
+pub struct WorkerSelection {
+    /// The name of the worker to use, when your configuration file declares [[workers]]
+    #[structopt(name = "worker", long)]
+    worker: Option<String>,
+
+    /// Use every worker declared in [[workers]]
+    #[structopt(long, conflicts_with = "worker")]
+    all: bool,
+}
+
+impl WorkerSelection {
+    /// Resolves the selected workers of a configuration file. `--all` orders them so that
+    /// Durable Object hosts come before the workers that bind to them. A file without
+    /// [[workers]] describes a single worker, and so does a file with only one.
+    pub fn select(&self, manifest: Manifest, env: Option<&str>) -> Result<Vec<Manifest>> {
+        let names = manifest.worker_names();
+        match (&self.worker, self.all) {
+            (Some(name), _) => Ok(vec![manifest.worker(name)?]),
+            (None, _) if names.is_empty() => Ok(vec![manifest]),
+            (None, true) => deployment_order(manifest.all_workers()?, env),
+            (None, false) if names.len() == 1 => manifest.all_workers(),
+            (None, false) => anyhow::bail!(
+                "Your configuration file declares more than one worker ({}); choose one with `--worker <name>`, or use `--all`",
+                names.join(", ")
+            ),
+        }
+    }
+
+    /// Like `select`, for commands that work with one worker at a time.
+    pub fn select_one(
+        &self,
+        manifest: Manifest,
+        env: Option<&str>,
+        command: &str,
+    ) -> Result<Manifest> {
+        if self.all {
+            anyhow::bail!(
+                "`wrangler {}` works with one worker at a time; choose one with `--worker <name>`",
+                command
+            )
+        }
+        Ok(self.select(manifest, env)?.remove(0))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -386,4 +493,45 @@ mod tests {
             assert!(false, "Unkown command {:?}", command)
         }
     }
+
+    #[test]
+    fn worker_selection() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+type = "javascript"
+
+[[workers]]
+name = "api"
+
+[[workers]]
+name = "auth"
+"#,
+        )
+        .unwrap();
+
+        let selected = |args: &[&str]| -> Result<Vec<String>> {
+            let workers = match Cli::from_iter(args).command {
+                Command::Publish { workers, .. } => workers.select(manifest.clone(), None)?,
+                Command::Tail { workers, .. } => {
+                    vec![workers.select_one(manifest.clone(), None, "tail")?]
+                }
+                command => panic!("Unknown command {:?}", command),
+            };
+            Ok(workers.into_iter().map(|worker| worker.name).collect())
+        };
+
+        assert_eq!(
+            selected(&["wrangler", "publish", "--all"]).unwrap(),
+            vec!["api", "auth"]
+        );
+        assert!(selected(&["wrangler", "publish"]).is_err());
+        assert_eq!(
+            selected(&["wrangler", "tail", "--worker", "auth"]).unwrap(),
+            vec!["auth"]
+        );
+        // tail follows one worker at a time
+        assert!(selected(&["wrangler", "tail", "--all"]).is_err());
+        assert!(selected(&["wrangler", "tail"]).is_err());
+        assert!(Cli::from_iter_safe(&["wrangler", "tail", "--all", "--worker", "api"]).is_err());
+    }
 }
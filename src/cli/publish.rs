@@ -1,6 +1,7 @@
 use super::Cli;
-use super::{AdhocMigration, Migrations};
+use super::{AdhocMigration, Migrations, WorkerSelection};
 use crate::commands;
+use crate::commands::sites::Diffs;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 use crate::terminal::message::{Message, Output, StdOut};
 use crate::terminal::styles;
@@ -10,7 +11,9 @@ use anyhow::Result;
 pub fn publish(
     release: bool,
     output: Option<String>,
+    dry_run: bool,
     migration: AdhocMigration,
+    workers: WorkerSelection,
     cli_params: &Cli,
 ) -> Result<()> {
     log::info!("Getting User settings");
@@ -28,12 +31,12 @@ pub fn publish(
 
     log::info!("Getting project settings");
     let manifest = Manifest::new(&cli_params.config)?;
-    let mut target = manifest.get_target(cli_params.environment.as_deref(), false)?;
+    let env = cli_params.environment.as_deref();
+    let manifests = workers.select(manifest, env)?;
 
-    if let Some(migration) = migration.into_migration_config() {
-        target.migrations = Some(Migrations {
-            migrations: vec![migration],
-        });
+    let migration = migration.into_migration_config();
+    if migration.is_some() && manifests.len() > 1 {
+        anyhow::bail!("The migration flags of `wrangler publish` apply to a single worker; choose one with `--worker <name>`.")
     }
 
     let output = if output.as_deref() == Some("json") {
@@ -41,6 +44,40 @@ pub fn publish(
     } else {
         Output::PlainText
     };
-    let deploy_config = manifest.get_deployments(cli_params.environment.as_deref())?;
-    commands::publish(&user, &mut target, deploy_config, output)
+
+    let is_many = manifests.len() > 1;
+    let mut diffs = Diffs::new(output);
+    let mut published = Vec::new();
+    for manifest in manifests {
+        let mut target = manifest.get_target(env, false)?;
+
+        if let Some(migration) = migration.clone() {
+            if target.migrations.is_some() {
+                anyhow::bail!("The migration flags of `wrangler publish` cannot be used when [[migrations]] are configured in your configuration file; add a new [[migrations]] entry instead.")
+            }
+            target.migrations = Some(Migrations::Adhoc(migration));
+        }
+
+        if is_many && output == Output::PlainText {
+            StdOut::info(&format!("Publishing {}", styles::highlight(&target.name)));
+        }
+        if dry_run {
+            commands::publish::dry_run(&user, &target, &mut diffs)?;
+            continue;
+        }
+        let deploy_config = manifest.get_deployments(env)?;
+        published.push(commands::publish(&user, &mut target, deploy_config)?);
+    }
+
+    if dry_run {
+        diffs.finish()?;
+    } else if output == Output::Json {
+        // one JSON document however many workers were published: an array of them when
+        // there are several, and the single worker's object as ever otherwise
+        match published.as_slice() {
+            [worker] => StdOut::as_json(worker),
+            workers => StdOut::as_json(workers),
+        }
+    }
+    Ok(())
 }
@@ -1,6 +1,8 @@
-use super::Cli;
+use super::{Cli, WorkerSelection};
 use crate::commands;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
 
 use anyhow::Result;
 use structopt::StructOpt;
@@ -11,14 +13,23 @@ pub enum Secret {
     Put {
         #[structopt(long, short = "n", index = 1)]
         name: String,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
     },
     /// Delete a secret variable from a script
     Delete {
         #[structopt(long, short = "n", index = 1)]
         name: String,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
     },
     /// List all secrets for a script
-    List,
+    List {
+        #[structopt(flatten)]
+        workers: WorkerSelection,
+    },
 }
 
 pub fn secret(secret: Secret, cli_params: &Cli) -> Result<()> {
@@ -27,10 +38,29 @@ pub fn secret(secret: Secret, cli_params: &Cli) -> Result<()> {
 
     log::info!("Getting project settings");
     let manifest = Manifest::new(&cli_params.config)?;
-    let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
-    match secret {
-        Secret::Put { name } => commands::secret::create_secret(&name, &user, &target),
-        Secret::Delete { name } => commands::secret::delete_secret(&name, &user, &target),
-        Secret::List => commands::secret::list_secrets(&user, &target),
+    let env = cli_params.environment.as_deref();
+    let workers = match &secret {
+        Secret::Put { workers, .. } | Secret::Delete { workers, .. } | Secret::List { workers } => {
+            workers
+        }
+    };
+    let manifests = workers.select(manifest, env)?;
+
+    let is_many = manifests.len() > 1;
+    for manifest in manifests {
+        let target = manifest.get_target(env, false)?;
+        match &secret {
+            Secret::Put { name, .. } => commands::secret::create_secret(name, &user, &target)?,
+            Secret::Delete { name, .. } => commands::secret::delete_secret(name, &user, &target)?,
+            Secret::List { .. } => {
+                // the listing itself doesn't say which worker it's for
+                if is_many {
+                    StdOut::info(&format!("Secrets for {}", styles::highlight(&target.name)));
+                }
+                commands::secret::list_secrets(&user, &target)?
+            }
+        }
     }
+
+    Ok(())
 }
@@ -0,0 +1,52 @@
+use super::{Cli, WorkerSelection};
+use crate::commands::sites::Diffs;
+use crate::settings::{global_user::GlobalUser, toml::Manifest};
+use crate::terminal::message::{Message, Output, StdOut};
+use crate::terminal::styles;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum Sites {
+    /// Show which files of your Workers Site publishing would add, change and remove, without
+    /// publishing. Fails when publishing wouldn't change any of them
+    Diff {
+        /// Print the diff as JSON
+        #[structopt(long, possible_value = "json")]
+        output: Option<String>,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
+    },
+}
+
+pub fn sites(sites: Sites, cli_params: &Cli) -> Result<()> {
+    let user = GlobalUser::new()?;
+    let manifest = Manifest::new(&cli_params.config)?;
+    let env = cli_params.environment.as_deref();
+
+    match sites {
+        Sites::Diff { output, workers } => {
+            let out = if output.as_deref() == Some("json") {
+                Output::Json
+            } else {
+                Output::PlainText
+            };
+            let manifests = workers.select(manifest, env)?;
+            let is_many = manifests.len() > 1;
+
+            let mut diffs = Diffs::new(out);
+            for manifest in manifests {
+                let target = manifest.get_target(env, false)?;
+                if is_many && out == Output::PlainText {
+                    StdOut::info(&format!("Site of {}", styles::highlight(&target.name)));
+                }
+                diffs.diff(&target, &user)?;
+            }
+            diffs.finish()?;
+            Ok(())
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::Cli;
+use super::{Cli, WorkerSelection};
 use crate::commands;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 
@@ -8,9 +8,11 @@ pub fn tail(
     format: String,
     tunnel_port: Option<u16>,
     metrics_port: Option<u16>,
+    workers: WorkerSelection,
     cli_params: &Cli,
 ) -> Result<()> {
     let manifest = Manifest::new(&cli_params.config)?;
+    let manifest = workers.select_one(manifest, cli_params.environment.as_deref(), "tail")?;
     let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
     let user = GlobalUser::new()?;
 
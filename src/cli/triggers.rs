@@ -0,0 +1,52 @@
+use super::{Cli, WorkerSelection};
+use crate::commands;
+use crate::settings::toml::Manifest;
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum Triggers {
+    /// Print the next times each cron in [triggers] fires at, in UTC
+    Preview {
+        /// How many times to print for each cron
+        #[structopt(long, short = "n", default_value = "5")]
+        count: usize,
+
+        #[structopt(flatten)]
+        workers: WorkerSelection,
+    },
+}
+
+pub fn triggers(triggers: Triggers, cli_params: &Cli) -> Result<()> {
+    let manifest = Manifest::new(&cli_params.config)?;
+    let env = cli_params.environment.as_deref();
+
+    match triggers {
+        Triggers::Preview { count, workers } => {
+            let manifests = workers.select(manifest, env)?;
+            let is_many = manifests.len() > 1;
+            let now = chrono::Utc::now();
+
+            for manifest in manifests {
+                if is_many {
+                    StdOut::info(&format!(
+                        "Triggers for {}",
+                        styles::highlight(manifest.worker_name(env))
+                    ));
+                }
+                match manifest.get_triggers(env)? {
+                    Some((triggers, field)) => {
+                        commands::triggers::preview(&triggers.crons, &field, count, now)?
+                    }
+                    None => StdOut::info("There are no [triggers] in your configuration file."),
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
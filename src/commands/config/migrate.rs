@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::settings::toml::{deprecations, ConfigFormat};
+use crate::terminal::message::{Message, StdErr, StdOut};
+use crate::terminal::unified_diff;
+
+pub fn migrate(config_path: &Path, dry_run: bool) -> Result<()> {
+    let file_name = config_path.display();
+    if ConfigFormat::from_path(config_path) != ConfigFormat::Toml {
+        anyhow::bail!("`wrangler config migrate` only rewrites TOML configuration files")
+    }
+
+    let source = fs::read_to_string(config_path)
+        .map_err(|e| anyhow!("Could not read {}: {}", file_name, e))?;
+
+    let rewrite = deprecations::rewrite(&source)?;
+
+    for conflict in &rewrite.conflicts {
+        StdErr::warn(conflict);
+    }
+
+    if rewrite.changes.is_empty() {
+        StdOut::info(&format!("{} is already up to date.", file_name));
+        return Ok(());
+    }
+
+    for change in &rewrite.changes {
+        StdOut::info(change);
+    }
+
+    let old_label = format!("a/{}", file_name);
+    let new_label = format!("b/{}", file_name);
+    print!(
+        "{}",
+        unified_diff(&source, &rewrite.toml, &old_label, &new_label)
+    );
+
+    if dry_run {
+        StdOut::info("This was a dry run; no changes were written.");
+        return Ok(());
+    }
+
+    fs::write(config_path, &rewrite.toml)
+        .map_err(|e| anyhow!("Could not write {}: {}", file_name, e))?;
+    StdOut::success(&format!("Updated {}", file_name));
+
+    Ok(())
+}
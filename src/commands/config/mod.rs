@@ -1,3 +1,11 @@
+mod migrate;
+mod show;
+mod validate;
+
+pub use migrate::migrate;
+pub use show::{show, show_resolved};
+pub use validate::validate;
+
 #[cfg(not(target_os = "windows"))]
 use std::fs::File;
 #[cfg(not(target_os = "windows"))]
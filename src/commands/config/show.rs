@@ -0,0 +1,330 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+
+use anyhow::Result;
+use serde::Serialize;
+use toml::value::{Table, Value};
+
+use crate::deploy::{DeployTarget, DeploymentSet};
+use crate::settings::binding::Binding;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::{provenance, Manifest, Source, Target};
+use crate::terminal::message::Output;
+use crate::upload;
+
+const REDACTED: &str = "<redacted>";
+
+// vars with any of these in their name are treated as secrets
+const SENSITIVE_NAMES: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "KEY",
+    "AUTH",
+    "CREDENTIAL",
+    "PRIVATE",
+];
+
+#[derive(Serialize)]
+struct Resolved {
+    target: Target,
+    deployments: DeploymentSet,
+    bindings: Vec<Binding>,
+    provenance: BTreeMap<String, String>,
+}
+
+/// Prints a configuration file as wrangler reads it, after `${VAR}`s and `extends` are
+/// resolved. Values that look like secrets are redacted.
+pub fn show(manifest: &Manifest, output: Output) -> Result<()> {
+    let mut manifest = manifest.clone();
+    redact_manifest(&mut manifest, &credentials());
+    match output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(&manifest)?),
+        Output::PlainText => print!("{}", to_toml(&manifest)?),
+    }
+    Ok(())
+}
+
+/// Prints the `Target` an environment resolves to, the deployments and bindings that
+/// `wrangler publish` would send for it, and where each setting came from. Values that
+/// look like secrets are redacted.
+pub fn show_resolved(
+    manifest: &Manifest,
+    environment: Option<&str>,
+    preview: bool,
+    output: Output,
+) -> Result<()> {
+    let mut target = manifest.get_target(environment, preview)?;
+    let deployments = manifest.get_deployments(environment)?;
+    let mut bindings = upload::form::bindings(&target)?;
+    let sources = provenance(manifest, environment)?;
+
+    let credentials = credentials();
+    redact_vars(&mut target.vars, &credentials);
+    for binding in &mut bindings {
+        match binding {
+            Binding::PlainText { name, text } if is_sensitive(name, text, &credentials) => {
+                *text = REDACTED.to_string();
+            }
+            Binding::Json { name, json } if is_sensitive(name, &json.to_string(), &credentials) => {
+                *json = REDACTED.into();
+            }
+            _ => {}
+        }
+    }
+
+    let deployment_sources: Vec<Option<&Source>> = deployments
+        .iter()
+        .map(|deployment| match deployment {
+            DeployTarget::Zoned(_) | DeployTarget::Zoneless(_) => sources.get("routes"),
+            DeployTarget::Schedule(_) => sources.get("triggers"),
+        })
+        .collect();
+    let binding_sources: Vec<Option<&Source>> = bindings
+        .iter()
+        .map(|binding| sources.get(&binding_source_key(binding)))
+        .collect();
+
+    match output {
+        Output::Json => {
+            let mut provenance: BTreeMap<String, String> = sources
+                .iter()
+                .filter(|(key, _)| key.as_str() != "routes" && key.as_str() != "triggers")
+                .map(|(key, source)| (format!("target.{}", key), source.to_string()))
+                .collect();
+            for (entries, key) in &[
+                (&deployment_sources, "deployments"),
+                (&binding_sources, "bindings"),
+            ] {
+                for (index, source) in entries.iter().enumerate() {
+                    if let Some(source) = source {
+                        provenance.insert(format!("{}[{}]", key, index), source.to_string());
+                    }
+                }
+            }
+
+            let resolved = Resolved {
+                target,
+                deployments,
+                bindings,
+                provenance,
+            };
+            println!("{}", serde_json::to_string_pretty(&resolved)?);
+        }
+        Output::PlainText => {
+            print!(
+                "{}",
+                annotate_fields(&section("target", &target)?, &sources)
+            );
+            print!(
+                "\n{}",
+                annotate_entries(&section("deployments", &deployments)?, &deployment_sources)
+            );
+            print!(
+                "\n{}",
+                annotate_entries(&section("bindings", &bindings)?, &binding_sources)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// going through `toml::Value` puts plain values ahead of tables, which serializing the
+// structs directly doesn't
+fn to_toml<T: Serialize>(value: &T) -> Result<String> {
+    Ok(toml::to_string(&Value::try_from(value)?)?)
+}
+
+fn section<T: Serialize>(name: &str, value: &T) -> Result<String> {
+    let mut table = Table::new();
+    table.insert(name.to_string(), Value::try_from(value)?);
+    to_toml(&table)
+}
+
+fn binding_source_key(binding: &Binding) -> String {
+    match binding {
+        Binding::WasmModule { name, .. } => format!("wasm_modules.{}", name),
+        Binding::KvNamespace { .. } => "kv_namespaces".to_string(),
+        Binding::DurableObjectsClass { .. } => "durable_objects".to_string(),
+        // the manifests of a Workers Site
+        Binding::TextBlob { name, .. } if name.starts_with("__STATIC_CONTENT_") => {
+            "site".to_string()
+        }
+        Binding::TextBlob { name, .. } => format!("text_blobs.{}", name),
+        Binding::PlainText { name, .. } | Binding::Json { name, .. } => {
+            format!("vars.{}", name)
+        }
+    }
+}
+
+// Adds the source of each field of the `[target]` section as a comment. Tables are annotated
+// on their header; the entries of `vars`, `text_blobs` and `wasm_modules` on their own lines.
+fn annotate_fields(toml: &str, sources: &BTreeMap<String, Source>) -> String {
+    let mut output = String::new();
+    let mut table = String::new();
+
+    for line in toml.lines() {
+        let trimmed = line.trim();
+        let path = if trimmed.starts_with('[') {
+            table = trimmed
+                .trim_matches(|c| c == '[' || c == ']')
+                .trim_start_matches("target")
+                .trim_start_matches('.')
+                .to_string();
+            Some(table.clone())
+        } else {
+            trimmed.split_once(" = ").map(|(key, _)| {
+                let key = key.trim_matches('"');
+                if table.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", table, key)
+                }
+            })
+        };
+
+        output.push_str(line);
+        if let Some(source) = path.and_then(|path| sources.get(&path)) {
+            output.push_str(&format!(" # {}", source));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+// Adds a source to the header of each entry in an array of tables, in order.
+fn annotate_entries(toml: &str, sources: &[Option<&Source>]) -> String {
+    let mut output = String::new();
+    let mut sources = sources.iter();
+
+    for line in toml.lines() {
+        output.push_str(line);
+        let is_entry = line.starts_with("[[") && !line.contains('.');
+        if is_entry {
+            if let Some(Some(source)) = sources.next() {
+                output.push_str(&format!(" # {}", source));
+            }
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn credentials() -> Vec<String> {
+    let mut credentials: Vec<String> = ["CF_API_TOKEN", "CF_API_KEY"]
+        .iter()
+        .filter_map(|variable| env::var(variable).ok())
+        .collect();
+    match GlobalUser::new() {
+        Ok(GlobalUser::TokenAuth { api_token }) => credentials.push(api_token),
+        Ok(GlobalUser::GlobalKeyAuth { api_key, .. }) => credentials.push(api_key),
+        Err(_) => {}
+    }
+    credentials.retain(|credential| !credential.is_empty());
+    credentials
+}
+
+// Redacts the vars of the top level, of each worker and of each of their environments
+fn redact_manifest(manifest: &mut Manifest, credentials: &[String]) {
+    redact_vars(&mut manifest.vars, credentials);
+    for environment in manifest.env.iter_mut().flat_map(HashMap::values_mut) {
+        redact_vars(&mut environment.vars, credentials);
+    }
+    for worker in manifest.workers.iter_mut().flatten() {
+        redact_vars(&mut worker.vars, credentials);
+        for environment in worker.env.iter_mut().flat_map(HashMap::values_mut) {
+            redact_vars(&mut environment.vars, credentials);
+        }
+    }
+}
+
+fn redact_vars(vars: &mut Option<HashMap<String, serde_json::Value>>, credentials: &[String]) {
+    for (name, value) in vars.iter_mut().flatten() {
+        if is_sensitive(name, &value.to_string(), credentials) {
+            *value = REDACTED.into();
+        }
+    }
+}
+
+fn is_sensitive(name: &str, value: &str, credentials: &[String]) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SENSITIVE_NAMES.iter().any(|word| upper.contains(word))
+        || credentials
+            .iter()
+            .any(|credential| value.contains(credential.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_redacts_secrets() {
+        let credentials = vec!["abc123".to_string()];
+
+        assert!(is_sensitive("API_TOKEN", "hunter2", &credentials));
+        assert!(is_sensitive("signing_key", "hunter2", &credentials));
+        assert!(is_sensitive("HEADER", "Bearer abc123", &credentials));
+        assert!(is_sensitive("AUTH", "{\"user\":\"me\"}", &credentials));
+        assert!(!is_sensitive("GREETING", "hello", &credentials));
+    }
+
+    #[test]
+    fn it_redacts_the_vars_of_every_environment_and_worker() {
+        let mut manifest: Manifest = toml::from_str(
+            r#"
+            type = "javascript"
+            vars = { API_TOKEN = "hunter2", GREETING = "hello" }
+
+            [env.staging]
+            vars = { PASSWORD = "hunter2" }
+
+            [[workers]]
+            name = "api"
+            vars = { HEADER = "Bearer abc123" }
+            "#,
+        )
+        .unwrap();
+        redact_manifest(&mut manifest, &["abc123".to_string()]);
+
+        let vars = manifest.vars.as_ref().unwrap();
+        assert_eq!(vars["API_TOKEN"], REDACTED);
+        assert_eq!(vars["GREETING"], "hello");
+        let staging = &manifest.env.as_ref().unwrap()["staging"];
+        assert_eq!(staging.vars.as_ref().unwrap()["PASSWORD"], REDACTED);
+        let worker = &manifest.workers.as_ref().unwrap()[0];
+        assert_eq!(worker.vars.as_ref().unwrap()["HEADER"], REDACTED);
+    }
+
+    #[test]
+    fn it_annotates_fields_with_their_source() {
+        let mut sources = BTreeMap::new();
+        sources.insert(
+            "name".to_string(),
+            Source::Environment("staging".to_string()),
+        );
+        sources.insert("vars.FOO".to_string(), Source::TopLevel);
+        sources.insert("build".to_string(), Source::Default);
+
+        let toml = "[target]\nname = \"worker-staging\"\n\n[target.build]\ncommand = \"make\"\n\n[target.vars]\nFOO = \"bar\"\n";
+        assert_eq!(
+            annotate_fields(toml, &sources),
+            "[target]\nname = \"worker-staging\" # env.staging\n\n[target.build] # default\ncommand = \"make\"\n\n[target.vars]\nFOO = \"bar\" # top-level\n"
+        );
+    }
+
+    #[test]
+    fn it_annotates_each_entry() {
+        let top_level = Source::TopLevel;
+        let sources = vec![Some(&top_level), None];
+        let toml = "[[bindings]]\ntype = \"kv_namespace\"\n\n[[bindings]]\ntype = \"text_blob\"\n";
+
+        assert_eq!(
+            annotate_entries(toml, &sources),
+            "[[bindings]] # top-level\ntype = \"kv_namespace\"\n\n[[bindings]]\ntype = \"text_blob\"\n"
+        );
+    }
+}
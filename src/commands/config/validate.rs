@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::settings::toml::ConfigFormat;
+use crate::terminal::message::{Message, StdErr, StdOut};
+
+pub fn validate(config_path: &Path) -> Result<()> {
+    let file_name = config_path.display();
+    let source = fs::read_to_string(config_path)
+        .map_err(|e| anyhow!("Could not read {}: {}", file_name, e))?;
+
+    let format = ConfigFormat::from_path(config_path);
+    let source = format
+        .to_toml(&source)
+        .map_err(|e| anyhow!("Could not parse {}: {}", file_name, e))?;
+
+    let diagnostics = crate::settings::toml::validate(&source);
+    if diagnostics.is_empty() {
+        StdOut::success(&format!("{} is valid.", file_name));
+        return Ok(());
+    }
+
+    for diagnostic in &diagnostics {
+        match format {
+            ConfigFormat::Toml => StdErr::user_error(&format!("{}:{}", file_name, diagnostic)),
+            // positions are only known for the TOML source
+            _ => StdErr::user_error(&format!(
+                "{}: {}: {}",
+                file_name, diagnostic.path, diagnostic.message
+            )),
+        }
+    }
+
+    anyhow::bail!(
+        "Found {} problem{} in {}",
+        diagnostics.len(),
+        if diagnostics.len() == 1 { "" } else { "s" },
+        file_name
+    )
+}
@@ -1,6 +1,6 @@
 use super::preview_request;
 use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
-use crate::commands::dev::{Protocol, ServerConfig};
+use crate::commands::dev::{scheduled, Protocol, ServerConfig};
 use crate::terminal::emoji;
 
 use std::sync::{Arc, Mutex};
@@ -45,7 +45,17 @@ pub async fn http(
                 let req_method = parts.method.to_string();
                 let now: DateTime<Local> = Local::now();
                 let path = get_path_as_str(&parts.uri);
+
+                let rejection = if server_config.test_scheduled {
+                    scheduled::check_request(&parts.uri)
+                } else {
+                    None
+                };
                 async move {
+                    if let Some(resp) = rejection {
+                        return Ok(resp);
+                    }
+
                     let mut resp = preview_request(
                         Request::from_parts(parts, body),
                         client,
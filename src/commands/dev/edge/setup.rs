@@ -22,23 +22,28 @@ pub(super) fn upload(
 ) -> Result<String> {
     let client = crate::http::legacy_auth_client(&user);
 
-    let (to_delete, asset_manifest, site_namespace_id) = if let Some(site_config) =
-        target.site.clone()
-    {
-        let site_namespace = add_namespace(user, target, true)?;
-        let path = Path::new(&site_config.bucket);
-        let (to_upload, to_delete, asset_manifest) = sync(target, user, &site_namespace.id, path)?;
-
-        // First, upload all existing files in given directory
-        if verbose {
-            StdOut::info("Uploading updated files...");
-        }
+    let (to_delete, asset_manifest, site_namespace_id, synced_keys) =
+        if let Some(site_config) = target.site.clone() {
+            let site_namespace = add_namespace(user, target, true)?;
+            let path = Path::new(&site_config.bucket);
+            let (to_upload, to_delete, asset_manifest, synced_keys) =
+                sync(target, user, &site_namespace.id, path)?;
+
+            // First, upload all existing files in given directory
+            if verbose {
+                StdOut::info("Uploading updated files...");
+            }
 
-        bulk::put(target, user, &site_namespace.id, to_upload, &None)?;
-        (to_delete, Some(asset_manifest), Some(site_namespace.id))
-    } else {
-        (Vec::new(), None, None)
-    };
+            bulk::put(target, user, &site_namespace.id, to_upload, &None)?;
+            (
+                to_delete,
+                Some(asset_manifest),
+                Some(site_namespace.id),
+                Some(synced_keys),
+            )
+        } else {
+            (Vec::new(), None, None, None)
+        };
 
     let session_config = get_session_config(deploy_target);
     let address = get_upload_address(target)?;
@@ -59,6 +64,9 @@ pub(super) fn upload(
 
         bulk::delete(target, user, &site_namespace_id.unwrap(), to_delete, &None)?;
     }
+    if let Some(synced_keys) = synced_keys {
+        synced_keys.remember();
+    }
 
     let text = &response.text()?;
 
@@ -1,5 +1,6 @@
 use super::preview_request;
 use crate::commands::dev::gcs::headers::destructure_response;
+use crate::commands::dev::scheduled;
 use crate::commands::dev::server_config::ServerConfig;
 use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
 use crate::terminal::emoji;
@@ -53,7 +54,17 @@ pub async fn http(server_config: ServerConfig, preview_id: Arc<Mutex<String>>) -
                 // we don't want to send "localhost:8787/path", just "/path"
                 let path = get_path_as_str(&parts.uri);
 
+                let rejection = if server_config.test_scheduled {
+                    scheduled::check_request(&parts.uri)
+                } else {
+                    None
+                };
+
                 async move {
+                    if let Some(resp) = rejection {
+                        return Ok(resp);
+                    }
+
                     // send the request to the preview service
                     let resp = preview_request(
                         Request::from_parts(parts, body),
@@ -0,0 +1,184 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::kv::NAMESPACES_PATH;
+use crate::settings::toml::Target;
+use crate::sites::AssetManifest;
+use crate::upload::form::{
+    self, modules_entry, Assets, ModuleType, Var, MODULES_ENTRY, SERVICE_WORKER_PRELUDE,
+};
+
+/// What a `LocalRuntime` is told about the Worker it serves.
+///
+/// Bindings point at whatever backs them on disk: text blobs and wasm modules are files,
+/// and KV namespaces are served over HTTP at their `url`, with the same routes, query
+/// parameters and responses as the Cloudflare API.
+#[derive(Debug, Serialize)]
+pub struct RuntimeConfig {
+    /// Where the runtime should listen for the requests wrangler forwards to it
+    address: SocketAddr,
+    #[serde(flatten)]
+    script: Script,
+    bindings: Vec<LocalBinding>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "format", rename_all = "kebab-case")]
+enum Script {
+    ServiceWorker { path: PathBuf },
+    Modules { main: String, modules: Vec<Module> },
+}
+
+#[derive(Debug, Serialize)]
+struct Module {
+    name: String,
+    path: PathBuf,
+    #[serde(rename = "type")]
+    module_type: ModuleType,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LocalBinding {
+    WasmModule {
+        name: String,
+        path: PathBuf,
+    },
+    KvNamespace {
+        name: String,
+        namespace_id: String,
+        url: String,
+    },
+    TextBlob {
+        name: String,
+        path: PathBuf,
+    },
+    PlainText {
+        name: String,
+        text: String,
+    },
+    Json {
+        name: String,
+        json: serde_json::Value,
+    },
+}
+
+impl RuntimeConfig {
+    /// Describes a built target, writing whatever only exists in memory (text blobs, and
+    /// the wrapped script for `--test-scheduled`) into `state_dir`.
+    pub fn new(
+        target: &Target,
+        asset_manifest: Option<AssetManifest>,
+        address: SocketAddr,
+        kv_address: SocketAddr,
+        state_dir: &Path,
+    ) -> Result<Self> {
+        let scripts_dir = state_dir.join("scripts");
+        let text_blobs_dir = state_dir.join("text_blobs");
+        fs::create_dir_all(&scripts_dir)?;
+        fs::create_dir_all(&text_blobs_dir)?;
+
+        let mut bindings = Vec::new();
+        // local namespaces are kept by binding rather than by id
+        let kv_binding = |name: String, namespace_id: String| {
+            let url = format!("http://{}{}{}", kv_address, NAMESPACES_PATH, name);
+            LocalBinding::KvNamespace {
+                name,
+                namespace_id,
+                url,
+            }
+        };
+
+        let (script, vars) = match form::assets(target, asset_manifest)? {
+            Assets::ServiceWorker(assets) => {
+                for wasm_module in &assets.wasm_modules {
+                    bindings.push(LocalBinding::WasmModule {
+                        name: wasm_module.binding_name(),
+                        path: fs::canonicalize(wasm_module.path())?,
+                    });
+                }
+                for namespace in &assets.kv_namespaces {
+                    bindings.push(kv_binding(namespace.binding.clone(), namespace.id.clone()));
+                }
+                for text_blob in &assets.text_blobs {
+                    let path = text_blobs_dir.join(&text_blob.binding);
+                    fs::write(&path, &text_blob.data)?;
+                    bindings.push(LocalBinding::TextBlob {
+                        name: text_blob.binding.clone(),
+                        path,
+                    });
+                }
+
+                let mut path = fs::canonicalize(assets.script_path())?;
+                if target.test_scheduled {
+                    let script = fs::read_to_string(&path)?;
+                    path = scripts_dir.join(format!("{}.js", assets.script_name()));
+                    fs::write(&path, format!("{}{}", SERVICE_WORKER_PRELUDE, script))?;
+                }
+
+                (Script::ServiceWorker { path }, assets.vars)
+            }
+            Assets::Modules(assets) => {
+                for namespace in &assets.kv_namespaces {
+                    bindings.push(kv_binding(namespace.binding.clone(), namespace.id.clone()));
+                }
+
+                let mut main = assets.manifest.main.clone();
+                let mut modules = assets
+                    .manifest
+                    .modules
+                    .iter()
+                    .map(|(name, module)| {
+                        Ok(Module {
+                            name: name.clone(),
+                            path: fs::canonicalize(&module.path)?,
+                            module_type: module.module_type,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                modules.sort_by(|a, b| a.name.cmp(&b.name));
+
+                if target.test_scheduled {
+                    let path = scripts_dir.join(MODULES_ENTRY);
+                    fs::write(&path, modules_entry(&main))?;
+                    modules.push(Module {
+                        name: MODULES_ENTRY.to_string(),
+                        path,
+                        module_type: ModuleType::ESModule,
+                    });
+                    main = MODULES_ENTRY.to_string();
+                }
+
+                (Script::Modules { main, modules }, assets.vars)
+            }
+        };
+
+        for var in vars {
+            bindings.push(match var {
+                Var::PlainText(plain_text) => LocalBinding::PlainText {
+                    name: plain_text.name,
+                    text: plain_text.value,
+                },
+                Var::Json(json) => LocalBinding::Json {
+                    name: json.name,
+                    json: json.value,
+                },
+            });
+        }
+
+        Ok(RuntimeConfig {
+            address,
+            script,
+            bindings,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use anyhow::{anyhow, Result};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use percent_encoding::percent_decode_str;
+use serde_json::json;
+use url::form_urlencoded;
+
+use crate::kv::local::{PutOptions, Store};
+
+/// The prefix of the routes `serve` answers, the same as the Cloudflare API's
+pub const NAMESPACES_PATH: &str = "/storage/kv/namespaces/";
+
+enum Route {
+    Value { namespace: String, key: String },
+    Metadata { namespace: String, key: String },
+    Keys { namespace: String },
+}
+
+fn route(path: &str) -> Option<Route> {
+    let (namespace, rest) = path.strip_prefix(NAMESPACES_PATH)?.split_once('/')?;
+    let namespace = namespace.to_string();
+    if rest == "keys" {
+        return Some(Route::Keys { namespace });
+    }
+
+    let (kind, key) = rest.split_once('/')?;
+    let key = percent_decode_str(key).decode_utf8().ok()?.to_string();
+    match kind {
+        "values" => Some(Route::Value { namespace, key }),
+        "metadata" => Some(Route::Metadata { namespace, key }),
+        _ => None,
+    }
+}
+
+/// Serves the namespaces in `store` over HTTP, with the routes the Cloudflare API has for
+/// reading, writing, deleting and listing keys
+pub async fn serve(store: Store, listening_address: SocketAddr) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let store = store.to_owned();
+        async move {
+            Ok::<_, anyhow::Error>(service_fn(move |req| {
+                let store = store.to_owned();
+                async move {
+                    let resp = match handle(&store, req).await {
+                        Ok(resp) => resp,
+                        Err(e) => error_response(StatusCode::BAD_REQUEST, None, &e.to_string()),
+                    };
+                    Ok::<_, anyhow::Error>(resp)
+                }
+            }))
+        }
+    });
+
+    let server = Server::bind(&listening_address).serve(make_service);
+    if let Err(e) = server.await {
+        eprintln!("kv server error: {}", e);
+    }
+    Ok(())
+}
+
+async fn handle(store: &Store, req: Request<Body>) -> Result<Response<Body>> {
+    let (parts, body) = req.into_parts();
+    let query: HashMap<String, String> =
+        form_urlencoded::parse(parts.uri.query().unwrap_or("").as_bytes())
+            .into_owned()
+            .collect();
+    let number = |name: &str| -> Result<Option<i64>> {
+        query
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("{} must be a number, not \"{}\"", name, value))
+            })
+            .transpose()
+    };
+
+    let resp = match (parts.method, route(parts.uri.path())) {
+        (Method::GET, Some(Route::Value { namespace, key })) => {
+            match store.get(&namespace, &key)? {
+                Some(value) => Response::new(Body::from(value)),
+                None => key_not_found("get"),
+            }
+        }
+        (Method::GET, Some(Route::Metadata { namespace, key })) => {
+            match store.get_with_key(&namespace, &key)? {
+                Some((_, key)) => success_response(json!(key.metadata), None),
+                None => key_not_found("metadata"),
+            }
+        }
+        (Method::PUT, Some(Route::Value { namespace, key })) => {
+            let content_type = parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+            let body = hyper::body::to_bytes(body).await?;
+
+            // values with metadata are sent as a form, with the value and metadata as parts
+            let (value, metadata) = if content_type.starts_with("multipart/form-data") {
+                let mut parts = form_parts(&content_type, &body)
+                    .ok_or_else(|| anyhow!("Could not parse the form"))?;
+                let value = parts
+                    .remove("value")
+                    .ok_or_else(|| anyhow!("The form has no value"))?;
+                let metadata = match parts.remove("metadata") {
+                    Some(metadata) => Some(serde_json::from_slice(&metadata)?),
+                    None => None,
+                };
+                (value, metadata)
+            } else {
+                (body.to_vec(), None)
+            };
+
+            let options = PutOptions {
+                expiration: number("expiration")?,
+                expiration_ttl: number("expiration_ttl")?,
+                metadata,
+            };
+            store.put(&namespace, &key, &value, options)?;
+            success_response(json!(null), None)
+        }
+        (Method::DELETE, Some(Route::Value { namespace, key })) => {
+            store.delete(&namespace, &key)?;
+            success_response(json!(null), None)
+        }
+        (Method::GET, Some(Route::Keys { namespace })) => {
+            let limit = number("limit")?.map(|limit| limit as usize);
+            let page = store.list(
+                &namespace,
+                query.get("prefix").map(String::as_str),
+                limit,
+                query.get("cursor").map(String::as_str),
+            )?;
+            let result_info = json!({
+                "count": page.keys.len(),
+                "cursor": page.cursor.unwrap_or_default(),
+            });
+            success_response(json!(page.keys), Some(result_info))
+        }
+        _ => error_response(StatusCode::NOT_FOUND, None, "No route for that URI"),
+    };
+
+    Ok(resp)
+}
+
+fn success_response(
+    result: serde_json::Value,
+    result_info: Option<serde_json::Value>,
+) -> Response<Body> {
+    let mut body = json!({ "success": true, "errors": [], "messages": [], "result": result });
+    if let Some(result_info) = result_info {
+        body["result_info"] = result_info;
+    }
+    json_response(StatusCode::OK, body)
+}
+
+fn key_not_found(operation: &str) -> Response<Body> {
+    error_response(
+        StatusCode::NOT_FOUND,
+        Some(10009),
+        &format!("{}: 'key not found'", operation),
+    )
+}
+
+fn error_response(status: StatusCode, code: Option<u16>, message: &str) -> Response<Body> {
+    let body = json!({
+        "success": false,
+        "errors": [{ "code": code.unwrap_or(10000), "message": message }],
+        "messages": [],
+        "result": null,
+    });
+    json_response(status, body)
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    let mut resp = Response::new(Body::from(body.to_string()));
+    *resp.status_mut() = status;
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    resp
+}
+
+// The parts of a multipart/form-data body, by name
+fn form_parts(content_type: &str, body: &[u8]) -> Option<HashMap<String, Vec<u8>>> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary);
+
+    let mut parts = HashMap::new();
+    for part in split(body, delimiter.as_bytes()).into_iter().skip(1) {
+        // the final delimiter is followed by "--"
+        if part.starts_with(b"--") {
+            break;
+        }
+        let part = part.strip_prefix(b"\r\n")?;
+        let headers_end = find(part, b"\r\n\r\n")?;
+        let headers = std::str::from_utf8(&part[..headers_end]).ok()?;
+        let content = &part[headers_end + 4..];
+        let content = content.strip_suffix(b"\r\n").unwrap_or(content);
+
+        let name = headers
+            .lines()
+            .find(|line| {
+                line.to_ascii_lowercase()
+                    .starts_with("content-disposition:")
+            })?
+            .split(';')
+            .find_map(|param| param.trim().strip_prefix("name="))?
+            .trim_matches('"');
+        parts.insert(name.to_string(), content.to_vec());
+    }
+
+    Some(parts)
+}
+
+fn split<'a>(mut haystack: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::new();
+    while let Some(index) = find(haystack, delimiter) {
+        pieces.push(&haystack[..index]);
+        haystack = &haystack[index + delimiter.len()..];
+    }
+    pieces.push(haystack);
+    pieces
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_values_and_metadata_from_forms() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"value\"\r\n\r\nline one\r\nline two\r\n--xyz\r\nContent-Disposition: form-data; name=\"metadata\"\r\n\r\n{\"a\":1}\r\n--xyz--\r\n";
+        let parts = form_parts("multipart/form-data; boundary=xyz", body).unwrap();
+
+        assert_eq!(parts["value"], b"line one\r\nline two".to_vec());
+        assert_eq!(parts["metadata"], b"{\"a\":1}".to_vec());
+    }
+
+    #[test]
+    fn it_routes_like_the_api() {
+        assert!(matches!(
+            route("/storage/kv/namespaces/NS/values/a%2Fb"),
+            Some(Route::Value { namespace, key }) if namespace == "NS" && key == "a/b"
+        ));
+        assert!(matches!(
+            route("/storage/kv/namespaces/NS/metadata/key"),
+            Some(Route::Metadata { .. })
+        ));
+        assert!(matches!(
+            route("/storage/kv/namespaces/NS/keys"),
+            Some(Route::Keys { .. })
+        ));
+        assert!(route("/storage/kv/namespaces/NS/other/key").is_none());
+    }
+}
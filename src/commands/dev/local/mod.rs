@@ -0,0 +1,241 @@
+mod config;
+mod kv;
+mod runtime;
+mod server;
+
+pub use runtime::LocalRuntime;
+
+use config::RuntimeConfig;
+
+use crate::commands::dev::{Protocol, ServerConfig};
+use crate::kv::local::{PutOptions, Store};
+use crate::settings::toml::{KvNamespace, Target};
+use crate::sites::{self, AssetManifest};
+use crate::terminal::message::{Message, StdOut};
+use crate::watch::{wait_for_changes, watch_and_build, COOLDOWN_PERIOD};
+
+use anyhow::Result;
+use notify::{RecursiveMode, Watcher};
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::{Handle, Runtime as TokioRuntime};
+use tokio::sync::RwLock;
+
+// Workers Sites read their files from the namespace bound to this
+const STATIC_CONTENT: &str = "__STATIC_CONTENT";
+
+/// spin up a local server that routes requests to a Workers runtime on this machine,
+/// with the Worker's bindings kept in a directory that lasts as long as the session, or
+/// its KV namespaces in `kv_persist` if given
+pub fn dev(
+    target: Target,
+    server_config: ServerConfig,
+    local_protocol: Protocol,
+    runtime: LocalRuntime,
+    kv_persist: Option<PathBuf>,
+    verbose: bool,
+) -> Result<()> {
+    StdOut::info(&format!("Running locally with `{}`", runtime));
+
+    if target.durable_objects.is_some() {
+        anyhow::bail!("wrangler dev --local does not support Durable Objects yet")
+    }
+
+    let state_dir = tempfile::tempdir()?;
+    // KV data only outlives the session if it's kept in a directory of the user's
+    let store = match kv_persist {
+        Some(dir) => {
+            StdOut::info(&format!("Persisting KV data to {}", dir.display()));
+            Store::new(&dir)?
+        }
+        None => Store::new(&state_dir.path().join("kv"))?,
+    };
+
+    let mut target = target;
+    let asset_manifest = match target.site.clone() {
+        Some(site) => {
+            let asset_manifest = upload_site(&target, &site.bucket, &store)?;
+            target.kv_namespaces.push(KvNamespace {
+                id: STATIC_CONTENT.to_string(),
+                binding: STATIC_CONTENT.to_string(),
+            });
+            Some(asset_manifest)
+        }
+        None => None,
+    };
+
+    let session = Session {
+        target,
+        asset_manifest,
+        store: store.clone(),
+        runtime,
+        state_dir: state_dir.path().to_path_buf(),
+        address: unused_address()?,
+        kv_address: unused_address()?,
+    };
+
+    // in order to spawn futures we must create a tokio runtime
+    let runtime = TokioRuntime::new()?;
+
+    // the local server reads this lock while forwarding a request, and the file watcher
+    // writes it while restarting the runtime. Requests await it rather than blocking the
+    // server's threads, which would stall every other request while the Worker is rebuilt
+    let child = Arc::new(RwLock::new(session.start(verbose)?));
+    let runtime_address = session.address;
+    let kv_address = session.kv_address;
+    {
+        let child = Arc::clone(&child);
+        let handle = runtime.handle().clone();
+        thread::spawn(move || watch_for_changes(session, child, handle, verbose));
+    }
+
+    // and we must block the main thread on the completion of
+    // said futures
+    let res = runtime.block_on(async {
+        let kv_server = tokio::spawn(kv::serve(store, kv_address));
+
+        let server = match local_protocol {
+            Protocol::Https => tokio::spawn(server::https(
+                server_config.clone(),
+                Arc::clone(&child),
+                runtime_address,
+            )),
+            Protocol::Http => tokio::spawn(server::http(
+                server_config.clone(),
+                Arc::clone(&child),
+                runtime_address,
+            )),
+        };
+
+        let res = tokio::try_join!(async { kv_server.await? }, async { server.await? });
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    });
+
+    stop(&mut *runtime.block_on(child.write()))?;
+    res
+}
+
+struct Session {
+    target: Target,
+    asset_manifest: Option<AssetManifest>,
+    store: Store,
+    runtime: LocalRuntime,
+    state_dir: PathBuf,
+    address: SocketAddr,
+    kv_address: SocketAddr,
+}
+
+impl Session {
+    /// Describes the Worker as it's currently built, and starts the runtime on it
+    fn start(&self, verbose: bool) -> Result<Child> {
+        let config = RuntimeConfig::new(
+            &self.target,
+            self.asset_manifest.clone(),
+            self.address,
+            self.kv_address,
+            &self.state_dir,
+        )?;
+        let config_path = self.state_dir.join("runtime.json");
+        config.write(&config_path)?;
+        if verbose {
+            StdOut::info(&format!(
+                "Wrote the runtime config to {}",
+                config_path.display()
+            ));
+        }
+
+        self.runtime.start(&config_path, self.address)
+    }
+
+    /// Puts the files of the site in its namespace again, and updates the manifest of them
+    /// the runtime is started with
+    fn upload_site(&mut self) -> Result<()> {
+        if let Some(site) = &self.target.site {
+            self.asset_manifest = Some(upload_site(&self.target, &site.bucket, &self.store)?);
+        }
+        Ok(())
+    }
+}
+
+fn watch_for_changes(
+    mut session: Session,
+    child: Arc<RwLock<Child>>,
+    handle: Handle,
+    verbose: bool,
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    watch_and_build(&session.target, Some(sender.clone()))?;
+    if let Some(site) = &session.target.site {
+        let bucket = site.bucket.clone();
+        thread::spawn(move || watch_bucket(&bucket, sender));
+    }
+
+    while receiver.recv().is_ok() {
+        // the Worker and its site are often changed together, which only takes one restart
+        while receiver.try_recv().is_ok() {}
+
+        if let Err(e) = session.upload_site() {
+            StdOut::user_error(&format!("{:?}", e));
+        }
+
+        // wait for the requests being forwarded, then halt incoming ones
+        // until the runtime is serving the new script
+        let mut child = handle.block_on(child.write());
+
+        stop(&mut child)?;
+        match session.start(verbose) {
+            Ok(restarted) => *child = restarted,
+            Err(e) => StdOut::user_error(&format!("{:?}", e)),
+        }
+    }
+
+    Ok(())
+}
+
+// Rebuilding the Worker doesn't touch the files of its site, so the bucket is watched too
+fn watch_bucket(bucket: &Path, sender: mpsc::Sender<()>) -> Result<()> {
+    let (watcher_tx, watcher_rx) = mpsc::channel();
+    let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
+    watcher.watch(bucket, RecursiveMode::Recursive)?;
+
+    while wait_for_changes(&watcher_rx, COOLDOWN_PERIOD).is_ok() {
+        // the runtime has stopped for good
+        if sender.send(()).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// the runtime may have exited on its own, in which case there's nothing to stop
+fn stop(child: &mut Child) -> Result<()> {
+    if child.try_wait()?.is_none() {
+        child.kill()?;
+        child.wait()?;
+    }
+    Ok(())
+}
+
+// Puts every file in the bucket into the local `__STATIC_CONTENT` namespace. Keys are named
+// for the contents of their files, so the keys of files that have since changed are only left
+// unused.
+fn upload_site(target: &Target, bucket: &Path, store: &Store) -> Result<AssetManifest> {
+    let (pairs, asset_manifest, _) = sites::directory_keys_values(target, bucket, None)?;
+    for pair in pairs {
+        let value = base64::decode(&pair.value)?;
+        store.put(STATIC_CONTENT, &pair.key, &value, PutOptions::default())?;
+    }
+    Ok(asset_manifest)
+}
+
+// the runtime and the KV server each get a port of their own
+fn unused_address() -> Result<SocketAddr> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?)
+}
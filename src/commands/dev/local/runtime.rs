@@ -0,0 +1,96 @@
+use std::env;
+use std::fmt;
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+use crate::terminal::styles;
+
+/// Overrides `local_runtime` under `[dev]`
+const RUNTIME_VAR: &str = "WRANGLER_LOCAL_RUNTIME";
+
+// how long a runtime gets to start listening before we give up on it
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The command `wrangler dev --local` runs a Worker with.
+///
+/// It's run from the project directory, with the path to a JSON file describing the Worker
+/// (see `RuntimeConfig`) as its last argument, and restarted whenever the Worker is
+/// rebuilt. It should serve the Worker on the `address` in that file.
+#[derive(Clone, Debug)]
+pub struct LocalRuntime {
+    program: String,
+    args: Vec<String>,
+}
+
+impl LocalRuntime {
+    pub fn new(command: &str) -> Result<Self> {
+        let mut words = command.split_whitespace().map(|word| word.to_string());
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow!("The local runtime command is empty"))?;
+
+        Ok(LocalRuntime {
+            program,
+            args: words.collect(),
+        })
+    }
+
+    /// The runtime in `WRANGLER_LOCAL_RUNTIME`, or else the one configured in `[dev]`
+    pub fn configured(local_runtime: Option<String>) -> Result<Self> {
+        match env::var(RUNTIME_VAR).ok().or(local_runtime) {
+            Some(command) => LocalRuntime::new(&command),
+            None => anyhow::bail!(
+                "{} needs a runtime to run your Worker with. Set one with `local_runtime` under [dev] in your configuration file, or with {}",
+                styles::highlight("wrangler dev --local"),
+                RUNTIME_VAR
+            ),
+        }
+    }
+
+    /// Starts the runtime, returning once it accepts connections on `address`
+    pub fn start(&self, config_path: &Path, address: SocketAddr) -> Result<Child> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(config_path)
+            .spawn()
+            .map_err(|e| anyhow!("Could not start the local runtime `{}`: {}", self, e))?;
+
+        let started = Instant::now();
+        while TcpStream::connect(address).is_err() {
+            if let Some(status) = child.try_wait()? {
+                anyhow::bail!(
+                    "The local runtime `{}` exited before serving your Worker ({})",
+                    self,
+                    status
+                )
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                child.kill()?;
+                anyhow::bail!(
+                    "The local runtime `{}` didn't start listening on {} within {} seconds",
+                    self,
+                    address,
+                    STARTUP_TIMEOUT.as_secs()
+                )
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(child)
+    }
+}
+
+impl fmt::Display for LocalRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
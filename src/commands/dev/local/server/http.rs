@@ -0,0 +1,122 @@
+use super::{runtime_request, unreachable_runtime};
+use crate::commands::dev::scheduled;
+use crate::commands::dev::server_config::ServerConfig;
+use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
+use crate::terminal::emoji;
+
+use std::net::SocketAddr;
+use std::process::Child;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::prelude::*;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client as HyperClient, Request, Server};
+use tokio::sync::RwLock;
+
+/// performs all logic that takes an incoming request
+/// and routes it to the Workers runtime running on this machine
+pub async fn http(
+    server_config: ServerConfig,
+    runtime: Arc<RwLock<Child>>,
+    runtime_address: SocketAddr,
+) -> Result<()> {
+    // set up http client to connect to the local runtime
+    let client = HyperClient::new();
+
+    let listening_address = server_config.listening_address;
+
+    // create a closure that hyper will use later to handle HTTP requests
+    // this takes care of sending an incoming request along to
+    // the local runtime and returning its response
+    let make_service = make_service_fn(move |_| {
+        let client = client.to_owned();
+        let server_config = server_config.to_owned();
+        let runtime = runtime.to_owned();
+        async move {
+            Ok::<_, anyhow::Error>(service_fn(move |req| {
+                let client = client.to_owned();
+                let server_config = server_config.to_owned();
+                let runtime = runtime.to_owned();
+                let version = req.version();
+
+                // record the time of the request
+                let now: DateTime<Local> = Local::now();
+
+                // split the request into parts so we can read
+                // what it contains and display in logs
+                let (parts, body) = req.into_parts();
+                let local_host = format!(
+                    "{}:{}",
+                    server_config.listening_address.ip().to_string(),
+                    server_config.listening_address.port().to_string()
+                );
+
+                let req_method = parts.method.to_string();
+
+                // parse the path so we can send it to the local runtime
+                // we don't want to send "localhost:8787/path", just "/path"
+                let path = get_path_as_str(&parts.uri);
+
+                let rejection = if server_config.test_scheduled {
+                    scheduled::check_request(&parts.uri)
+                } else {
+                    None
+                };
+
+                async move {
+                    if let Some(resp) = rejection {
+                        return Ok(resp);
+                    }
+
+                    // wait for the runtime to restart if the Worker is being rebuilt,
+                    // and keep it from restarting until it has responded
+                    let _runtime = runtime.read().await;
+
+                    // send the request to the local runtime
+                    let mut resp = match runtime_request(
+                        Request::from_parts(parts, body),
+                        client,
+                        server_config.host.to_string(),
+                        runtime_address,
+                    )
+                    .await
+                    {
+                        Ok(resp) => resp,
+                        Err(e) => unreachable_runtime(e),
+                    };
+                    rewrite_redirect(
+                        &mut resp,
+                        &server_config.host.to_string(),
+                        &local_host,
+                        false,
+                    );
+
+                    // print information about the response
+                    // [2020-04-20 15:25:54] GET example.com/ HTTP/1.1 200 OK
+                    println!(
+                        "[{}] {} {}{} {:?} {}",
+                        now.format("%Y-%m-%d %H:%M:%S"),
+                        req_method,
+                        server_config.host,
+                        path,
+                        version,
+                        resp.status()
+                    );
+                    Ok::<_, anyhow::Error>(resp)
+                }
+            }))
+        }
+    });
+
+    let server = Server::bind(&listening_address).serve(make_service);
+    println!(
+        "{} Listening on http://{}",
+        emoji::EAR,
+        listening_address.to_string()
+    );
+    if let Err(e) = server.await {
+        eprintln!("server error: {}", e);
+    }
+    Ok(())
+}
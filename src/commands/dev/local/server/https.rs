@@ -0,0 +1,162 @@
+use super::{runtime_request, unreachable_runtime};
+use crate::commands::dev::scheduled;
+use crate::commands::dev::server_config::ServerConfig;
+use crate::commands::dev::tls;
+use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
+use crate::terminal::emoji;
+use crate::terminal::message::{Message, StdOut};
+
+use std::net::SocketAddr;
+use std::process::Child;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::prelude::*;
+use futures_util::{FutureExt, StreamExt};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client as HyperClient, Request, Server};
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+
+/// performs all logic that takes an incoming request
+/// and routes it to the Workers runtime running on this machine
+pub async fn https(
+    server_config: ServerConfig,
+    runtime: Arc<RwLock<Child>>,
+    runtime_address: SocketAddr,
+) -> Result<()> {
+    tls::generate_cert()?;
+
+    // set up http client to connect to the local runtime
+    let client = HyperClient::new();
+
+    let listening_address = server_config.listening_address;
+
+    // create a closure that hyper will use later to handle HTTP requests
+    // this takes care of sending an incoming request along to
+    // the local runtime and returning its response
+    let service = make_service_fn(move |_| {
+        let client = client.to_owned();
+        let server_config = server_config.to_owned();
+        let runtime = runtime.to_owned();
+        async move {
+            Ok::<_, anyhow::Error>(service_fn(move |req| {
+                let client = client.to_owned();
+                let server_config = server_config.to_owned();
+                let runtime = runtime.to_owned();
+                let version = req.version();
+
+                // record the time of the request
+                let now: DateTime<Local> = Local::now();
+
+                // split the request into parts so we can read
+                // what it contains and display in logs
+                let (parts, body) = req.into_parts();
+                let local_host = format!(
+                    "{}:{}",
+                    server_config.listening_address.ip().to_string(),
+                    server_config.listening_address.port().to_string()
+                );
+
+                let req_method = parts.method.to_string();
+
+                // parse the path so we can send it to the local runtime
+                // we don't want to send "localhost:8787/path", just "/path"
+                let path = get_path_as_str(&parts.uri);
+
+                let rejection = if server_config.test_scheduled {
+                    scheduled::check_request(&parts.uri)
+                } else {
+                    None
+                };
+
+                async move {
+                    if let Some(resp) = rejection {
+                        return Ok(resp);
+                    }
+
+                    // wait for the runtime to restart if the Worker is being rebuilt,
+                    // and keep it from restarting until it has responded
+                    let _runtime = runtime.read().await;
+
+                    // send the request to the local runtime
+                    let mut resp = match runtime_request(
+                        Request::from_parts(parts, body),
+                        client,
+                        server_config.host.to_string(),
+                        runtime_address,
+                    )
+                    .await
+                    {
+                        Ok(resp) => resp,
+                        Err(e) => unreachable_runtime(e),
+                    };
+                    rewrite_redirect(
+                        &mut resp,
+                        &server_config.host.to_string(),
+                        &local_host,
+                        true,
+                    );
+
+                    // print information about the response
+                    // [2020-04-20 15:25:54] GET example.com/ HTTP/1.1 200 OK
+                    println!(
+                        "[{}] {} {}{} {:?} {}",
+                        now.format("%Y-%m-%d %H:%M:%S"),
+                        req_method,
+                        server_config.host,
+                        path,
+                        version,
+                        resp.status()
+                    );
+                    Ok::<_, anyhow::Error>(resp)
+                }
+            }))
+        }
+    });
+
+    // Create a TCP listener via tokio.
+    let tcp = TcpListener::bind(&listening_address).await?;
+    let tls_acceptor = &tls::get_tls_acceptor()?;
+    let incoming_tls_stream = async {
+        let tcp_stream = match tcp.accept().await {
+            Ok((tcp_stream, _addr)) => Ok(tcp_stream),
+            Err(e) => {
+                eprintln!("Failed to accept client {}", e);
+                Err(e)
+            }
+        };
+
+        match tcp_stream {
+            Ok(stream) => match tls_acceptor.accept(stream).await {
+                Ok(tls_stream) => Ok(tls_stream),
+                Err(e) => {
+                    eprintln!("Client connection error {}", e);
+                    StdOut::info("Make sure to use https and `--insecure` with curl");
+                    Err(e)
+                }
+            },
+            Err(e) => Err(e),
+        }
+    }
+    .into_stream()
+    .boxed();
+
+    let server = Server::builder(tls::HyperAcceptor {
+        acceptor: incoming_tls_stream,
+    })
+    .serve(service);
+    println!(
+        "{} Listening on https://{}",
+        emoji::EAR,
+        listening_address.to_string()
+    );
+
+    StdOut::info("Generated certificate is not verified, browsers will give a warning and curl will require `--insecure`");
+
+    if let Err(e) = server.await {
+        eprintln!("{}", e);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+mod http;
+mod https;
+
+pub use self::http::http;
+pub use self::https::https;
+
+use crate::commands::dev::utils::get_path_as_str;
+
+use std::net::SocketAddr;
+
+use hyper::client::{HttpConnector, ResponseFuture};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Body, Client as HyperClient, Request, Response, StatusCode, Version};
+
+fn runtime_request(
+    req: Request<Body>,
+    client: HyperClient<HttpConnector>,
+    host: String,
+    runtime_address: SocketAddr,
+) -> ResponseFuture {
+    let (mut parts, body) = req.into_parts();
+
+    let path = get_path_as_str(&parts.uri);
+
+    // the Worker sees the host it would be served from
+    parts.headers.insert(
+        HeaderName::from_static("host"),
+        HeaderValue::from_str(&host).expect("Could not create host header"),
+    );
+
+    parts.version = Version::HTTP_11;
+    parts.uri = format!("http://{}{}", runtime_address, path)
+        .parse()
+        .expect("Could not construct local runtime url");
+
+    let req = Request::from_parts(parts, body);
+
+    client.request(req)
+}
+
+fn unreachable_runtime(error: hyper::Error) -> Response<Body> {
+    let mut resp = Response::new(Body::from(format!(
+        "wrangler could not reach the local runtime: {}",
+        error
+    )));
+    *resp.status_mut() = StatusCode::BAD_GATEWAY;
+    resp
+}
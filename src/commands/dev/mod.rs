@@ -1,18 +1,24 @@
 mod edge;
 mod gcs;
+mod local;
+mod scheduled;
 mod server_config;
 mod socket;
 mod tls;
 mod utils;
 
+pub use local::LocalRuntime;
 pub use server_config::Protocol;
 pub use server_config::ServerConfig;
 
+use std::path::PathBuf;
+use std::thread;
+
 use crate::build::build_target;
-use crate::deploy::{DeployTarget, DeploymentSet};
+use crate::deploy::{DeployTarget, DeploymentSet, ZonelessTarget};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
-use crate::terminal::message::{Message, StdOut};
+use crate::terminal::message::{Message, StdErr, StdOut};
 use crate::terminal::styles;
 
 use anyhow::Result;
@@ -31,25 +37,41 @@ pub fn dev(
     // before serving requests we must first build the Worker
     build_target(&target)?;
 
-    let deploy_target = {
-        let valid_targets = deployments
-            .into_iter()
-            .filter(|t| matches!(t, DeployTarget::Zoned(_) | DeployTarget::Zoneless(_)))
-            .collect::<Vec<_>>();
+    let crons: Vec<String> = deployments
+        .iter()
+        .filter_map(|t| match t {
+            DeployTarget::Schedule(schedule) => Some(schedule.crons.clone()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
 
-        let valid_target = valid_targets
+    let deploy_target = {
+        let valid_target = deployments
             .iter()
-            .find(|&t| matches!(t, DeployTarget::Zoned(_)))
+            .find(|t| matches!(t, DeployTarget::Zoned(_)))
             .or_else(|| {
-                valid_targets
+                deployments
                     .iter()
-                    .find(|&t| matches!(t, DeployTarget::Zoneless(_)))
+                    .find(|t| matches!(t, DeployTarget::Zoneless(_)))
             });
 
+        let schedule = deployments.iter().find_map(|t| match t {
+            DeployTarget::Schedule(schedule) => Some(schedule),
+            _ => None,
+        });
+
         if let Some(target) = valid_target {
             target.clone()
+        } else if let Some(schedule) = schedule {
+            // a worker that only runs on a schedule has no routes, so it's previewed on
+            // workers.dev
+            DeployTarget::Zoneless(ZonelessTarget {
+                account_id: schedule.account_id.clone(),
+                script_name: schedule.script_name.clone(),
+            })
         } else {
-            anyhow::bail!("No valid deployment targets: `wrangler dev` can only be used to develop zoned and zoneless deployments")
+            anyhow::bail!("No valid deployment targets: `wrangler dev` can only be used to develop zoned, zoneless and scheduled deployments")
         }
     };
 
@@ -67,6 +89,9 @@ pub fn dev(
         anyhow::bail!("{} cannot be https if {} is http", local_str, upstream_str)
     }
 
+    let mut target = target;
+    start_scheduled(&mut target, crons, &server_config, local_protocol);
+
     if let Some(user) = user {
         if server_config.host.is_default() {
             // Authenticated and no host provided, run on edge with user's zone
@@ -97,3 +122,52 @@ pub fn dev(
 
     gcs::dev(target, server_config, local_protocol, verbose)
 }
+
+/// `wrangler dev --local` serves the Worker from a runtime on the dev machine instead,
+/// with its bindings emulated on disk
+pub fn dev_local(
+    target: Target,
+    crons: Vec<String>,
+    server_config: ServerConfig,
+    local_protocol: Protocol,
+    runtime: LocalRuntime,
+    kv_persist: Option<PathBuf>,
+    verbose: bool,
+) -> Result<()> {
+    build_target(&target)?;
+
+    let mut target = target;
+    start_scheduled(&mut target, crons, &server_config, local_protocol);
+
+    local::dev(
+        target,
+        server_config,
+        local_protocol,
+        runtime,
+        kv_persist,
+        verbose,
+    )
+}
+
+fn start_scheduled(
+    target: &mut Target,
+    crons: Vec<String>,
+    server_config: &ServerConfig,
+    local_protocol: Protocol,
+) {
+    target.test_scheduled = server_config.test_scheduled;
+
+    if server_config.run_crons {
+        if crons.is_empty() {
+            StdOut::warn("There are no crons in [triggers] to run");
+        } else {
+            let listening_address = server_config.listening_address;
+            thread::spawn(move || {
+                // the server keeps running without them, so a failure is only worth a warning
+                if let Err(e) = scheduled::run_crons(crons, listening_address, local_protocol) {
+                    StdErr::warn(&format!("Stopped running crons: {:#}", e));
+                }
+            });
+        }
+    }
+}
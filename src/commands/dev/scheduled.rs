@@ -0,0 +1,114 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::thread;
+
+use anyhow::Result;
+use chrono::prelude::*;
+use hyper::{Body, Response, StatusCode, Uri};
+use url::{form_urlencoded, Url};
+
+use crate::commands::dev::Protocol;
+use crate::settings::toml::Cron;
+use crate::terminal::message::{Message, StdErr, StdOut};
+use crate::upload::form::SCHEDULED_PATH;
+
+/// Checks a request for the scheduled endpoint, returning the response to send back instead
+/// of forwarding it if it asks for an invalid cron.
+pub(super) fn check_request(uri: &Uri) -> Option<Response<Body>> {
+    if uri.path() != SCHEDULED_PATH {
+        return None;
+    }
+
+    let cron = form_urlencoded::parse(uri.query()?.as_bytes())
+        .find(|(key, _)| key == "cron")
+        .map(|(_, cron)| cron.into_owned())?;
+    let error = cron.parse::<Cron>().err()?;
+
+    let mut response = Response::new(Body::from(format!(
+        "invalid cron \"{}\": {}\n",
+        cron, error
+    )));
+    *response.status_mut() = StatusCode::BAD_REQUEST;
+    Some(response)
+}
+
+/// Fires each cron at the times it's scheduled for, by requesting the scheduled endpoint of
+/// the local server, for as long as `wrangler dev` runs.
+pub(super) fn run_crons(
+    crons: Vec<String>,
+    listening_address: SocketAddr,
+    local_protocol: Protocol,
+) -> Result<()> {
+    let crons = crons
+        .into_iter()
+        .map(|cron| cron.parse::<Cron>().map(|parsed| (cron, parsed)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // the local server's certificate is self-signed
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let mut address = listening_address;
+    if address.ip().is_unspecified() {
+        address.set_ip(match address.ip() {
+            IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let scheme = if local_protocol.is_https() {
+        "https"
+    } else {
+        "http"
+    };
+    let endpoint = format!("{}://{}{}", scheme, address, SCHEDULED_PATH);
+
+    loop {
+        let now = Utc::now();
+        let next = crons
+            .iter()
+            .filter_map(|(_, parsed)| parsed.next_after(now))
+            .min();
+        let next = match next {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+
+        // `to_std` fails if the time has already come
+        if let Ok(wait) = (next - Utc::now()).to_std() {
+            thread::sleep(wait);
+        }
+
+        for (cron, parsed) in &crons {
+            if parsed.next_after(now) != Some(next) {
+                continue;
+            }
+
+            StdOut::info(&format!("Firing cron \"{}\"", cron));
+            let url = Url::parse_with_params(&endpoint, &[("cron", cron)])?;
+            if let Err(e) = client.get(url).send() {
+                StdErr::warn(&format!("Could not fire cron \"{}\": {}", cron, e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_rejects_invalid_crons() {
+        let uri: Uri = "/__scheduled?cron=0+25+*+*+*".parse().unwrap();
+        let response = check_request(&uri).unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let valid: Vec<Uri> = vec![
+            "/__scheduled?cron=*%2F5+*+*+*+*".parse().unwrap(),
+            "/__scheduled".parse().unwrap(),
+            "/other?cron=invalid".parse().unwrap(),
+        ];
+        for uri in valid {
+            assert!(check_request(&uri).is_none(), "{}", uri);
+        }
+    }
+}
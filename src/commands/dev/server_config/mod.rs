@@ -12,6 +12,10 @@ use std::net::{IpAddr, SocketAddr, TcpListener};
 pub struct ServerConfig {
     pub host: Host,
     pub listening_address: SocketAddr,
+    /// Whether the script is wrapped to answer the scheduled endpoint
+    pub test_scheduled: bool,
+    /// Whether to fire the crons in `[triggers]` on their schedule
+    pub run_crons: bool,
 }
 
 impl ServerConfig {
@@ -20,6 +24,8 @@ impl ServerConfig {
         ip: IpAddr,
         port: u16,
         upstream_protocol: Protocol,
+        test_scheduled: bool,
+        run_crons: bool,
     ) -> Result<Self> {
         let addr = SocketAddr::new(ip, port);
         let listening_address = match TcpListener::bind(&addr) {
@@ -42,6 +48,9 @@ impl ServerConfig {
         Ok(ServerConfig {
             host,
             listening_address,
+            // firing crons goes through the scheduled endpoint
+            test_scheduled: test_scheduled || run_crons,
+            run_crons,
         })
     }
 }
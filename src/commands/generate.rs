@@ -6,7 +6,7 @@ use std::{env, fs};
 use anyhow::Result;
 
 use crate::commands::validate_worker_name;
-use crate::settings::toml::{Manifest, Site, TargetType};
+use crate::settings::toml::{ConfigFormat, Manifest, Site, TargetType};
 use crate::{commands, install};
 
 pub fn generate(
@@ -14,6 +14,7 @@ pub fn generate(
     template: &str,
     target_type: Option<TargetType>,
     site: bool,
+    format: ConfigFormat,
 ) -> Result<()> {
     validate_worker_name(name)?;
 
@@ -43,7 +44,7 @@ pub fn generate(
     } else {
         None
     };
-    Manifest::generate(new_name, target_type, &config_path, generated_site)?;
+    Manifest::generate(new_name, target_type, &config_path, generated_site, format)?;
 
     Ok(())
 }
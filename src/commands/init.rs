@@ -3,12 +3,19 @@ use std::path::{Path, PathBuf};
 use anyhow::Result;
 
 use crate::commands::validate_worker_name;
-use crate::settings::toml::{Manifest, Site, TargetType};
+use crate::settings::toml::{ConfigFormat, Manifest, Site, TargetType};
 use crate::terminal::message::{Message, StdOut};
-pub fn init(name: Option<&str>, target_type: Option<TargetType>, site_flag: bool) -> Result<()> {
-    if Path::new("./wrangler.toml").exists() {
+pub fn init(
+    name: Option<&str>,
+    target_type: Option<TargetType>,
+    site_flag: bool,
+    format: ConfigFormat,
+) -> Result<()> {
+    let file_name = format.file_name();
+    if Path::new("./").join(file_name).exists() {
         if site_flag {
-            let msg = r#"A wrangler.toml file already exists!
+            let msg = match format {
+                ConfigFormat::Toml => r#"A wrangler.toml file already exists!
 
 To add Workers Sites to your existing wrangler.toml, please add this section:
 
@@ -16,10 +23,28 @@ To add Workers Sites to your existing wrangler.toml, please add this section:
 bucket = "" # this should point to the directory with static assets
 entry-point = "workers-site"
 
-"#;
+"#
+                .to_string(),
+                _ => format!(
+                    r#"A {} file already exists!
+
+To add Workers Sites to your existing {}, please add this section:
+
+"site": {{
+  "bucket": "",
+  "entry-point": "workers-site"
+}}
+
+"#,
+                    file_name, file_name
+                ),
+            };
             anyhow::bail!(msg);
         } else {
-            anyhow::bail!("A wrangler.toml file already exists! Please remove it before running this command again.");
+            anyhow::bail!(
+                "A {} file already exists! Please remove it before running this command again.",
+                file_name
+            );
         }
     }
     let dirname = get_current_dirname()?;
@@ -36,15 +61,22 @@ entry-point = "workers-site"
             Some(target_type),
             &config_path,
             Some(site.clone()),
+            format,
         )?;
 
         site.scaffold_worker()?;
         StdOut::success("Successfully scaffolded workers site");
     } else {
-        Manifest::generate(name.to_string(), Some(target_type), &config_path, None)?;
+        Manifest::generate(
+            name.to_string(),
+            Some(target_type),
+            &config_path,
+            None,
+            format,
+        )?;
     }
 
-    StdOut::success("Succesfully created a `wrangler.toml`");
+    StdOut::success(&format!("Succesfully created a `{}`", file_name));
     Ok(())
 }
 
@@ -0,0 +1,74 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::commands::kv::remote::{get_value, list_keys};
+use crate::http;
+use crate::kv::archive::{Entry, Format, Writer};
+use crate::kv::local::LIST_LIMIT;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
+
+/// Writes every key in a namespace, with its value, expiration and metadata, to an archive.
+/// Keys are written a page at a time as they're listed.
+pub fn export(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    path: &Path,
+    format: Format,
+) -> Result<()> {
+    let client = http::legacy_auth_client(user);
+    let mut writer = Writer::new(BufWriter::new(File::create(path)?), format);
+
+    StdErr::working(&format!("exporting namespace {}", namespace_id));
+    let style = ProgressStyle::default_spinner().template("{spinner}   {pos} keys exported");
+    let spinner = ProgressBar::new_spinner().with_style(style);
+
+    let mut cursor = None;
+    let mut skipped = 0;
+    loop {
+        let (keys, next) = list_keys(
+            &client,
+            target,
+            namespace_id,
+            None,
+            cursor.as_deref(),
+            LIST_LIMIT,
+        )?;
+        for key in keys {
+            match get_value(&client, target, namespace_id, &key.name)? {
+                Some(value) => {
+                    writer.write(Entry::new(key, &value))?;
+                    spinner.inc(1);
+                }
+                None => skipped += 1,
+            }
+        }
+
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    writer.finish()?;
+    spinner.finish_and_clear();
+
+    if skipped > 0 {
+        StdErr::warn(&format!(
+            "{} keys expired or were deleted while exporting, and were left out",
+            skipped
+        ));
+    }
+    StdErr::success(&format!(
+        "exported {} keys to {}",
+        spinner.position(),
+        path.display()
+    ));
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
+
+use crate::commands::kv::remote::{all_keys, get_value};
+use crate::http;
+use crate::kv::archive::{self, Entry, Format};
+use crate::kv::bulk::{batch_keys_values, put_entries};
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr, StdOut};
+
+/// Writes every entry in an archive to a namespace, in the same batches as `kv:bulk put`.
+///
+/// Each batch written is recorded next to the archive, so an import that fails part way
+/// picks up where it left off when run again. With `dry_run`, nothing is written, and the
+/// archive is compared with what's in the namespace instead.
+pub fn import(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    path: &Path,
+    format: Format,
+    dry_run: bool,
+) -> Result<()> {
+    let contents = fs::read(path)?;
+    let entries = archive::read(&contents, format)?;
+
+    if dry_run {
+        let client = http::legacy_auth_client(user);
+        return diff(&client, target, namespace_id, &entries);
+    }
+
+    let progress_path = progress_path(path);
+    let mut progress = Progress::new(namespace_id, &contents);
+    if let Some(saved) = Progress::load(&progress_path) {
+        if saved.resumes(&progress) {
+            StdErr::info(&format!(
+                "Resuming the import recorded in {}; delete it to start over",
+                progress_path.display()
+            ));
+            progress = saved;
+        }
+    }
+
+    let len = entries.len();
+    StdErr::working(&format!(
+        "importing {} keys into namespace {}",
+        len, namespace_id
+    ));
+    let progress_bar = ProgressBar::new(len as u64);
+    progress_bar.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
+
+    let now = Utc::now().timestamp();
+    let mut expired = 0;
+    for (i, batch) in batch_keys_values(entries).into_iter().enumerate() {
+        let batch_len = batch.len() as u64;
+        if i < progress.batches {
+            progress_bar.inc(batch_len);
+            continue;
+        }
+
+        let (batch, dead): (Vec<Entry>, Vec<Entry>) =
+            batch.into_iter().partition(|entry| !entry.has_expired(now));
+        expired += dead.len();
+        put_entries(target, user, namespace_id, batch, &None)?;
+
+        progress.batches = i + 1;
+        progress.save(&progress_path)?;
+        progress_bar.inc(batch_len);
+    }
+    progress_bar.finish_and_clear();
+
+    if progress_path.exists() {
+        fs::remove_file(&progress_path)?;
+    }
+    if expired > 0 {
+        StdErr::warn(&format!(
+            "{} keys had expired since they were exported, and were skipped",
+            expired
+        ));
+    }
+    StdErr::success(&format!("imported {} keys", len - expired));
+    Ok(())
+}
+
+// Shows which keys an import would add or change
+fn diff(client: &Client, target: &Target, namespace_id: &str, entries: &[Entry]) -> Result<()> {
+    let remote: HashMap<String, _> = all_keys(client, target, namespace_id, None)?
+        .into_iter()
+        .map(|key| (key.name.clone(), key))
+        .collect();
+
+    let now = Utc::now().timestamp();
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+    let mut expired = 0;
+    for entry in entries {
+        if entry.has_expired(now) {
+            expired += 1;
+            continue;
+        }
+
+        match remote.get(&entry.key) {
+            None => added.push(&entry.key),
+            Some(key) => {
+                let same = key.expiration == entry.expiration
+                    && key.metadata == entry.metadata
+                    && get_value(client, target, namespace_id, &key.name)? == Some(entry.value()?);
+                if same {
+                    unchanged += 1;
+                } else {
+                    changed.push(&entry.key);
+                }
+            }
+        }
+    }
+
+    for key in &added {
+        println!("+ {}", key);
+    }
+    for key in &changed {
+        println!("~ {}", key);
+    }
+
+    StdOut::info(&format!(
+        "Dry run: {} keys would be added, {} changed and {} left as they are",
+        added.len(),
+        changed.len(),
+        unchanged
+    ));
+    if expired > 0 {
+        StdOut::warn(&format!(
+            "{} keys have expired since they were exported, and would be skipped",
+            expired
+        ));
+    }
+    let archived: HashSet<&String> = entries.iter().map(|entry| &entry.key).collect();
+    let extraneous = remote.keys().filter(|key| !archived.contains(key)).count();
+    if extraneous > 0 {
+        StdOut::info(&format!(
+            "{} keys in the namespace are not in the archive; importing does not delete them",
+            extraneous
+        ));
+    }
+    Ok(())
+}
+
+fn progress_path(archive: &Path) -> PathBuf {
+    let mut path = OsString::from(archive);
+    path.push(".progress");
+    PathBuf::from(path)
+}
+
+/// How far an import has got: the number of batches written from an archive into a
+/// namespace. The archive is identified by a hash of its contents, so that an archive
+/// that has changed since isn't resumed part way through.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+struct Progress {
+    namespace_id: String,
+    archive: String,
+    batches: usize,
+}
+
+impl Progress {
+    fn new(namespace_id: &str, contents: &[u8]) -> Progress {
+        let mut hasher = XxHash64::with_seed(0);
+        hasher.write(contents);
+        Progress {
+            namespace_id: namespace_id.to_string(),
+            archive: format!("{:016x}", hasher.finish()),
+            batches: 0,
+        }
+    }
+
+    // a record that can't be read is as good as none; the import starts over
+    fn load(path: &Path) -> Option<Progress> {
+        let contents = fs::read(path).ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    fn resumes(&self, fresh: &Progress) -> bool {
+        self.namespace_id == fresh.namespace_id && self.archive == fresh.archive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_only_resumes_the_same_archive_into_the_same_namespace() {
+        let mut saved = Progress::new("namespace", b"archive");
+        saved.batches = 2;
+
+        assert!(saved.resumes(&Progress::new("namespace", b"archive")));
+        assert!(!saved.resumes(&Progress::new("namespace", b"changed archive")));
+        assert!(!saved.resumes(&Progress::new("other namespace", b"archive")));
+    }
+}
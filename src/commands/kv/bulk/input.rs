@@ -0,0 +1,374 @@
+use std::fs;
+use std::io::BufRead;
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
+use path_slash::PathExt;
+
+use crate::kv::archive::Entry;
+
+// the columns a CSV file of key-value pairs may have, in any order
+const CSV_COLUMNS: &[&str] = &["key", "value", "ttl", "expiration", "metadata", "base64"];
+
+/// How a file of key-value pairs to upload is laid out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    /// An array of pairs, which is read all at once
+    Json,
+    /// A pair per line, in the same shape as the pairs in a JSON array
+    Ndjson,
+    /// A header row naming the columns, then a pair per row
+    Csv,
+}
+
+impl InputFormat {
+    /// The format asked for, or else the one the file's extension suggests
+    pub fn new(format: Option<InputFormat>, path: &Path) -> InputFormat {
+        format.unwrap_or_else(|| match path.extension().and_then(|e| e.to_str()) {
+            Some("ndjson") | Some("jsonl") => InputFormat::Ndjson,
+            Some("csv") => InputFormat::Csv,
+            _ => InputFormat::Json,
+        })
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(InputFormat::Json),
+            "ndjson" => Ok(InputFormat::Ndjson),
+            "csv" => Ok(InputFormat::Csv),
+            _ => Err(anyhow!("{} is not a supported format", s)),
+        }
+    }
+}
+
+/// Reads a pair per line, skipping blank ones
+pub fn ndjson<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Entry>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(
+                serde_json::from_str(&line)
+                    .map_err(|e| anyhow!("Line {} is not a valid key-value pair: {}", i + 1, e)),
+            ),
+            Err(e) => Some(Err(e.into())),
+        })
+}
+
+/// Reads a pair per row, once the header row has said which columns there are. Only `key`
+/// and `value` are required; `ttl` and `expiration` are in seconds, `metadata` is JSON, and
+/// `base64` is true if the value is base64 encoded. Empty fields are left out.
+pub fn csv<R: BufRead>(reader: R) -> Result<impl Iterator<Item = Result<Entry>>> {
+    let mut records = Records { reader, line: 0 };
+    let columns = match records.next_record()? {
+        Some((_, header)) => Columns::new(&header)?,
+        None => anyhow::bail!(
+            "The CSV file is empty, but should start with a header like key,value,ttl,metadata"
+        ),
+    };
+
+    Ok(iter::from_fn(move || loop {
+        match records.next_record().transpose()? {
+            // blank lines aren't rows
+            Ok((_, fields)) if fields.len() == 1 && fields[0].is_empty() => {}
+            Ok((line, fields)) => {
+                return Some(
+                    columns
+                        .entry(fields)
+                        .map_err(|e| anyhow!("Line {} is not a valid key-value pair: {}", line, e)),
+                )
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    }))
+}
+
+/// Reads every file under `directory` as a pair, keyed by its path relative to the
+/// directory. Values must be UTF-8 unless they're to be base64 encoded. Only the paths are
+/// read up front; each file is read as its pair is needed.
+pub fn directory(
+    directory: &Path,
+    base64: bool,
+) -> Result<impl Iterator<Item = Result<Entry>> + Send> {
+    let mut paths = Vec::new();
+    for entry in WalkBuilder::new(directory).standard_filters(false).build() {
+        let entry = entry?;
+        if entry.path().is_file() {
+            paths.push(entry.into_path());
+        }
+    }
+    paths.sort();
+
+    let directory = directory.to_path_buf();
+    Ok(paths.into_iter().map(move |path| {
+        let key = path
+            .strip_prefix(&directory)?
+            .to_slash()
+            .ok_or_else(|| anyhow!("{} is not a valid UTF-8 path", path.display()))?;
+        let value = fs::read(&path)?;
+        let value = if base64 {
+            base64::encode(&value)
+        } else {
+            String::from_utf8(value).map_err(|_| {
+                anyhow!(
+                    "{} is not UTF-8 text; pass --base64 to upload it encoded",
+                    path.display()
+                )
+            })?
+        };
+        Ok(Entry {
+            key,
+            value,
+            base64,
+            expiration: None,
+            expiration_ttl: None,
+            metadata: None,
+        })
+    }))
+}
+
+// Reads CSV records, which span more than one line when a quoted field has a line break in it
+struct Records<R> {
+    reader: R,
+    line: usize,
+}
+
+impl<R: BufRead> Records<R> {
+    // The next record, and the line it starts on
+    fn next_record(&mut self) -> Result<Option<(usize, Vec<String>)>> {
+        let start = self.line + 1;
+        let mut record = String::new();
+        loop {
+            if self.reader.read_line(&mut record)? == 0 {
+                if record.is_empty() {
+                    return Ok(None);
+                }
+                anyhow::bail!("Line {} has a quoted field that is never closed", start)
+            }
+            self.line += 1;
+            // quotes inside quoted fields are doubled, so the record ends on a line that
+            // leaves an even number of them
+            if record.matches('"').count() % 2 == 0 {
+                break;
+            }
+        }
+        let record = record.trim_end_matches(|c| c == '\n' || c == '\r');
+        Ok(Some((start, parse_record(record))))
+    }
+}
+
+fn parse_record(record: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = record.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// Where each column is in a row
+struct Columns {
+    count: usize,
+    key: usize,
+    value: usize,
+    ttl: Option<usize>,
+    expiration: Option<usize>,
+    metadata: Option<usize>,
+    base64: Option<usize>,
+}
+
+impl Columns {
+    fn new(header: &[String]) -> Result<Columns> {
+        for column in header {
+            if !CSV_COLUMNS.contains(&column.trim()) {
+                anyhow::bail!(
+                    "\"{}\" is not a column wrangler knows. Columns can be {}",
+                    column,
+                    CSV_COLUMNS.join(", ")
+                )
+            }
+        }
+        let position = |name: &str| header.iter().position(|column| column.trim() == name);
+        let required = |name: &str| {
+            position(name).ok_or_else(|| anyhow!("The CSV header has no {} column", name))
+        };
+
+        Ok(Columns {
+            count: header.len(),
+            key: required("key")?,
+            value: required("value")?,
+            ttl: position("ttl"),
+            expiration: position("expiration"),
+            metadata: position("metadata"),
+            base64: position("base64"),
+        })
+    }
+
+    fn entry(&self, mut fields: Vec<String>) -> Result<Entry> {
+        if fields.len() != self.count {
+            anyhow::bail!(
+                "it has {} fields, but the header has {} columns",
+                fields.len(),
+                self.count
+            )
+        }
+        let optional = |column: Option<usize>| {
+            column
+                .map(|i| fields[i].as_str())
+                .filter(|field| !field.is_empty())
+        };
+        let number = |column: Option<usize>, name: &str| -> Result<Option<i64>> {
+            optional(column)
+                .map(|field| {
+                    field
+                        .parse()
+                        .map_err(|_| anyhow!("{} \"{}\" is not a number of seconds", name, field))
+                })
+                .transpose()
+        };
+
+        let expiration_ttl = number(self.ttl, "ttl")?;
+        let expiration = number(self.expiration, "expiration")?;
+        let metadata = optional(self.metadata)
+            .map(|field| {
+                serde_json::from_str(field).map_err(|e| anyhow!("metadata is not JSON: {}", e))
+            })
+            .transpose()?;
+        let base64 = optional(self.base64)
+            .map(|field| {
+                field
+                    .parse()
+                    .map_err(|_| anyhow!("base64 \"{}\" is not true or false", field))
+            })
+            .transpose()?
+            .unwrap_or(false);
+
+        Ok(Entry {
+            key: std::mem::take(&mut fields[self.key]),
+            value: std::mem::take(&mut fields[self.value]),
+            base64,
+            expiration,
+            expiration_ttl,
+            metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_reads_csv_rows_by_their_header() {
+        let input = "value,key,ttl,metadata\n\
+                     plain,a,,\n\
+                     \n\
+                     \"two\r\nlines, and \"\"quotes\"\"\",b,3600,\"{\"\"owner\"\":\"\"alice\"\"}\"\n";
+        let entries = csv(input.as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, "a");
+        assert_eq!(entries[0].value, "plain");
+        assert_eq!(entries[0].expiration_ttl, None);
+        assert_eq!(entries[0].metadata, None);
+        assert_eq!(entries[1].key, "b");
+        assert_eq!(entries[1].value, "two\r\nlines, and \"quotes\"");
+        assert_eq!(entries[1].expiration_ttl, Some(3600));
+        assert_eq!(entries[1].metadata, Some(json!({ "owner": "alice" })));
+    }
+
+    #[test]
+    fn it_reports_the_line_of_a_bad_csv_row() {
+        let input = "key,value,ttl\na,1,60\nb,2,soon\nc,3,60\n";
+        let entries: Vec<_> = csv(input.as_bytes()).unwrap().collect();
+
+        assert!(entries[0].is_ok());
+        assert_eq!(
+            entries[1].as_ref().unwrap_err().to_string(),
+            "Line 3 is not a valid key-value pair: ttl \"soon\" is not a number of seconds"
+        );
+        assert!(csv("key,value,colour\n".as_bytes()).is_err());
+        assert!(csv("value\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn it_reads_ndjson_a_line_at_a_time() {
+        let input = "{\"key\":\"a\",\"value\":\"1\",\"expiration_ttl\":60}\n\n{\"key\":\"b\"}\n";
+        let entries: Vec<_> = ndjson(input.as_bytes()).collect();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].as_ref().unwrap().expiration_ttl, Some(60));
+        assert!(entries[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Line 3 is not a valid key-value pair"));
+    }
+
+    #[test]
+    fn it_keys_files_by_their_path() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("text.txt"), "hello").unwrap();
+        fs::write(
+            dir.path().join("nested").join("binary"),
+            &[0, 159, 146, 150],
+        )
+        .unwrap();
+
+        let entries = directory(dir.path(), true)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let keys: Vec<_> = entries.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, vec!["nested/binary", "text.txt"]);
+        assert_eq!(entries[0].value().unwrap(), vec![0, 159, 146, 150]);
+
+        let entries: Vec<_> = directory(dir.path(), false).unwrap().collect();
+        assert!(entries[0].is_err());
+        assert_eq!(entries[1].as_ref().unwrap().value, "hello");
+    }
+
+    #[test]
+    fn it_picks_a_format_from_the_extension() {
+        assert_eq!(
+            InputFormat::new(None, Path::new("keys.ndjson")),
+            InputFormat::Ndjson
+        );
+        assert_eq!(
+            InputFormat::new(None, Path::new("keys.csv")),
+            InputFormat::Csv
+        );
+        assert_eq!(
+            InputFormat::new(None, Path::new("keys.json")),
+            InputFormat::Json
+        );
+        assert_eq!(
+            InputFormat::new(Some(InputFormat::Csv), Path::new("keys.json")),
+            InputFormat::Csv
+        );
+    }
+}
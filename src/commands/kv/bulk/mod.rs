@@ -1,5 +1,7 @@
 pub mod delete;
+mod input;
 pub mod put;
 
 pub use delete::run as delete;
+pub use input::InputFormat;
 pub use put::run as put;
@@ -1,37 +1,62 @@
 extern crate base64;
 
 use std::fs;
-use std::fs::metadata;
+use std::fs::{metadata, File};
+use std::io::BufReader;
 use std::path::Path;
 
-use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
-
 use anyhow::{anyhow, Result};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::kv::bulk::put;
+use super::input::{self, InputFormat};
+use crate::kv::archive::Entry;
 use crate::kv::bulk::BATCH_KEY_MAX;
+use crate::kv::bulk::{put_entries, put_stream};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdErr};
-pub fn run(target: &Target, user: &GlobalUser, namespace_id: &str, filename: &Path) -> Result<()> {
-    let pairs: Vec<KeyValuePair> = match &metadata(filename) {
-        Ok(file_type) if file_type.is_file() => {
-            let data = fs::read_to_string(filename)?;
-            let data_vec = serde_json::from_str(&data);
-            match data_vec {
-                Ok(data_vec) => Ok(data_vec),
-                Err(_) => Err(anyhow!("Failed to decode JSON. Please make sure to follow the format, [{{\"key\": \"test_key\", \"value\": \"test_value\"}}, ...]"))
-            }
+
+/// Uploads the key-value pairs in a file, or every file in a directory. Only JSON files are
+/// read all at once; the pairs in NDJSON and CSV files, and the files in a directory, are
+/// uploaded a batch at a time as they're read.
+pub fn run(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    path: &Path,
+    format: Option<InputFormat>,
+    base64: bool,
+) -> Result<()> {
+    match &metadata(path) {
+        Ok(file_type) if file_type.is_dir() => {
+            let entries = input::directory(path, base64)?;
+            return upload_stream(target, user, namespace_id, entries);
+        }
+        Ok(_) if base64 => anyhow::bail!("--base64 only applies to uploading a directory"),
+        Ok(_) => {}
+        Err(e) => anyhow::bail!("{}", e),
+    }
+
+    match InputFormat::new(format, path) {
+        InputFormat::Json => upload_json(target, user, namespace_id, path),
+        InputFormat::Ndjson => {
+            let entries = input::ndjson(BufReader::new(File::open(path)?));
+            upload_stream(target, user, namespace_id, entries)
+        }
+        InputFormat::Csv => {
+            let entries = input::csv(BufReader::new(File::open(path)?))?;
+            upload_stream(target, user, namespace_id, entries)
         }
-        Ok(_) => Err(anyhow!(
-            "{} should be a JSON file, but is not",
-            filename.display()
-        )),
-        Err(e) => Err(anyhow!("{}", e)),
-    }?;
+    }
+}
 
-    let len = pairs.len();
+fn upload_json(target: &Target, user: &GlobalUser, namespace_id: &str, path: &Path) -> Result<()> {
+    let data = fs::read_to_string(path)?;
+    let entries: Vec<Entry> = serde_json::from_str(&data).map_err(|_| {
+        anyhow!("Failed to decode JSON. Please make sure to follow the format, [{{\"key\": \"test_key\", \"value\": \"test_value\"}}, ...]")
+    })?;
+
+    let len = entries.len();
 
     StdErr::working(&format!("uploading {} key value pairs", len));
     let progress_bar = if len > BATCH_KEY_MAX {
@@ -42,7 +67,7 @@ pub fn run(target: &Target, user: &GlobalUser, namespace_id: &str, filename: &Pa
         None
     };
 
-    put(target, &user, namespace_id, pairs, &progress_bar)?;
+    put_entries(target, user, namespace_id, entries, &progress_bar)?;
 
     if let Some(pb) = &progress_bar {
         pb.finish_with_message(&format!("uploaded {} key value pairs", len));
@@ -51,3 +76,30 @@ pub fn run(target: &Target, user: &GlobalUser, namespace_id: &str, filename: &Pa
     StdErr::success("Success");
     Ok(())
 }
+
+// there's no telling how many pairs there are until they've all been read, so progress is
+// only counted
+fn upload_stream<I>(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    entries: I,
+) -> Result<()>
+where
+    I: Iterator<Item = Result<Entry>> + Send + 'static,
+{
+    StdErr::working("uploading key value pairs");
+    let spinner = ProgressBar::new_spinner().with_style(
+        ProgressStyle::default_spinner().template("{spinner}   {pos} key value pairs uploaded"),
+    );
+    spinner.enable_steady_tick(100);
+    let progress_bar = Some(spinner);
+
+    let uploaded = put_stream(target, user, namespace_id, entries, &progress_bar);
+    if let Some(pb) = &progress_bar {
+        pb.finish_and_clear();
+    }
+
+    StdErr::success(&format!("uploaded {} key value pairs", uploaded?));
+    Ok(())
+}
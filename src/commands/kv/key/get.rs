@@ -3,45 +3,104 @@
 // because the GET key operation doesn't return json on success--just the raw
 // value).
 
-use cloudflare::framework::response::ApiFailure;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
 
 use anyhow::Result;
+use atty::Stream;
+use serde::Serialize;
+use serde_json::Value;
 
-use crate::commands::kv;
+use crate::commands::kv::remote::{get_key, get_metadata, get_value};
 use crate::http;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
-use std::io::{self, Write};
+use crate::terminal::message::{Message, StdErr, StdOut};
+
+pub struct GetOptions {
+    /// Where to write the value, rather than stdout
+    pub output: Option<PathBuf>,
+    /// Whether to print the key's metadata to stderr, after the value
+    pub with_metadata: bool,
+    /// Whether to print the value, metadata and expiration as JSON
+    pub json: bool,
+}
+
+// A value as it's printed with --json
+#[derive(Serialize)]
+struct Got {
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base64: Option<bool>,
+    metadata: Option<Value>,
+    expiration: Option<i64>,
+}
+
+/// A value as text, base64 encoded if it isn't UTF-8, and whether it was encoded
+pub(super) fn encode_value(value: Vec<u8>) -> (String, Option<bool>) {
+    match String::from_utf8(value) {
+        Ok(value) => (value, None),
+        Err(e) => (base64::encode(e.as_bytes()), Some(true)),
+    }
+}
 
-pub fn get(target: &Target, user: &GlobalUser, id: &str, key: &str) -> Result<()> {
-    let api_endpoint = format!(
-        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
-        target.account_id.load()?,
-        id,
-        kv::url_encode_key(key)
-    );
+pub fn get(
+    target: &Target,
+    user: &GlobalUser,
+    id: &str,
+    key: &str,
+    options: GetOptions,
+) -> Result<()> {
+    let client = http::legacy_auth_client(user);
 
-    let client = http::legacy_auth_client(&user);
+    let value = match get_value(&client, target, id, key)? {
+        Some(value) => value,
+        None => anyhow::bail!("There is no key named \"{}\" in the namespace", key),
+    };
+    let metadata = if options.with_metadata || options.json {
+        get_metadata(&client, target, id, key)?
+    } else {
+        None
+    };
 
-    let res = client.get(&api_endpoint).send()?;
+    if options.json {
+        // only listing a key tells when it expires
+        let expiration = get_key(&client, target, id, key)?.and_then(|key| key.expiration);
+        let (value, base64) = encode_value(value);
+        StdOut::as_json(&Got {
+            value,
+            base64,
+            metadata,
+            expiration,
+        });
+        return Ok(());
+    }
 
-    let response_status = res.status();
-    if response_status.is_success() {
-        let body = res.bytes()?;
+    match &options.output {
+        Some(path) => {
+            fs::write(path, &value)?;
+            StdErr::success(&format!(
+                "Wrote the value of \"{}\" to {}",
+                key,
+                path.display()
+            ));
+        }
+        // binary values are garbled on a terminal, but left as they are when piped
+        None if atty::is(Stream::Stdout) && std::str::from_utf8(&value).is_err() => {
+            StdErr::warn("The value is not UTF-8 text, so it's shown base64 encoded. Pass --output to write it to a file as it is.");
+            println!("{}", base64::encode(&value));
+        }
         // We don't use message::success because we don't want to include the emoji/formatting
         // in case someone is piping this to stdin.
-        // This will probably fail for non-UTF8 on Windows, but should at least work for people
-        // getting binary data from KV on Unix-y systems.
-        io::stdout().write_all(&*body)?;
-    } else {
-        // This is logic pulled from cloudflare-rs for pretty error formatting right now;
-        // it will be redundant when we switch to using cloudflare-rs for all API requests.
-        let parsed = res.json();
-        let errors = parsed.unwrap_or_default();
-        print!(
-            "{}",
-            kv::format_error(ApiFailure::Error(response_status, errors))
-        );
+        None => io::stdout().write_all(&value)?,
+    }
+
+    if options.with_metadata {
+        match metadata {
+            Some(metadata) => StdErr::info(&format!("metadata: {}", metadata)),
+            None => StdErr::info("The key has no metadata"),
+        }
     }
 
     Ok(())
@@ -1,45 +1,412 @@
 extern crate serde_json;
 
-use crate::commands::kv;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use prettytable::{Cell, Row, Table};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::get::encode_value;
+use crate::commands::kv::remote::{get_value, list_keys};
 use crate::http;
-use crate::kv::key::KeyList;
+use crate::kv::local::{Key, LIST_LIMIT, MIN_LIST_LIMIT};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
+
+// values longer than this are cut short in tables
+const TABLE_VALUE_MAX_CHARS: usize = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListFormat {
+    Json,
+    Ndjson,
+    Csv,
+    Table,
+}
+
+impl FromStr for ListFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ListFormat::Json),
+            "ndjson" => Ok(ListFormat::Ndjson),
+            "csv" => Ok(ListFormat::Csv),
+            "table" => Ok(ListFormat::Table),
+            _ => Err(anyhow!("{} is not a supported format", s)),
+        }
+    }
+}
+
+/// Matches keys whose metadata has a value at a path like `owner.name` or `tags.0`, and
+/// if given, whether that value is the one expected. Values are compared as JSON when
+/// they parse as JSON, so `count=3` matches the number and `count="3"` the string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataFilter {
+    path: Vec<String>,
+    value: Option<String>,
+}
+
+impl FromStr for MetadataFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (path, value) = match s.split_once('=') {
+            Some((path, value)) => (path, Some(value.to_string())),
+            None => (s, None),
+        };
+        if path.is_empty() || path.split('.').any(str::is_empty) {
+            anyhow::bail!("\"{}\" is not a path like `owner.name=value`", s)
+        }
+        Ok(MetadataFilter {
+            path: path.split('.').map(str::to_string).collect(),
+            value,
+        })
+    }
+}
+
+impl MetadataFilter {
+    fn matches(&self, metadata: Option<&Value>) -> bool {
+        let mut found = match metadata {
+            Some(metadata) => metadata,
+            None => return false,
+        };
+        for segment in &self.path {
+            let next = match found {
+                Value::Object(map) => map.get(segment),
+                Value::Array(items) => segment.parse().ok().and_then(|i: usize| items.get(i)),
+                _ => None,
+            };
+            found = match next {
+                Some(next) => next,
+                None => return false,
+            };
+        }
+
+        match &self.value {
+            None => true,
+            Some(expected) => match serde_json::from_str::<Value>(expected) {
+                Ok(expected) => *found == expected,
+                Err(_) => found.as_str() == Some(expected),
+            },
+        }
+    }
+}
+
+pub struct ListOptions {
+    pub prefix: Option<String>,
+    /// The most keys to read from the namespace, before any filters
+    pub limit: Option<usize>,
+    /// Where to resume an earlier listing that stopped at its limit
+    pub cursor: Option<String>,
+    pub with_values: bool,
+    pub expires_before: Option<i64>,
+    pub expires_after: Option<i64>,
+    pub metadata_filters: Vec<MetadataFilter>,
+    pub format: ListFormat,
+}
+
+impl ListOptions {
+    fn matches(&self, key: &Key) -> bool {
+        let before = self.expires_before.map_or(true, |before| {
+            key.expiration
+                .map_or(false, |expiration| expiration < before)
+        });
+        let after = self.expires_after.map_or(true, |after| {
+            key.expiration
+                .map_or(false, |expiration| expiration > after)
+        });
+        before
+            && after
+            && self
+                .metadata_filters
+                .iter()
+                .all(|filter| filter.matches(key.metadata.as_ref()))
+    }
+}
+
+// A key as it's printed, with its value if asked for. Values that aren't UTF-8 are base64
+// encoded.
+#[derive(Serialize)]
+struct Listed {
+    #[serde(flatten)]
+    key: Key,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base64: Option<bool>,
+}
+
+impl Listed {
+    fn new(key: Key, value: Option<Vec<u8>>) -> Listed {
+        let (value, base64) = match value.map(encode_value) {
+            Some((value, base64)) => (Some(value), base64),
+            None => (None, None),
+        };
+        Listed { key, value, base64 }
+    }
+
+    // the cells of a table or CSV row
+    fn fields(&self, with_values: bool) -> Vec<String> {
+        let mut fields = vec![
+            self.key.name.clone(),
+            self.key
+                .expiration
+                .map(|expiration| expiration.to_string())
+                .unwrap_or_default(),
+            self.key
+                .metadata
+                .as_ref()
+                .map(Value::to_string)
+                .unwrap_or_default(),
+        ];
+        if with_values {
+            let value = self.value.clone().unwrap_or_default();
+            fields.push(match self.base64 {
+                Some(true) => format!("base64:{}", value),
+                _ => value,
+            });
+        }
+        fields
+    }
+}
 
-use anyhow::Result;
+// Prints keys as they're listed, except for tables, which need every row to be laid out
+enum Printer {
+    Json { first: bool },
+    Ndjson,
+    Csv,
+    Table(Table),
+}
+
+impl Printer {
+    fn new(format: ListFormat, with_values: bool) -> Printer {
+        let mut header = vec!["name", "expiration", "metadata"];
+        if with_values {
+            header.push("value");
+        }
 
-// Note: this function only prints keys in json form, given that
-// the number of entries in each json blob is variable (so csv and tsv
-// representation won't make sense)
+        match format {
+            ListFormat::Json => {
+                print!("["); // Open json list bracket
+                Printer::Json { first: true }
+            }
+            ListFormat::Ndjson => Printer::Ndjson,
+            ListFormat::Csv => {
+                println!("{}", header.join(","));
+                Printer::Csv
+            }
+            ListFormat::Table => {
+                let mut table = Table::new();
+                table.add_row(Row::new(header.into_iter().map(Cell::new).collect()));
+                Printer::Table(table)
+            }
+        }
+    }
+
+    fn print(&mut self, listed: &Listed, with_values: bool) -> Result<()> {
+        match self {
+            Printer::Json { first } => {
+                if !*first {
+                    print!(",");
+                }
+                *first = false;
+                print!("{}", serde_json::to_string(listed)?);
+            }
+            Printer::Ndjson => println!("{}", serde_json::to_string(listed)?),
+            Printer::Csv => {
+                let fields: Vec<String> = listed
+                    .fields(with_values)
+                    .iter()
+                    .map(|field| csv_field(field))
+                    .collect();
+                println!("{}", fields.join(","));
+            }
+            Printer::Table(table) => {
+                let mut fields = listed.fields(with_values);
+                fields[1] = listed
+                    .key
+                    .expiration
+                    .and_then(|expiration| NaiveDateTime::from_timestamp_opt(expiration, 0))
+                    .map(|expiration| expiration.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .unwrap_or_default();
+                if let Some(value) = fields.get_mut(3) {
+                    *value = truncate(value, TABLE_VALUE_MAX_CHARS);
+                }
+                table.add_row(Row::new(
+                    fields.iter().map(|field| Cell::new(field)).collect(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) {
+        match self {
+            Printer::Json { .. } => print!("]"), // Close json list bracket
+            Printer::Table(table) => {
+                table.printstd();
+            }
+            Printer::Ndjson | Printer::Csv => {}
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    if value.chars().count() > max_chars {
+        let truncated: String = value.chars().take(max_chars - 1).collect();
+        format!("{}…", truncated)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Lists the keys in a namespace that match `options`, a page at a time. When the listing
+/// stops at `options.limit` with keys left, the cursor to resume from is printed to stderr,
+/// so that stdout can be piped elsewhere.
 pub fn list(
     target: &Target,
     user: &GlobalUser,
     namespace_id: &str,
-    prefix: Option<&str>,
+    options: ListOptions,
 ) -> Result<()> {
-    let client = http::cf_v4_client(&user)?;
-    let key_list = KeyList::new(target, client, namespace_id, prefix)?;
-
-    print!("["); // Open json list bracket
+    if let Some(limit) = options.limit {
+        if limit < MIN_LIST_LIMIT {
+            anyhow::bail!("--limit must be at least {}", MIN_LIST_LIMIT)
+        }
+    }
 
-    let mut first_key = true;
+    let client = http::legacy_auth_client(user);
+    let mut printer = Printer::new(options.format, options.with_values);
+    let mut cursor = options.cursor.clone();
+    let mut remaining = options.limit;
+    loop {
+        // the API won't list fewer keys than MIN_LIST_LIMIT at a time, so the last page may
+        // go a little over the limit
+        let page_limit = remaining.map_or(LIST_LIMIT, |remaining| {
+            remaining.min(LIST_LIMIT).max(MIN_LIST_LIMIT)
+        });
+        let (keys, next) = list_keys(
+            &client,
+            target,
+            namespace_id,
+            options.prefix.as_deref(),
+            cursor.as_deref(),
+            page_limit,
+        )?;
+        if let Some(remaining) = &mut remaining {
+            *remaining = remaining.saturating_sub(keys.len());
+        }
 
-    for key_result in key_list {
-        match key_result {
-            Ok(key) => {
-                if first_key {
-                    first_key = false;
-                } else {
-                    print!(",");
+        for key in keys.into_iter().filter(|key| options.matches(key)) {
+            let value = if options.with_values {
+                // keys can expire or be deleted after they're listed
+                match get_value(&client, target, namespace_id, &key.name)? {
+                    Some(value) => Some(value),
+                    None => continue,
                 }
+            } else {
+                None
+            };
+            printer.print(&Listed::new(key, value), options.with_values)?;
+        }
 
-                print!("{}", serde_json::to_string(&key)?);
-            }
-            Err(e) => print!("{}", kv::format_error(e)),
+        cursor = next;
+        if cursor.is_none() || remaining == Some(0) {
+            break;
         }
     }
+    printer.finish();
 
-    print!("]"); // Close json list bracket
-
+    if let Some(cursor) = cursor {
+        StdErr::info(&format!(
+            "There are more keys to list; pass `--cursor {}` to continue",
+            cursor
+        ));
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_filters_on_metadata_paths() {
+        let metadata = json!({ "owner": { "name": "alice" }, "count": 3, "tags": ["a", "b"] });
+        let matches = |filter: &str| {
+            MetadataFilter::from_str(filter)
+                .unwrap()
+                .matches(Some(&metadata))
+        };
+
+        assert!(matches("owner.name"));
+        assert!(matches("owner.name=alice"));
+        assert!(matches("owner.name=\"alice\""));
+        assert!(matches("count=3"));
+        assert!(matches("tags.1=b"));
+        assert!(!matches("count=\"3\""));
+        assert!(!matches("owner.name=bob"));
+        assert!(!matches("owner.email"));
+        assert!(!matches("tags.2"));
+        assert!(MetadataFilter::from_str("owner..name").is_err());
+        assert!(!MetadataFilter::from_str("owner").unwrap().matches(None));
+    }
+
+    #[test]
+    fn it_filters_on_expiration() {
+        let options = ListOptions {
+            prefix: None,
+            limit: None,
+            cursor: None,
+            with_values: false,
+            expires_before: Some(2000),
+            expires_after: Some(1000),
+            metadata_filters: Vec::new(),
+            format: ListFormat::Json,
+        };
+        let key = |expiration| Key {
+            name: "key".to_string(),
+            expiration,
+            metadata: None,
+        };
+
+        assert!(options.matches(&key(Some(1500))));
+        assert!(!options.matches(&key(Some(2500))));
+        assert!(!options.matches(&key(Some(500))));
+        assert!(!options.matches(&key(None)));
+    }
+
+    #[test]
+    fn it_quotes_csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("{\"a\":1}"), "\"{\"\"a\"\":1}\"");
+    }
+
+    #[test]
+    fn it_base64_encodes_binary_values() {
+        let key = Key {
+            name: "key".to_string(),
+            expiration: None,
+            metadata: None,
+        };
+        let listed = Listed::new(key, Some(vec![0, 159, 146, 150]));
+
+        assert_eq!(listed.value.as_deref(), Some("AJ+Slg=="));
+        assert_eq!(listed.base64, Some(true));
+        assert_eq!(listed.fields(true)[3], "base64:AJ+Slg==");
+    }
+}
@@ -8,9 +8,11 @@ use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use crate::http;
 use crate::settings::toml::Target;
 
+pub mod archive;
 pub mod bulk;
 pub mod key;
 pub mod namespace;
+pub(crate) mod remote;
 
 // TODO: callers outside this module should write their own error handling (lookin at you sites)
 pub fn format_error(e: ApiFailure) -> String {
@@ -114,6 +116,7 @@ mod tests {
             build: None,
             wasm_modules: None,
             usage_model: None,
+            test_scheduled: false,
         };
         assert!(kv::get_namespace_id(&target_with_dup_kv_bindings, "").is_err());
     }
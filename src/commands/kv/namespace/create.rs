@@ -51,7 +51,7 @@ pub fn run(
     Ok(())
 }
 
-fn validate_binding(binding: &str) -> Result<()> {
+pub(crate) fn validate_binding(binding: &str) -> Result<()> {
     let re = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
     if !re.is_match(binding) {
         anyhow::bail!(
@@ -1,7 +1,10 @@
 mod create;
 mod delete;
 mod list;
+mod sync;
 
 pub use create::run as create;
+pub(crate) use create::validate_binding;
 pub use delete::run as delete;
 pub use list::run as list;
+pub use sync::{copy, sync, Location};
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+
+use crate::commands::kv::remote::{all_keys, get_value};
+use crate::http;
+use crate::kv::archive::Entry;
+use crate::kv::bulk::{self, BATCH_KEY_MAX};
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, Output, StdErr, StdOut};
+
+/// A namespace, with the target for the account it belongs to
+pub struct Location {
+    pub target: Target,
+    pub namespace_id: String,
+}
+
+/// What a copy or sync did to the destination namespace
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    added: Vec<String>,
+    changed: Vec<String>,
+    unchanged: usize,
+    /// Keys that expire too soon for the API to write them
+    expired: Vec<String>,
+    /// Keys only in the destination
+    extraneous: Vec<String>,
+    deleted_extraneous: bool,
+}
+
+/// Writes every key in `source` to `destination`, whether or not it's there already
+pub fn copy(
+    user: &GlobalUser,
+    source: &Location,
+    destination: &Location,
+    prefix: Option<&str>,
+    output: Output,
+) -> Result<()> {
+    transfer(user, source, destination, prefix, false, false, output)
+}
+
+/// Writes the keys in `source` that are missing from or different in `destination`, and
+/// with `delete_extraneous`, deletes the keys only in `destination`
+pub fn sync(
+    user: &GlobalUser,
+    source: &Location,
+    destination: &Location,
+    prefix: Option<&str>,
+    delete_extraneous: bool,
+    output: Output,
+) -> Result<()> {
+    transfer(
+        user,
+        source,
+        destination,
+        prefix,
+        true,
+        delete_extraneous,
+        output,
+    )
+}
+
+fn transfer(
+    user: &GlobalUser,
+    source: &Location,
+    destination: &Location,
+    prefix: Option<&str>,
+    compare_values: bool,
+    delete_extraneous: bool,
+    output: Output,
+) -> Result<()> {
+    if source.namespace_id == destination.namespace_id
+        && source.target.account_id.load()? == destination.target.account_id.load()?
+    {
+        anyhow::bail!("The source and destination are the same namespace")
+    }
+
+    let client = http::legacy_auth_client(user);
+    let source_keys = all_keys(&client, &source.target, &source.namespace_id, prefix)?;
+    let destination_keys: HashMap<String, _> = all_keys(
+        &client,
+        &destination.target,
+        &destination.namespace_id,
+        prefix,
+    )?
+    .into_iter()
+    .map(|key| (key.name.clone(), key))
+    .collect();
+
+    let mut summary = Summary::default();
+    let source_names: HashSet<&String> = source_keys.iter().map(|key| &key.name).collect();
+    summary.extraneous = destination_keys
+        .keys()
+        .filter(|name| !source_names.contains(name))
+        .cloned()
+        .collect();
+    summary.extraneous.sort();
+
+    let spinner_style = ProgressStyle::default_spinner().template("{spinner}   Comparing {msg}...");
+    let spinner = ProgressBar::new_spinner().with_style(spinner_style);
+    let now = Utc::now().timestamp();
+    let mut entries = Vec::new();
+    for key in source_keys {
+        spinner.set_message(&key.name);
+        // keys can expire or be deleted after they're listed
+        let value = match get_value(&client, &source.target, &source.namespace_id, &key.name)? {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let entry = Entry::new(key, &value);
+        if entry.has_expired(now) {
+            summary.expired.push(entry.key);
+            continue;
+        }
+
+        match destination_keys.get(&entry.key) {
+            None => summary.added.push(entry.key.clone()),
+            Some(existing) => {
+                let unchanged = compare_values
+                    && existing.expiration == entry.expiration
+                    && existing.metadata == entry.metadata
+                    && get_value(
+                        &client,
+                        &destination.target,
+                        &destination.namespace_id,
+                        &entry.key,
+                    )? == Some(value);
+                if unchanged {
+                    summary.unchanged += 1;
+                    continue;
+                }
+                summary.changed.push(entry.key.clone());
+            }
+        }
+        entries.push(entry);
+    }
+    spinner.finish_and_clear();
+
+    let len = entries.len();
+    let progress_bar = if len > BATCH_KEY_MAX {
+        let pb = ProgressBar::new(len as u64);
+        pb.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
+        Some(pb)
+    } else {
+        None
+    };
+    bulk::put_entries(
+        &destination.target,
+        user,
+        &destination.namespace_id,
+        entries,
+        &progress_bar,
+    )?;
+    if let Some(pb) = &progress_bar {
+        pb.finish_with_message(&format!("wrote {} key value pairs", len));
+    }
+
+    if delete_extraneous && !summary.extraneous.is_empty() {
+        bulk::delete(
+            &destination.target,
+            user,
+            &destination.namespace_id,
+            summary.extraneous.clone(),
+            &None,
+        )?;
+        summary.deleted_extraneous = true;
+    }
+
+    match output {
+        Output::Json => StdOut::as_json(&summary),
+        Output::PlainText => print_summary(&summary),
+    }
+    Ok(())
+}
+
+fn print_summary(summary: &Summary) {
+    for key in &summary.added {
+        println!("+ {}", key);
+    }
+    for key in &summary.changed {
+        println!("~ {}", key);
+    }
+    if summary.deleted_extraneous {
+        for key in &summary.extraneous {
+            println!("- {}", key);
+        }
+    }
+
+    if !summary.expired.is_empty() {
+        StdErr::warn(&format!(
+            "{} keys expire too soon to be written, and were skipped",
+            summary.expired.len()
+        ));
+    }
+    if !summary.deleted_extraneous && !summary.extraneous.is_empty() {
+        StdErr::info(&format!(
+            "{} keys are only in the destination namespace, and were left as they are",
+            summary.extraneous.len()
+        ));
+    }
+
+    let deleted = if summary.deleted_extraneous {
+        summary.extraneous.len()
+    } else {
+        0
+    };
+    StdErr::success(&format!(
+        "{} keys added, {} changed, {} deleted and {} unchanged",
+        summary.added.len(),
+        summary.changed.len(),
+        deleted,
+        summary.unchanged
+    ));
+}
@@ -0,0 +1,161 @@
+// These use http::legacy_auth_client rather than cloudflare-rs: values aren't returned as
+// json, and the keys cloudflare-rs lists don't have their metadata.
+
+use cloudflare::framework::response::ApiFailure;
+use reqwest::blocking::{Client, Response};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+use anyhow::Result;
+
+use crate::commands::kv;
+use crate::kv::local::{Key, LIST_LIMIT, MIN_LIST_LIMIT};
+use crate::settings::toml::Target;
+
+#[derive(Deserialize)]
+struct KeysResponse {
+    result: Vec<Key>,
+    result_info: ResultInfo,
+}
+
+#[derive(Deserialize)]
+struct MetadataResponse {
+    result: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct ResultInfo {
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+fn namespace_url(target: &Target, namespace_id: &str) -> Result<String> {
+    Ok(format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}",
+        target.account_id.load()?,
+        namespace_id
+    ))
+}
+
+/// A page of up to `limit` keys with their expiration and metadata, and the cursor for the
+/// next page if there is one
+pub fn list_keys(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+    prefix: Option<&str>,
+    cursor: Option<&str>,
+    limit: usize,
+) -> Result<(Vec<Key>, Option<String>)> {
+    let url = format!("{}/keys", namespace_url(target, namespace_id)?);
+    let limit = limit.to_string();
+    let mut query = vec![("limit", limit.as_str())];
+    if let Some(prefix) = prefix {
+        query.push(("prefix", prefix));
+    }
+    if let Some(cursor) = cursor {
+        query.push(("cursor", cursor));
+    }
+
+    let res = check(client.get(&url).query(&query).send()?)?;
+    let page: KeysResponse = res.json()?;
+    let cursor = page.result_info.cursor.filter(|cursor| !cursor.is_empty());
+    Ok((page.result, cursor))
+}
+
+/// Every key in a namespace, or every one starting with `prefix`
+pub fn all_keys(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+    prefix: Option<&str>,
+) -> Result<Vec<Key>> {
+    let mut keys = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (page, next) = list_keys(
+            client,
+            target,
+            namespace_id,
+            prefix,
+            cursor.as_deref(),
+            LIST_LIMIT,
+        )?;
+        keys.extend(page);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return Ok(keys),
+        }
+    }
+}
+
+/// The value of a key, or `None` if it has expired or been deleted since it was listed
+pub fn get_value(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+    key: &str,
+) -> Result<Option<Vec<u8>>> {
+    let url = format!(
+        "{}/values/{}",
+        namespace_url(target, namespace_id)?,
+        kv::url_encode_key(key)
+    );
+
+    let res = client.get(&url).send()?;
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(check(res)?.bytes()?.to_vec()))
+}
+
+/// The metadata of a key, or `None` if it has none
+pub fn get_metadata(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+    key: &str,
+) -> Result<Option<Value>> {
+    let url = format!(
+        "{}/metadata/{}",
+        namespace_url(target, namespace_id)?,
+        kv::url_encode_key(key)
+    );
+
+    let res = client.get(&url).send()?;
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let res: MetadataResponse = check(res)?.json()?;
+    Ok(res.result)
+}
+
+/// A key as it's listed, with its expiration and metadata, or `None` if there's no such key
+pub fn get_key(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+    key: &str,
+) -> Result<Option<Key>> {
+    // keys are listed in order, so a key comes before every other it's a prefix of
+    let (keys, _) = list_keys(
+        client,
+        target,
+        namespace_id,
+        Some(key),
+        None,
+        MIN_LIST_LIMIT,
+    )?;
+    Ok(keys.into_iter().find(|listed| listed.name == key))
+}
+
+fn check(res: Response) -> Result<Response> {
+    let status = res.status();
+    if status.is_success() {
+        Ok(res)
+    } else {
+        let errors = res.json().unwrap_or_default();
+        anyhow::bail!("{}", kv::format_error(ApiFailure::Error(status, errors)))
+    }
+}
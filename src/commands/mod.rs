@@ -11,8 +11,10 @@ pub mod publish;
 pub mod report;
 pub mod route;
 pub mod secret;
+pub mod sites;
 pub mod subdomain;
 pub mod tail;
+pub mod triggers;
 pub mod whoami;
 
 pub use self::config::global_config;
@@ -6,14 +6,16 @@ use indicatif::{ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 
 use crate::build::build_target;
+use crate::commands::sites::Diffs;
 use crate::deploy::{self, DeploymentSet};
 use crate::http::{self, Feature};
 use crate::kv::bulk;
 use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::migrations::Migrations;
 use crate::settings::toml::Target;
 use crate::sites;
 use crate::terminal::emoji;
-use crate::terminal::message::{Message, Output, StdErr, StdOut};
+use crate::terminal::message::{Message, StdErr};
 use crate::upload;
 
 #[derive(Serialize, Deserialize, Default)]
@@ -24,19 +26,24 @@ pub struct PublishOutput {
     pub schedules: Vec<String>,
 }
 
+/// Publishes `target`, returning what was published for `--output json`
 pub fn publish(
     user: &GlobalUser,
     target: &mut Target,
     deployments: DeploymentSet,
-    out: Output,
-) -> Result<()> {
+) -> Result<PublishOutput> {
     validate_target_required_fields_present(target)?;
 
+    // Migrations from the configuration file are applied relative to the migration tag
+    // the script currently has, so look it up before building the upload form.
+    if let Some(Migrations::List { script_tag, .. }) = &mut target.migrations {
+        let client = http::legacy_auth_client(user);
+        *script_tag =
+            upload::script_migration_tag(&client, target.account_id.load()?, &target.name)?;
+    }
+
     let run_deploy = |target: &Target| match deploy::deploy(&user, &deployments) {
-        Ok(results) => {
-            build_output_message(results, target.name.clone(), out);
-            Ok(())
-        }
+        Ok(results) => Ok(build_output_message(results, target.name.clone())),
         Err(e) => Err(e),
     };
 
@@ -61,7 +68,7 @@ pub fn publish(
 
         let site_namespace = sites::add_namespace(user, target, false)?;
 
-        let (to_upload, to_delete, asset_manifest) =
+        let (to_upload, to_delete, asset_manifest, synced_keys) =
             sites::sync(target, user, &site_namespace.id, &path)?;
 
         // First, upload all existing files in bucket directory
@@ -92,7 +99,7 @@ pub fn publish(
         // Next, upload and deploy the worker with the updated asset_manifest
         upload::script(&upload_client, &target, Some(asset_manifest))?;
 
-        run_deploy(target)?;
+        let published = run_deploy(target)?;
 
         // Finally, remove any stale files
         if !to_delete.is_empty() {
@@ -120,17 +127,21 @@ pub fn publish(
                 pb.finish_with_message("Done deleting");
             }
         }
+
+        synced_keys.remember();
+        Ok(published)
     } else {
         let upload_client = http::legacy_auth_client(user);
 
         upload::script(&upload_client, &target, None)?;
-        run_deploy(target)?;
+        run_deploy(target)
     }
-
-    Ok(())
 }
 
-fn build_output_message(deploy_results: deploy::DeployResults, target_name: String, out: Output) {
+fn build_output_message(
+    deploy_results: deploy::DeployResults,
+    target_name: String,
+) -> PublishOutput {
     let deploy::DeployResults { urls, schedules } = deploy_results;
 
     let mut msg = "Successfully published your script ".to_owned();
@@ -142,14 +153,29 @@ fn build_output_message(deploy_results: deploy::DeployResults, target_name: Stri
     }
 
     StdErr::success(&msg);
-    if out == Output::Json {
-        StdOut::as_json(&PublishOutput {
-            success: true,
-            name: target_name,
-            urls,
-            schedules,
-        });
+    PublishOutput {
+        success: true,
+        name: target_name,
+        urls,
+        schedules,
+    }
+}
+
+/// Builds `target` and adds which files of its site publishing would change to `diffs`,
+/// without publishing. Only the files of a Workers Site are diffed, so a worker without one
+/// is only built.
+pub fn dry_run(user: &GlobalUser, target: &Target, diffs: &mut Diffs) -> Result<()> {
+    validate_target_required_fields_present(target)?;
+    StdErr::success(&build_target(target)?);
+
+    if target.site.is_none() {
+        StdErr::info(&format!(
+            "{} has no [site], so there are no files to diff.",
+            target.name
+        ));
+        return Ok(());
     }
+    diffs.diff(target, user)
 }
 
 // We don't want folks setting their bucket to the top level directory,
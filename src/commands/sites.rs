@@ -0,0 +1,141 @@
+use anyhow::Result;
+use number_prefix::NumberPrefix;
+use serde::Serialize;
+
+use crate::commands::publish::validate_bucket_location;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::sites::{self, DiffFile, SiteDiff};
+use crate::terminal::message::{Message, Output, StdErr, StdOut};
+
+#[derive(Serialize)]
+struct DiffOutput {
+    name: String,
+    #[serde(flatten)]
+    diff: SiteDiff,
+}
+
+/// The diffs of the sites of one or more workers. As text, each is printed as it's added;
+/// as JSON, they're printed together as one array once they all are.
+pub struct Diffs {
+    out: Output,
+    diffs: Vec<DiffOutput>,
+}
+
+impl Diffs {
+    pub fn new(out: Output) -> Self {
+        Diffs {
+            out,
+            diffs: Vec::new(),
+        }
+    }
+
+    /// Works out which files publishing the site of `target` would add, change and remove,
+    /// without changing anything. A site that's never been published has no namespace yet,
+    /// so it isn't created.
+    pub fn diff(&mut self, target: &Target, user: &GlobalUser) -> Result<()> {
+        let site = match &target.site {
+            Some(site) => site,
+            None => anyhow::bail!("There's no [site] in your configuration file to diff."),
+        };
+        validate_bucket_location(&site.bucket)?;
+
+        let namespace_id = sites::find_namespace(user, target, false)?;
+        let diff = sites::diff(target, user, namespace_id.as_deref(), &site.bucket)?;
+
+        // publishing checks the size of every file before uploading any, so these fail it
+        for reason in &diff.rejected {
+            StdErr::warn(&format!(
+                "{}; publishing will fail until it's excluded",
+                reason
+            ));
+        }
+
+        if self.out == Output::PlainText {
+            print_diff(&diff);
+        }
+        self.diffs.push(DiffOutput {
+            name: target.name.clone(),
+            diff,
+        });
+        Ok(())
+    }
+
+    /// Prints the diffs as JSON, if that's how they're printed, and fails if there were
+    /// sites to diff but publishing wouldn't change any of them
+    pub fn finish(self) -> Result<()> {
+        if self.out == Output::Json {
+            StdOut::as_json(&self.diffs);
+        }
+        let changed = self.diffs.iter().any(|output| !output.diff.is_empty());
+        if !self.diffs.is_empty() && !changed {
+            anyhow::bail!("Publishing would not change any site files.")
+        }
+        Ok(())
+    }
+}
+
+fn print_diff(diff: &SiteDiff) {
+    let sections: [(&str, &[DiffFile]); 4] = [
+        ("+", &diff.added),
+        ("~", &diff.changed),
+        ("-", &diff.removed),
+        (" ", &diff.unchanged),
+    ];
+    for (sign, files) in sections.iter() {
+        for file in files.iter() {
+            println!("{} {} ({})", sign, file.path, human_size(file.size));
+        }
+    }
+
+    if !diff.since_last_sync {
+        StdOut::info("This site hasn't been published from this machine, so files that are uploaded already count as unchanged, and none as removed.");
+    }
+    StdOut::info(&format!(
+        "{} added, {} changed, {} removed and {} unchanged: {} keys to upload and {} to delete",
+        diff.added.len(),
+        diff.changed.len(),
+        diff.removed.len(),
+        diff.unchanged.len(),
+        diff.keys_to_upload,
+        diff.keys_to_delete
+    ));
+}
+
+fn human_size(bytes: u64) -> String {
+    match NumberPrefix::binary(bytes as f64) {
+        NumberPrefix::Standalone(bytes) => format!("{} bytes", bytes),
+        NumberPrefix::Prefixed(prefix, n) => format!("{:.0} {}B", n, prefix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diffs(diffs: Vec<SiteDiff>) -> Diffs {
+        Diffs {
+            out: Output::PlainText,
+            diffs: diffs
+                .into_iter()
+                .map(|diff| DiffOutput {
+                    name: "worker".to_string(),
+                    diff,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn it_only_fails_when_there_were_sites_and_none_would_change() {
+        let changed = SiteDiff {
+            keys_to_upload: 1,
+            ..SiteDiff::default()
+        };
+
+        // none of the workers had a site
+        assert!(diffs(vec![]).finish().is_ok());
+        assert!(diffs(vec![SiteDiff::default(), changed]).finish().is_ok());
+        assert!(diffs(vec![SiteDiff::default()]).finish().is_err());
+    }
+}
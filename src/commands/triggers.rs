@@ -0,0 +1,42 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::settings::toml::Cron;
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
+
+/// Prints the next `count` times each cron fires at after `now`. `field` is where the crons
+/// are set, to point at the invalid ones.
+pub fn preview(crons: &[String], field: &str, count: usize, now: DateTime<Utc>) -> Result<()> {
+    let mut parsed = Vec::with_capacity(crons.len());
+    for (index, cron) in crons.iter().enumerate() {
+        match cron.parse::<Cron>() {
+            Ok(expression) => parsed.push((cron, expression)),
+            Err(e) => anyhow::bail!(
+                "{}.crons[{}]: invalid cron \"{}\": {}",
+                field,
+                index,
+                cron,
+                e
+            ),
+        }
+    }
+
+    if parsed.is_empty() {
+        StdOut::info(&format!("There are no crons in [{}].", field));
+        return Ok(());
+    }
+
+    for (cron, expression) in parsed {
+        println!("{}", styles::highlight(cron));
+        let mut fire_times = expression.fire_times(now).take(count).peekable();
+        if fire_times.peek().is_none() {
+            println!("  never fires");
+        }
+        for time in fire_times {
+            println!("  {}", time.format("%Y-%m-%d %H:%M UTC (%a)"));
+        }
+    }
+
+    Ok(())
+}
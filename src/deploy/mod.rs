@@ -5,6 +5,7 @@ mod zoneless;
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
 pub use schedule::ScheduleTarget;
+use serde::Serialize;
 pub use zoned::ZonedTarget;
 pub use zoneless::ZonelessTarget;
 
@@ -13,7 +14,8 @@ use crate::settings::global_user::GlobalUser;
 /// A set of deploy targets.
 pub type DeploymentSet = Vec<DeployTarget>;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum DeployTarget {
     Zoned(ZonedTarget),
     Zoneless(ZonelessTarget),
@@ -1,9 +1,11 @@
 use crate::http;
 use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Cron;
 
 use anyhow::Result;
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct ScheduleTarget {
     pub account_id: String,
     pub script_name: String,
@@ -16,8 +18,12 @@ impl ScheduleTarget {
         script_name: String,
         crons: Vec<String>,
     ) -> Result<Self> {
-        // TODO: add validation for expressions before pushing them to the API
-        // we can do this once the cron parser is open sourced
+        for (index, cron) in crons.iter().enumerate() {
+            if let Err(e) = cron.parse::<Cron>() {
+                anyhow::bail!("crons[{}]: invalid cron \"{}\": {}", index, cron, e)
+            }
+        }
+
         Ok(Self {
             account_id: account_id.unwrap_or_default(),
             script_name,
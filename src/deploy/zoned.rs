@@ -11,7 +11,7 @@ use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::{Route, RouteConfig};
 use crate::terminal::message::{Message, StdOut};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct ZonedTarget {
     pub zone_id: String,
     pub routes: Vec<Route>,
@@ -4,8 +4,9 @@ use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::RouteConfig;
 
 use anyhow::Result;
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct ZonelessTarget {
     pub account_id: String,
     pub script_name: String,
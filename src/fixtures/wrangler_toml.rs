@@ -30,6 +30,21 @@ pub struct SiteConfig {
     pub exclude: Option<Vec<&'static str>>,
 }
 
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DevConfig {
+    pub ip: Option<&'static str>,
+    pub port: Option<u16>,
+    pub local_protocol: Option<&'static str>,
+    pub upstream_protocol: Option<&'static str>,
+    pub local_runtime: Option<&'static str>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MigrationConfig {
+    pub tag: Option<&'static str>,
+    pub new_classes: Option<Vec<&'static str>>,
+}
+
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct EnvConfig {
     pub name: Option<&'static str>,
@@ -40,12 +55,16 @@ pub struct EnvConfig {
     pub zone_id: Option<&'static str>,
     pub webpack_config: Option<&'static str>,
     pub private: Option<bool>,
+    pub usage_model: Option<&'static str>,
     pub site: Option<SiteConfig>,
     #[serde(alias = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<KvConfig>>,
     pub vars: Option<HashMap<&'static str, &'static str>>,
     pub text_blobs: Option<HashMap<&'static str, &'static str>>,
+    pub wasm_modules: Option<HashMap<&'static str, &'static str>>,
     pub triggers: Option<Triggers>,
+    pub dev: Option<DevConfig>,
+    pub migrations: Option<Vec<MigrationConfig>>,
 }
 
 impl EnvConfig {
@@ -100,13 +119,17 @@ pub struct WranglerToml {
     pub zone_id: Option<&'static str>,
     pub webpack_config: Option<&'static str>,
     pub private: Option<bool>,
+    pub usage_model: Option<&'static str>,
     pub env: Option<HashMap<&'static str, EnvConfig>>,
     #[serde(alias = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<KvConfig>>,
     pub site: Option<SiteConfig>,
     pub vars: Option<HashMap<&'static str, &'static str>>,
     pub text_blobs: Option<HashMap<&'static str, &'static str>>,
+    pub wasm_modules: Option<HashMap<&'static str, &'static str>>,
     pub triggers: Option<Triggers>,
+    pub dev: Option<DevConfig>,
+    pub migrations: Option<Vec<MigrationConfig>>,
 }
 
 impl WranglerToml {
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::kv::bulk::BulkPair;
+use crate::kv::local::Key;
+
+// the API won't write keys that expire any sooner than this many seconds from now
+const MIN_EXPIRATION_TTL: i64 = 60;
+
+/// How `wrangler kv:export` lays out a namespace, and `wrangler kv:import` reads it back.
+///
+/// An NDJSON archive has a line per key, each an `Entry`. A tar archive has a pair of files
+/// per key, numbered in the order they were exported: `<n>.json` holds the `Key` itself and
+/// `<n>.value` its raw value, so values can be read without decoding them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Ndjson,
+    Tar,
+}
+
+impl Format {
+    /// The format asked for, or else the one the archive's extension suggests
+    pub fn new(format: Option<Format>, path: &Path) -> Format {
+        format.unwrap_or_else(|| match path.extension() {
+            Some(extension) if extension == "tar" => Format::Tar,
+            _ => Format::Ndjson,
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ndjson" => Ok(Format::Ndjson),
+            "tar" => Ok(Format::Tar),
+            _ => Err(anyhow!("{} is not a supported archive format", s)),
+        }
+    }
+}
+
+/// A key with its value, expiration and metadata, in the shape the bulk API takes it.
+/// Archived values are always base64 encoded, so that binary values survive the round trip.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    #[serde(default)]
+    pub base64: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_ttl: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl Entry {
+    pub fn new(key: Key, value: &[u8]) -> Entry {
+        Entry {
+            key: key.name,
+            value: base64::encode(value),
+            base64: true,
+            expiration: key.expiration,
+            expiration_ttl: None,
+            metadata: key.metadata,
+        }
+    }
+
+    /// The raw value, decoded if need be
+    pub fn value(&self) -> Result<Vec<u8>> {
+        if self.base64 {
+            base64::decode(&self.value)
+                .map_err(|e| anyhow!("The value of \"{}\" is not valid base64: {}", self.key, e))
+        } else {
+            Ok(self.value.as_bytes().to_vec())
+        }
+    }
+
+    /// Whether the key expires too soon for the API to write it
+    pub fn has_expired(&self, now: i64) -> bool {
+        self.expiration
+            .map_or(false, |expiration| expiration < now + MIN_EXPIRATION_TTL)
+    }
+
+    fn into_key(self) -> Key {
+        Key {
+            name: self.key,
+            expiration: self.expiration,
+            metadata: self.metadata,
+        }
+    }
+}
+
+impl BulkPair for Entry {
+    fn size(&self) -> usize {
+        let metadata = self.metadata.as_ref().map_or(0, |m| m.to_string().len());
+        self.key.len() + self.value.len() + metadata
+    }
+}
+
+/// Writes entries to an archive one at a time, so a namespace never has to fit in memory
+pub enum Writer<W: Write> {
+    Ndjson(W),
+    Tar {
+        builder: tar::Builder<W>,
+        count: usize,
+    },
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(out: W, format: Format) -> Writer<W> {
+        match format {
+            Format::Ndjson => Writer::Ndjson(out),
+            Format::Tar => Writer::Tar {
+                builder: tar::Builder::new(out),
+                count: 0,
+            },
+        }
+    }
+
+    pub fn write(&mut self, entry: Entry) -> Result<()> {
+        match self {
+            Writer::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, &entry)?;
+                out.write_all(b"\n")?;
+            }
+            Writer::Tar { builder, count } => {
+                let value = entry.value()?;
+                let key = serde_json::to_vec(&entry.into_key())?;
+                append(builder, &format!("{}.json", count), &key)?;
+                append(builder, &format!("{}.value", count), &value)?;
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Writer::Ndjson(mut out) => out.flush()?,
+            Writer::Tar { builder, .. } => builder.into_inner()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn append<W: Write>(builder: &mut tar::Builder<W>, path: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+/// Reads every entry in an archive, in the order they were written
+pub fn read(archive: &[u8], format: Format) -> Result<Vec<Entry>> {
+    match format {
+        Format::Ndjson => read_ndjson(archive),
+        Format::Tar => read_tar(archive),
+    }
+}
+
+fn read_ndjson(archive: &[u8]) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (i, line) in archive.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("Line {} of the archive is not a valid entry: {}", i + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn read_tar(archive: &[u8]) -> Result<Vec<Entry>> {
+    let mut keys = HashMap::new();
+    let mut values = HashMap::new();
+    for file in tar::Archive::new(archive).entries()? {
+        let mut file = file?;
+        let path = file.path()?.to_string_lossy().to_string();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let (n, extension) = path
+            .split_once('.')
+            .and_then(|(n, extension)| Some((n.parse::<usize>().ok()?, extension)))
+            .ok_or_else(|| anyhow!("{} does not belong in a namespace archive", path))?;
+        match extension {
+            "json" => {
+                let key: Key = serde_json::from_slice(&contents)
+                    .map_err(|e| anyhow!("{} is not a valid key: {}", path, e))?;
+                keys.insert(n, key);
+            }
+            "value" => {
+                values.insert(n, contents);
+            }
+            _ => anyhow::bail!("{} does not belong in a namespace archive", path),
+        }
+    }
+
+    let mut entries = Vec::new();
+    for n in 0..keys.len() {
+        let key = keys
+            .remove(&n)
+            .ok_or_else(|| anyhow!("The archive is missing {}.json", n))?;
+        let value = values
+            .remove(&n)
+            .ok_or_else(|| anyhow!("The archive is missing {}.value", n))?;
+        entries.push(Entry::new(key, &value));
+    }
+    if let Some(n) = values.keys().min() {
+        anyhow::bail!("The archive is missing {}.json", n)
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry::new(
+                Key {
+                    name: "plain".to_string(),
+                    expiration: None,
+                    metadata: None,
+                },
+                b"some text",
+            ),
+            Entry::new(
+                Key {
+                    name: "a/b".to_string(),
+                    expiration: Some(1_700_000_000),
+                    metadata: Some(json!({ "made with": "wrangler" })),
+                },
+                &[0, 159, 146, 150],
+            ),
+        ]
+    }
+
+    fn round_trip(format: Format) -> Vec<Entry> {
+        let mut writer = Writer::new(Vec::new(), format);
+        for entry in entries() {
+            writer.write(entry).unwrap();
+        }
+        let archive = match writer {
+            Writer::Ndjson(out) => out,
+            Writer::Tar { builder, .. } => builder.into_inner().unwrap(),
+        };
+        read(&archive, format).unwrap()
+    }
+
+    #[test]
+    fn it_round_trips_ndjson() {
+        assert_eq!(round_trip(Format::Ndjson), entries());
+    }
+
+    #[test]
+    fn it_round_trips_tar() {
+        assert_eq!(round_trip(Format::Tar), entries());
+    }
+
+    #[test]
+    fn it_reads_plain_values_from_ndjson() {
+        let archive = b"{\"key\":\"k\",\"value\":\"v\"}\n\n";
+        let entries = read(archive, Format::Ndjson).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value().unwrap(), b"v".to_vec());
+    }
+
+    #[test]
+    fn it_counts_keys_the_api_would_not_write_as_expired() {
+        let entry = |expiration| Entry {
+            key: "key".to_string(),
+            value: String::new(),
+            base64: true,
+            expiration,
+            expiration_ttl: None,
+            metadata: None,
+        };
+
+        assert!(!entry(None).has_expired(1000));
+        assert!(!entry(Some(1060)).has_expired(1000));
+        assert!(entry(Some(1059)).has_expired(1000));
+    }
+
+    #[test]
+    fn it_picks_a_format_from_the_extension() {
+        assert_eq!(Format::new(None, Path::new("backup.tar")), Format::Tar);
+        assert_eq!(
+            Format::new(None, Path::new("backup.ndjson")),
+            Format::Ndjson
+        );
+        assert_eq!(
+            Format::new(Some(Format::Ndjson), Path::new("backup.tar")),
+            Format::Ndjson
+        );
+    }
+}
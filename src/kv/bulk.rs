@@ -1,17 +1,23 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 use std::time::Duration;
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use indicatif::ProgressBar;
+use reqwest::blocking::Client;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
 
-use cloudflare::endpoints::workerskv::delete_bulk::DeleteBulk;
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
-use cloudflare::endpoints::workerskv::write_bulk::WriteBulk;
-use cloudflare::framework::apiclient::ApiClient;
-use cloudflare::framework::auth::Credentials;
-use cloudflare::framework::{Environment, HttpApiClient, HttpApiClientConfig};
+use cloudflare::framework::response::ApiFailure;
 
 use crate::commands::kv::format_error;
-use crate::http::feature::headers;
+use crate::http;
+use crate::kv::archive::Entry;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 
@@ -21,21 +27,93 @@ const API_MAX_PAIRS: usize = 10000;
 pub const BATCH_KEY_MAX: usize = API_MAX_PAIRS / 2;
 const UPLOAD_MAX_SIZE: usize = 50 * 1024 * 1024;
 
-// Create a special API client that has a longer timeout than usual, given that KV operations
-// can be lengthy if payloads are large.
-fn bulk_api_client(user: &GlobalUser) -> Result<HttpApiClient> {
-    let config = HttpApiClientConfig {
-        http_timeout: Duration::from_secs(5 * 60),
-        default_headers: headers(None),
-    };
+// How many batches are sent at once
+const MAX_CONCURRENT_BATCHES: usize = 4;
+// How many times a batch is sent before its keys fail, when the API is having trouble with it
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+// also the longest a Retry-After header is waited for
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// Bulk operations can be lengthy if payloads are large
+const BULK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Anything the bulk API writes, which `batch_keys_values` can batch
+pub trait BulkPair: Clone {
+    /// How many bytes the pair adds to an upload
+    fn size(&self) -> usize;
+}
+
+impl BulkPair for KeyValuePair {
+    fn size(&self) -> usize {
+        self.key.len() + self.value.len()
+    }
+}
+
+// Anything the bulk API is sent a list of, so a failure can be pinned on a key
+trait Keyed {
+    fn key(&self) -> &str;
+}
+
+impl Keyed for KeyValuePair {
+    fn key(&self) -> &str {
+        &self.key
+    }
+}
 
-    HttpApiClient::new(
-        Credentials::from(user.to_owned()),
-        config,
-        Environment::Production,
-    )
+impl Keyed for Entry {
+    fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Keyed for String {
+    fn key(&self) -> &str {
+        self
+    }
+}
+
+/// The keys a bulk operation couldn't write or delete, by the reason why
+#[derive(Debug)]
+pub struct BulkError {
+    operation: &'static str,
+    total: usize,
+    failures: BTreeMap<String, Vec<String>>,
 }
 
+// keys listed under each reason, past which the rest are only counted
+const LISTED_KEYS_MAX: usize = 10;
+
+impl BulkError {
+    /// Every key that failed
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.failures.values().flatten()
+    }
+}
+
+impl fmt::Display for BulkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to {} {} of {} keys",
+            self.operation,
+            self.keys().count(),
+            self.total
+        )?;
+        for (reason, keys) in &self.failures {
+            write!(f, "\n\n{}", reason.trim_end())?;
+            for key in keys.iter().take(LISTED_KEYS_MAX) {
+                write!(f, "\n  {}", key)?;
+            }
+            if keys.len() > LISTED_KEYS_MAX {
+                write!(f, "\n  ...and {} more", keys.len() - LISTED_KEYS_MAX)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BulkError {}
+
 pub fn put(
     target: &Target,
     user: &GlobalUser,
@@ -43,24 +121,44 @@ pub fn put(
     pairs: Vec<KeyValuePair>,
     progress_bar: &Option<ProgressBar>,
 ) -> Result<()> {
-    let client = bulk_api_client(user)?;
-
-    for b in batch_keys_values(pairs) {
-        match client.request(&WriteBulk {
-            account_identifier: target.account_id.load()?,
-            namespace_identifier: namespace_id,
-            bulk_key_value_pairs: b.to_owned(),
-        }) {
-            Ok(_) => {}
-            Err(e) => anyhow::bail!("{}", format_error(e)),
-        }
+    let request = BulkRequest::new(Method::PUT, target, user, namespace_id)?;
+    let batches = batch_keys_values(pairs).into_iter().map(Ok);
+    let sent = send_all(batches, progress_bar, move |batch| request.send(batch));
+    check("upload", sent).map(|_| ())
+}
 
-        if let Some(pb) = &progress_bar {
-            pb.inc(b.len() as u64);
-        }
-    }
+/// Like `put`, but for entries with metadata
+pub fn put_entries(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    entries: Vec<Entry>,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<()> {
+    let request = BulkRequest::new(Method::PUT, target, user, namespace_id)?;
+    let batches = batch_keys_values(entries).into_iter().map(Ok);
+    let sent = send_all(batches, progress_bar, move |batch| request.send(batch));
+    check("upload", sent).map(|_| ())
+}
 
-    Ok(())
+/// Like `put_entries`, but batches entries as they're read, so that only the batches being
+/// sent are ever in memory. Stops at the first entry that can't be read, once the ones read
+/// before it are sent. Returns how many entries were uploaded.
+pub fn put_stream<I>(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    entries: I,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<usize>
+where
+    I: Iterator<Item = Result<Entry>> + Send + 'static,
+{
+    let request = BulkRequest::new(Method::PUT, target, user, namespace_id)?;
+    let sent = send_all(Batches::new(entries), progress_bar, move |batch| {
+        request.send(batch)
+    });
+    check("upload", sent)
 }
 
 pub fn delete(
@@ -70,65 +168,420 @@ pub fn delete(
     keys: Vec<String>,
     progress_bar: &Option<ProgressBar>,
 ) -> Result<()> {
-    let client = bulk_api_client(user)?;
-
-    for b in batch_keys(keys) {
-        match client.request(&DeleteBulk {
-            account_identifier: target.account_id.load()?,
-            namespace_identifier: namespace_id,
-            bulk_keys: b.to_owned(),
-        }) {
-            Ok(_) => {}
-            Err(e) => anyhow::bail!("{}", format_error(e)),
+    let request = BulkRequest::new(Method::DELETE, target, user, namespace_id)?;
+    let batches = batch_keys(keys).into_iter().map(Ok);
+    let sent = send_all(batches, progress_bar, move |batch| request.send(batch));
+    check("delete", sent).map(|_| ())
+}
+
+// Returns how many keys were written or deleted, if all of them were
+fn check(operation: &'static str, sent: Sent) -> Result<usize> {
+    let Sent {
+        total,
+        failed,
+        read_error,
+    } = sent;
+    if let Some(e) = read_error {
+        return Err(e.context(format!(
+            "Stopped reading keys to {} after {} of them, {} of which failed",
+            operation,
+            total,
+            failed.len()
+        )));
+    }
+    if failed.is_empty() {
+        return Ok(total);
+    }
+
+    let mut failures: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, reason) in failed {
+        failures.entry(reason).or_default().push(key);
+    }
+    Err(BulkError {
+        operation,
+        total,
+        failures,
+    }
+    .into())
+}
+
+// Why a batch wasn't written
+#[derive(Debug)]
+enum Failure {
+    // worth sending again in a while: the API is rate limiting us, or is having trouble
+    Transient {
+        retry_after: Option<Duration>,
+        reason: String,
+    },
+    // something in the batch was refused, so either half of it may yet be written
+    Rejected(String),
+    // nothing will be written however it's sent, e.g. because credentials are wrong
+    Fatal(String),
+}
+
+// The bulk endpoint of a namespace, for either writing or deleting
+struct BulkRequest {
+    client: Client,
+    method: Method,
+    url: String,
+}
+
+impl BulkRequest {
+    fn new(method: Method, target: &Target, user: &GlobalUser, namespace_id: &str) -> Result<Self> {
+        let url = format!(
+            "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/bulk",
+            target.account_id.load()?,
+            namespace_id
+        );
+        Ok(BulkRequest {
+            client: http::legacy_auth_client(user),
+            method,
+            url,
+        })
+    }
+
+    // Sends a batch, trying again with exponential backoff while the failure is transient.
+    // The API having trouble says nothing about the keys, so once it's tried enough the
+    // transient failure is returned as it is, failing the whole batch rather than splitting it.
+    fn send<P: Serialize>(&self, batch: &[P]) -> Result<(), Failure> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(batch) {
+                Err(Failure::Transient {
+                    retry_after,
+                    reason,
+                }) => {
+                    attempt += 1;
+                    if attempt == MAX_ATTEMPTS {
+                        return Err(Failure::Transient {
+                            retry_after,
+                            reason,
+                        });
+                    }
+                    log::info!("retrying a bulk request: {}", reason);
+                    thread::sleep(retry_after.unwrap_or_else(|| backoff(attempt)));
+                }
+                result => return result,
+            }
         }
+    }
+
+    fn send_once<P: Serialize>(&self, batch: &[P]) -> Result<(), Failure> {
+        let res = self
+            .client
+            .request(self.method.clone(), &self.url)
+            .timeout(BULK_TIMEOUT)
+            .json(batch)
+            .send()
+            .map_err(|e| Failure::Transient {
+                retry_after: None,
+                reason: e.to_string(),
+            })?;
+
+        let status = res.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        let errors = res.json().unwrap_or_default();
+        let reason = format_error(ApiFailure::Error(status, errors));
+        Err(classify(status, retry_after, reason))
+    }
+}
 
-        if let Some(pb) = &progress_bar {
-            pb.inc(b.len() as u64);
+fn classify(status: StatusCode, retry_after: Option<Duration>, reason: String) -> Failure {
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => Failure::Transient {
+            retry_after,
+            reason,
+        },
+        status if status.is_server_error() => Failure::Transient {
+            retry_after: None,
+            reason,
+        },
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+            Failure::Fatal(reason)
         }
+        _ => Failure::Rejected(reason),
     }
+}
 
-    Ok(())
+// A Retry-After header is either a number of seconds or an HTTP date to wait until, and
+// either is capped at MAX_BACKOFF
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let retry_after = match value.parse() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            // a date that's passed already means trying again right away
+            (date.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or_default()
+        }
+    };
+    Some(retry_after.min(MAX_BACKOFF))
 }
 
-fn batch_keys_values(mut pairs: Vec<KeyValuePair>) -> Vec<Vec<KeyValuePair>> {
-    let mut batches: Vec<Vec<KeyValuePair>> = Vec::new();
+// how long to wait before the given retry: doubling each time, up to MAX_BACKOFF
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .checked_mul(2u32.saturating_pow(attempt - 1))
+        .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF))
+}
 
-    if !pairs.is_empty() {
-        // Iterate over all key-value pairs and create batches of uploads, each of which are
-        // maximum 5K key-value pairs in size OR maximum ~50MB in size. Upload each batch
-        // as it is created.
-        let mut key_count = 0;
-        let mut key_pair_bytes = 0;
-        let mut key_value_batch: Vec<KeyValuePair> = Vec::new();
+// Where batches come from: read as they're needed, and failing if the keys can't be read
+type Source<P> = Box<dyn Iterator<Item = Result<Vec<P>>> + Send>;
 
-        while !(pairs.is_empty() && key_value_batch.is_empty()) {
-            if pairs.is_empty() {
-                // Last batch to upload
-                batches.push(key_value_batch.to_vec());
-                key_value_batch.clear();
-            } else {
-                let pair = pairs.pop().unwrap();
-                if key_count + 1 > BATCH_KEY_MAX
-                // Keep upload size small to keep KV bulk API happy
-                || key_pair_bytes + pair.key.len() + pair.value.len() > UPLOAD_MAX_SIZE
-                {
-                    batches.push(key_value_batch.to_vec());
-                    key_count = 0;
-                    key_pair_bytes = 0;
-                    key_value_batch.clear();
+// The batches left to send, shared by the threads sending them
+struct Queue<P> {
+    source: Source<P>,
+    // halves of batches that were rejected, sent before any more are read
+    batches: VecDeque<Vec<P>>,
+    // batches being sent, which may yet be split into more
+    in_flight: usize,
+    // once a batch fails in a way every other one would too, the rest aren't sent
+    fatal: Option<String>,
+    // keys read from the source so far
+    total: usize,
+    failed: Vec<(String, String)>,
+    read_error: Option<anyhow::Error>,
+}
+
+impl<P> Queue<P> {
+    fn next_batch(&mut self) -> Option<Vec<P>> {
+        if let Some(batch) = self.batches.pop_front() {
+            return Some(batch);
+        }
+        if self.read_error.is_some() {
+            return None;
+        }
+        match self.source.next()? {
+            Ok(batch) => {
+                self.total += batch.len();
+                Some(batch)
+            }
+            Err(e) => {
+                self.read_error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+// What came of sending every batch
+struct Sent {
+    total: usize,
+    failed: Vec<(String, String)>,
+    read_error: Option<anyhow::Error>,
+}
+
+// Sends batches a few at a time, splitting the ones that fail in half and sending those,
+// until every key has either been sent or failed on its own. Batches are only read from
+// `batches` as there's a thread free to send them.
+fn send_all<P, I, F>(batches: I, progress_bar: &Option<ProgressBar>, send: F) -> Sent
+where
+    P: Keyed + Send + 'static,
+    I: Iterator<Item = Result<Vec<P>>> + Send + 'static,
+    F: Fn(&[P]) -> Result<(), Failure> + Send + Sync + 'static,
+{
+    let queue = Queue {
+        source: Box::new(batches),
+        batches: VecDeque::new(),
+        in_flight: 0,
+        fatal: None,
+        total: 0,
+        failed: Vec::new(),
+        read_error: None,
+    };
+    let shared = Arc::new((Mutex::new(queue), Condvar::new()));
+    let send = Arc::new(send);
+
+    let workers: Vec<_> = (0..MAX_CONCURRENT_BATCHES)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            let send = Arc::clone(&send);
+            let progress_bar = progress_bar.clone();
+            thread::spawn(move || work(&shared, &*send, &progress_bar))
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("a bulk upload thread panicked");
+    }
+
+    let mut queue = shared.0.lock().unwrap();
+    Sent {
+        total: queue.total,
+        failed: std::mem::take(&mut queue.failed),
+        read_error: queue.read_error.take(),
+    }
+}
+
+fn work<P, F>(shared: &(Mutex<Queue<P>>, Condvar), send: &F, progress_bar: &Option<ProgressBar>)
+where
+    P: Keyed,
+    F: Fn(&[P]) -> Result<(), Failure>,
+{
+    let (lock, condvar) = shared;
+    loop {
+        let (batch, fatal) = {
+            let mut queue = lock.lock().unwrap();
+            loop {
+                // reading the next batch holds the lock, but the threads waiting on it
+                // would only have been waiting for that batch anyway
+                if let Some(batch) = queue.next_batch() {
+                    queue.in_flight += 1;
+                    break (batch, queue.fatal.clone());
                 }
+                // nothing's queued, and nothing's being sent that could be split
+                if queue.in_flight == 0 {
+                    return;
+                }
+                queue = condvar.wait(queue).unwrap();
+            }
+        };
+
+        let result = match fatal {
+            Some(reason) => Err(Failure::Fatal(reason)),
+            None => send(&batch),
+        };
 
-                // Add the popped key-value pair to the running batch of key-value pair uploads
-                key_count += 1;
-                key_pair_bytes = key_pair_bytes + pair.key.len() + pair.value.len();
-                key_value_batch.push(pair);
+        let mut queue = lock.lock().unwrap();
+        match result {
+            Ok(()) => {
+                if let Some(pb) = progress_bar {
+                    pb.inc(batch.len() as u64);
+                }
+            }
+            Err(Failure::Rejected(_)) if batch.len() > 1 => {
+                let mut first = batch;
+                let second = first.split_off(first.len() / 2);
+                queue.batches.push_back(first);
+                queue.batches.push_back(second);
+            }
+            Err(failure) => {
+                let reason = match failure {
+                    Failure::Fatal(reason) => {
+                        queue.fatal = Some(reason.clone());
+                        reason
+                    }
+                    Failure::Rejected(reason) | Failure::Transient { reason, .. } => reason,
+                };
+                for pair in &batch {
+                    queue.failed.push((pair.key().to_string(), reason.clone()));
+                }
+                if let Some(pb) = progress_bar {
+                    pb.inc(batch.len() as u64);
+                }
             }
         }
+        queue.in_flight -= 1;
+        condvar.notify_all();
     }
+}
 
+pub fn batch_keys_values<P: BulkPair>(mut pairs: Vec<P>) -> Vec<Vec<P>> {
+    let mut batcher = Batcher::new();
+    let mut batches: Vec<Vec<P>> = Vec::new();
+    while let Some(pair) = pairs.pop() {
+        batches.extend(batcher.push(pair));
+    }
+    batches.extend(batcher.finish());
     batches
 }
 
+// Groups key-value pairs into batches of uploads, each of which are maximum 5K key-value
+// pairs in size OR maximum ~50MB in size.
+struct Batcher<P> {
+    batch: Vec<P>,
+    bytes: usize,
+}
+
+impl<P: BulkPair> Batcher<P> {
+    fn new() -> Self {
+        Batcher {
+            batch: Vec::new(),
+            bytes: 0,
+        }
+    }
+
+    // Adds a pair to the running batch, returning the batch before it if it's full
+    fn push(&mut self, pair: P) -> Option<Vec<P>> {
+        let full = !self.batch.is_empty()
+            && (self.batch.len() + 1 > BATCH_KEY_MAX
+                // Keep upload size small to keep KV bulk API happy
+                || self.bytes + pair.size() > UPLOAD_MAX_SIZE);
+        let batch = if full { self.finish() } else { None };
+
+        self.bytes += pair.size();
+        self.batch.push(pair);
+        batch
+    }
+
+    // The last batch, if there's anything left in it
+    fn finish(&mut self) -> Option<Vec<P>> {
+        if self.batch.is_empty() {
+            return None;
+        }
+        self.bytes = 0;
+        Some(std::mem::take(&mut self.batch))
+    }
+}
+
+// Batches pairs as they're read, until one can't be
+struct Batches<I, P> {
+    pairs: I,
+    batcher: Batcher<P>,
+    error: Option<anyhow::Error>,
+    done: bool,
+}
+
+impl<I, P: BulkPair> Batches<I, P> {
+    fn new(pairs: I) -> Self {
+        Batches {
+            pairs,
+            batcher: Batcher::new(),
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<I, P> Iterator for Batches<I, P>
+where
+    I: Iterator<Item = Result<P>>,
+    P: BulkPair,
+{
+    type Item = Result<Vec<P>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.pairs.next() {
+                Some(Ok(pair)) => {
+                    if let Some(batch) = self.batcher.push(pair) {
+                        return Some(Ok(batch));
+                    }
+                }
+                Some(Err(e)) => {
+                    self.error = Some(e);
+                    self.done = true;
+                }
+                None => self.done = true,
+            }
+        }
+        // the pairs read before one that couldn't be are still sent
+        match self.batcher.finish() {
+            Some(batch) => Some(Ok(batch)),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
 fn batch_keys(mut keys: Vec<String>) -> Vec<Vec<String>> {
     let mut batches = Vec::new();
     while !keys.is_empty() {
@@ -143,3 +596,189 @@ fn batch_keys(mut keys: Vec<String>) -> Vec<Vec<String>> {
 
     batches
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    impl BulkPair for String {
+        fn size(&self) -> usize {
+            self.len()
+        }
+    }
+
+    fn keys(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("key-{}", i)).collect()
+    }
+
+    #[test]
+    fn it_splits_batches_until_only_the_refused_keys_fail() {
+        let batches = vec![keys(8), vec!["another".to_string()]];
+        let sent = send_all(batches.into_iter().map(Ok), &None, |batch: &[String]| {
+            if batch.iter().any(|key| key == "key-5") {
+                Err(Failure::Rejected("value too large".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(sent.total, 9);
+        assert_eq!(
+            sent.failed,
+            vec![("key-5".to_string(), "value too large".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_stops_sending_after_a_fatal_failure() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let batches = (0..20).map(|_| Ok(keys(2)));
+        let failed = {
+            let sent = Arc::clone(&sent);
+            send_all(batches, &None, move |_: &[String]| {
+                sent.fetch_add(1, Ordering::SeqCst);
+                Err(Failure::Fatal("bad credentials".to_string()))
+            })
+            .failed
+        };
+
+        assert_eq!(failed.len(), 40);
+        // only batches already being sent when the first failed are sent at all
+        assert!(sent.load(Ordering::SeqCst) <= MAX_CONCURRENT_BATCHES);
+    }
+
+    #[test]
+    fn it_backs_off_exponentially() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(1));
+        assert_eq!(backoff(4), Duration::from_secs(4));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn it_waits_as_long_as_retry_after_says_up_to_a_point() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(parse_retry_after(" 3 ", now), Some(Duration::from_secs(3)));
+        assert_eq!(parse_retry_after("3600", now), Some(MAX_BACKOFF));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:10 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 08:28:00 GMT", now),
+            Some(MAX_BACKOFF)
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn it_fails_batches_the_api_keeps_having_trouble_with_without_splitting_them() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let failed = {
+            let sent = Arc::clone(&sent);
+            send_all(
+                vec![Ok(keys(8))].into_iter(),
+                &None,
+                move |_: &[String]| {
+                    sent.fetch_add(1, Ordering::SeqCst);
+                    Err(Failure::Transient {
+                        retry_after: None,
+                        reason: "service unavailable".to_string(),
+                    })
+                },
+            )
+            .failed
+        };
+
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+        assert_eq!(failed.len(), 8);
+        assert!(failed
+            .iter()
+            .all(|(_, reason)| reason == "service unavailable"));
+    }
+
+    #[test]
+    fn it_retries_rate_limits_and_server_errors() {
+        let reason = || "reason".to_string();
+        let retry_after = Some(Duration::from_secs(3));
+
+        assert!(matches!(
+            classify(StatusCode::TOO_MANY_REQUESTS, retry_after, reason()),
+            Failure::Transient { retry_after: Some(after), .. } if after == Duration::from_secs(3)
+        ));
+        assert!(matches!(
+            classify(StatusCode::BAD_GATEWAY, None, reason()),
+            Failure::Transient { .. }
+        ));
+        assert!(matches!(
+            classify(StatusCode::FORBIDDEN, None, reason()),
+            Failure::Fatal(_)
+        ));
+        assert!(matches!(
+            classify(StatusCode::BAD_REQUEST, None, reason()),
+            Failure::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn it_lists_failed_keys_by_reason() {
+        let failed = vec![
+            ("a".to_string(), "too large".to_string()),
+            ("b".to_string(), "bad metadata".to_string()),
+            ("c".to_string(), "too large".to_string()),
+        ];
+        let sent = Sent {
+            total: 10,
+            failed,
+            read_error: None,
+        };
+        let error = check("upload", sent).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to upload 3 of 10 keys\n\nbad metadata\n  b\n\ntoo large\n  a\n  c"
+        );
+    }
+
+    #[test]
+    fn it_sends_what_was_read_before_a_bad_key() {
+        let pairs = (0..12).map(|i| match i {
+            10 => Err(anyhow::anyhow!("line 11 is not valid")),
+            i => Ok(format!("key-{}", i)),
+        });
+        let sent = send_all(Batches::new(pairs), &None, |_: &[String]| Ok(()));
+
+        assert_eq!(sent.total, 10);
+        assert!(sent.failed.is_empty());
+        let error = check("upload", sent).unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "Stopped reading keys to upload after 10 of them, 0 of which failed: line 11 is not valid"
+        );
+    }
+
+    #[test]
+    fn it_batches_by_count_and_size() {
+        let batches = batch_keys_values(keys(BATCH_KEY_MAX + 1));
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), BATCH_KEY_MAX);
+        // pairs are taken from the end
+        assert_eq!(batches[1], vec!["key-0".to_string()]);
+
+        let large = "x".repeat(UPLOAD_MAX_SIZE / 2 + 1);
+        let batches = batch_keys_values(vec![large.clone(), large.clone(), large]);
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![1, 1, 1]
+        );
+    }
+}
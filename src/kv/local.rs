@@ -0,0 +1,438 @@
+use std::fs;
+use std::hash::Hasher;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use twox_hash::XxHash64;
+
+use crate::sites::{KEY_MAX_SIZE, VALUE_MAX_SIZE};
+
+/// The most keys a page of `Store::list` holds, as for the API
+pub const LIST_LIMIT: usize = 1000;
+/// The fewest keys a page can be asked to hold
+pub const MIN_LIST_LIMIT: usize = 10;
+
+// the API won't expire keys any sooner than this many seconds from now
+const MIN_EXPIRATION_TTL: i64 = 60;
+const METADATA_MAX_SIZE: usize = 1024;
+
+/// A key as the API lists it
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Key {
+    pub name: String,
+    /// When the key expires, in seconds since the UNIX epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PutOptions {
+    pub expiration: Option<i64>,
+    pub expiration_ttl: Option<i64>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// A page of keys, with the cursor to pass to `Store::list` for the next one if there is
+/// one
+#[derive(Debug, PartialEq)]
+pub struct KeyPage {
+    pub keys: Vec<Key>,
+    pub cursor: Option<String>,
+}
+
+/// KV namespaces kept in a directory, which behave like the namespaces behind the API:
+/// keys list in order, in pages with a cursor for the next, and are gone once they expire.
+/// `wrangler dev --local` binds its namespaces to one, and it lets tests use KV without
+/// an account.
+///
+/// Each namespace is a directory, and each key in it a pair of files named after a hash of
+/// the key: `<hash>.value` holds the value, and `<hash>.json` the `Key` itself, since keys
+/// can be longer than a file name can.
+#[derive(Clone, Debug)]
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Store {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.get_with_key(namespace, key)?.map(|(value, _)| value))
+    }
+
+    /// The value of a key along with its expiration and metadata
+    pub fn get_with_key(&self, namespace: &str, key: &str) -> Result<Option<(Vec<u8>, Key)>> {
+        self.get_at(namespace, key, Utc::now().timestamp())
+    }
+
+    pub fn put(&self, namespace: &str, key: &str, value: &[u8], options: PutOptions) -> Result<()> {
+        self.put_at(namespace, key, value, options, Utc::now().timestamp())
+    }
+
+    pub fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        let dir = self.namespace_dir(namespace)?;
+        for path in &[dir.join(key_file(key)), dir.join(value_file(key))] {
+            if let Err(e) = fs::remove_file(path) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The keys in a namespace that start with `prefix`, in order, up to `limit` of them
+    /// after the `cursor` of a previous page
+    pub fn list(
+        &self,
+        namespace: &str,
+        prefix: Option<&str>,
+        limit: Option<usize>,
+        cursor: Option<&str>,
+    ) -> Result<KeyPage> {
+        self.list_at(namespace, prefix, limit, cursor, Utc::now().timestamp())
+    }
+
+    fn get_at(&self, namespace: &str, key: &str, now: i64) -> Result<Option<(Vec<u8>, Key)>> {
+        let dir = self.namespace_dir(namespace)?;
+        let stored = match read_key(&dir.join(key_file(key)))? {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        if is_expired(&stored, now) {
+            self.delete(namespace, key)?;
+            return Ok(None);
+        }
+
+        match fs::read(dir.join(value_file(key))) {
+            Ok(value) => Ok(Some((value, stored))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put_at(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &[u8],
+        options: PutOptions,
+        now: i64,
+    ) -> Result<()> {
+        validate_key(key)?;
+        if value.len() as u64 > VALUE_MAX_SIZE {
+            anyhow::bail!(
+                "The value of `{}` is {} bytes, but values can be at most {} bytes",
+                key,
+                value.len(),
+                VALUE_MAX_SIZE
+            )
+        }
+        if let Some(metadata) = &options.metadata {
+            let size = serde_json::to_string(metadata)?.len();
+            if size > METADATA_MAX_SIZE {
+                anyhow::bail!(
+                    "The metadata of `{}` is {} bytes, but metadata can be at most {} bytes",
+                    key,
+                    size,
+                    METADATA_MAX_SIZE
+                )
+            }
+        }
+
+        let stored = Key {
+            name: key.to_string(),
+            expiration: expiration(&options, now)?,
+            metadata: options.metadata,
+        };
+
+        let dir = self.namespace_dir(namespace)?;
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(value_file(key)), value)?;
+        fs::write(dir.join(key_file(key)), serde_json::to_vec(&stored)?)?;
+        Ok(())
+    }
+
+    fn list_at(
+        &self,
+        namespace: &str,
+        prefix: Option<&str>,
+        limit: Option<usize>,
+        cursor: Option<&str>,
+        now: i64,
+    ) -> Result<KeyPage> {
+        let limit = limit.unwrap_or(LIST_LIMIT);
+        if !(MIN_LIST_LIMIT..=LIST_LIMIT).contains(&limit) {
+            anyhow::bail!(
+                "The limit must be between {} and {}",
+                MIN_LIST_LIMIT,
+                LIST_LIMIT
+            )
+        }
+        let after = cursor.map(decode_cursor).transpose()?;
+
+        let dir = self.namespace_dir(namespace)?;
+        let mut keys = Vec::new();
+        if dir.is_dir() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path
+                    .extension()
+                    .map_or(true, |extension| extension != "json")
+                {
+                    continue;
+                }
+                if let Some(key) = read_key(&path)? {
+                    if is_expired(&key, now) {
+                        self.delete(namespace, &key.name)?;
+                    } else if key.name.starts_with(prefix.unwrap_or(""))
+                        && after.as_ref().map_or(true, |after| &key.name > after)
+                    {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let cursor = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().map(|key| base64::encode(&key.name))
+        } else {
+            None
+        };
+
+        Ok(KeyPage { keys, cursor })
+    }
+
+    fn namespace_dir(&self, namespace: &str) -> Result<PathBuf> {
+        let is_valid = !namespace.is_empty()
+            && namespace
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !is_valid {
+            anyhow::bail!("\"{}\" is not a valid namespace", namespace)
+        }
+        Ok(self.dir.join(namespace))
+    }
+}
+
+fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key == "." || key == ".." {
+        anyhow::bail!("\"{}\" is not a valid key", key)
+    }
+    if key.len() > KEY_MAX_SIZE {
+        anyhow::bail!(
+            "Key `{}` of {} bytes exceeds the maximum key size limit of {} bytes",
+            key,
+            key.len(),
+            KEY_MAX_SIZE
+        )
+    }
+    Ok(())
+}
+
+// works out when a key expires, rejecting the same expirations the API does
+fn expiration(options: &PutOptions, now: i64) -> Result<Option<i64>> {
+    match (options.expiration, options.expiration_ttl) {
+        (Some(_), Some(_)) => {
+            anyhow::bail!("Only one of expiration and expiration_ttl can be set")
+        }
+        (Some(expiration), None) if expiration < now + MIN_EXPIRATION_TTL => anyhow::bail!(
+            "Invalid expiration of {}. Expiration times must be at least {} seconds in the future",
+            expiration,
+            MIN_EXPIRATION_TTL
+        ),
+        (None, Some(ttl)) if ttl < MIN_EXPIRATION_TTL => anyhow::bail!(
+            "Invalid expiration_ttl of {}. Expiration TTLs must be at least {}",
+            ttl,
+            MIN_EXPIRATION_TTL
+        ),
+        (expiration, ttl) => Ok(expiration.or_else(|| ttl.map(|ttl| now + ttl))),
+    }
+}
+
+fn is_expired(key: &Key, now: i64) -> bool {
+    key.expiration.map_or(false, |expiration| expiration <= now)
+}
+
+fn decode_cursor(cursor: &str) -> Result<String> {
+    let invalid = || anyhow::anyhow!("\"{}\" is not a valid cursor", cursor);
+    let name = base64::decode(cursor).map_err(|_| invalid())?;
+    String::from_utf8(name).map_err(|_| invalid())
+}
+
+fn read_key(path: &Path) -> Result<Option<Key>> {
+    match fs::read(path) {
+        Ok(key) => Ok(Some(serde_json::from_slice(&key)?)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn file_stem(key: &str) -> String {
+    let mut hasher = XxHash64::default();
+    hasher.write(key.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+fn value_file(key: &str) -> String {
+    format!("{}.value", file_stem(key))
+}
+
+fn key_file(key: &str) -> String {
+    format!("{}.json", file_stem(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    const NOW: i64 = 1_600_000_000;
+
+    fn store() -> (tempfile::TempDir, Store) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        (dir, store)
+    }
+
+    fn names(page: &KeyPage) -> Vec<&str> {
+        page.keys.iter().map(|key| key.name.as_str()).collect()
+    }
+
+    #[test]
+    fn it_stores_keys_by_namespace() {
+        let (_dir, store) = store();
+        let long_key = "a/".repeat(KEY_MAX_SIZE / 2);
+
+        store
+            .put("NS", &long_key, b"1", PutOptions::default())
+            .unwrap();
+        store.put("NS", "b", b"2", PutOptions::default()).unwrap();
+        store
+            .put("OTHER", "b", b"3", PutOptions::default())
+            .unwrap();
+
+        assert_eq!(store.get("NS", &long_key).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(store.get("NS", "b").unwrap(), Some(b"2".to_vec()));
+
+        store.delete("NS", "b").unwrap();
+        store.delete("NS", "missing").unwrap();
+        assert_eq!(store.get("NS", "b").unwrap(), None);
+        assert_eq!(store.get("OTHER", "b").unwrap(), Some(b"3".to_vec()));
+
+        assert!(store
+            .put("../NS", "key", b"", PutOptions::default())
+            .is_err());
+        assert!(store.put("NS", "..", b"", PutOptions::default()).is_err());
+        assert!(store.get("", "key").is_err());
+    }
+
+    #[test]
+    fn it_lists_keys_in_pages() {
+        let (_dir, store) = store();
+        for i in (0..25).rev() {
+            let key = format!("key-{:02}", i);
+            store.put("NS", &key, b"", PutOptions::default()).unwrap();
+        }
+        store
+            .put("NS", "other", b"", PutOptions::default())
+            .unwrap();
+
+        let first = store.list("NS", Some("key-"), Some(10), None).unwrap();
+        assert_eq!(names(&first)[..2], ["key-00", "key-01"]);
+        assert_eq!(first.keys.len(), 10);
+
+        let second = store
+            .list("NS", Some("key-"), Some(10), first.cursor.as_deref())
+            .unwrap();
+        assert_eq!(names(&second)[0], "key-10");
+
+        let last = store
+            .list("NS", Some("key-"), Some(10), second.cursor.as_deref())
+            .unwrap();
+        assert_eq!(
+            names(&last),
+            ["key-20", "key-21", "key-22", "key-23", "key-24"]
+        );
+        assert_eq!(last.cursor, None);
+
+        assert_eq!(store.list("NS", None, None, None).unwrap().keys.len(), 26);
+        assert!(store.list("NS", None, Some(LIST_LIMIT + 1), None).is_err());
+        assert!(store.list("NS", None, None, Some("not a cursor")).is_err());
+    }
+
+    #[test]
+    fn it_expires_keys() {
+        let (_dir, store) = store();
+        let ttl = PutOptions {
+            expiration_ttl: Some(60),
+            ..Default::default()
+        };
+        let at = PutOptions {
+            expiration: Some(NOW + 120),
+            metadata: Some(json!({ "made with": "wrangler" })),
+            ..Default::default()
+        };
+        store.put_at("NS", "ttl", b"1", ttl, NOW).unwrap();
+        store.put_at("NS", "at", b"2", at, NOW).unwrap();
+
+        let (_, key) = store.get_at("NS", "at", NOW).unwrap().unwrap();
+        assert_eq!(key.expiration, Some(NOW + 120));
+        assert_eq!(key.metadata, Some(json!({ "made with": "wrangler" })));
+
+        let page = store.list_at("NS", None, None, None, NOW + 60).unwrap();
+        assert_eq!(names(&page), ["at"]);
+        assert_eq!(store.get_at("NS", "ttl", NOW + 60).unwrap(), None);
+        assert_eq!(store.get_at("NS", "at", NOW + 120).unwrap(), None);
+    }
+
+    #[test]
+    fn it_rejects_what_the_api_rejects() {
+        let (_dir, store) = store();
+        let put = |options| store.put_at("NS", "key", b"", options, NOW);
+
+        assert!(put(PutOptions {
+            expiration: Some(NOW + 59),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(put(PutOptions {
+            expiration_ttl: Some(30),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(put(PutOptions {
+            expiration: Some(NOW + 60),
+            expiration_ttl: Some(60),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(put(PutOptions {
+            metadata: Some(json!("a".repeat(METADATA_MAX_SIZE))),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(store
+            .put_at(
+                "NS",
+                &"a".repeat(KEY_MAX_SIZE + 1),
+                b"",
+                PutOptions::default(),
+                NOW
+            )
+            .is_err());
+    }
+}
@@ -1,3 +1,5 @@
+pub mod archive;
 pub mod bulk;
 pub mod key;
+pub mod local;
 pub mod namespace;
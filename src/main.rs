@@ -58,18 +58,24 @@ fn run() -> Result<()> {
     let cli_params = cli.clone();
 
     match cli.command {
-        Command::Config { api_key, no_verify } => exec::configure(api_key, no_verify),
+        Command::Config {
+            subcommand: Some(subcommand),
+            ..
+        } => exec::config(subcommand, &cli_params),
+        Command::Config {
+            api_key, no_verify, ..
+        } => exec::configure(api_key, no_verify),
         Command::Generate {
             name,
             site,
             template,
             target_type,
-        } => exec::generate(name, site, template, target_type),
+        } => exec::generate(name, site, template, target_type, &cli_params),
         Command::Init {
             name,
             site,
             target_type,
-        } => exec::init(name, site, target_type),
+        } => exec::init(name, site, target_type, &cli_params),
         Command::Build => exec::build(&cli_params),
         Command::Preview {
             method,
@@ -84,31 +90,48 @@ fn run() -> Result<()> {
             port,
             local_protocol,
             upstream_protocol,
+            test_scheduled,
+            run_crons,
+            local,
+            kv_persist,
+            workers,
         } => exec::dev(
             host,
             ip,
             port,
             local_protocol,
             upstream_protocol,
+            test_scheduled,
+            run_crons,
+            local,
+            kv_persist,
+            workers,
             &cli_params,
         ),
         Command::Whoami => exec::whoami(),
         Command::Publish {
             release,
             output,
+            dry_run,
             migration,
-        } => exec::publish(release, output, migration, &cli_params),
+            workers,
+        } => exec::publish(release, output, dry_run, migration, workers, &cli_params),
         Command::Subdomain { name } => exec::subdomain(name, &cli_params),
         Command::Route(route) => exec::route(route, &cli_params),
+        Command::Triggers(triggers) => exec::triggers(triggers, &cli_params),
+        Command::Sites(sites) => exec::sites(sites, &cli_params),
         Command::Secret(secret) => exec::secret(secret, &cli_params),
         Command::KvNamespace(namespace) => exec::kv_namespace(namespace, &cli_params),
         Command::KvKey(key) => exec::kv_key(key, &cli_params),
         Command::KvBulk(bulk) => exec::kv_bulk(bulk, &cli_params),
+        Command::KvExport(export) => exec::kv_export(export, &cli_params),
+        Command::KvImport(import) => exec::kv_import(import, &cli_params),
         Command::Tail {
             format,
             tunnel_port,
             metrics_port,
-        } => exec::tail(format, tunnel_port, metrics_port, &cli_params),
+            workers,
+        } => exec::tail(format, tunnel_port, metrics_port, workers, &cli_params),
         Command::Login => commands::login::run(),
         Command::Report { log } => commands::report::run(log.as_deref()).map(|_| {
             eprintln!("Report submission sucessful. Thank you!");
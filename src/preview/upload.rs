@@ -63,7 +63,7 @@ pub fn upload(
                     let site_namespace = add_namespace(user, target, true)?;
 
                     let path = Path::new(&site_config.bucket);
-                    let (to_upload, to_delete, asset_manifest) =
+                    let (to_upload, to_delete, asset_manifest, synced_keys) =
                         sync(target, user, &site_namespace.id, path)?;
 
                     // First, upload all existing files in given directory
@@ -81,6 +81,7 @@ pub fn upload(
 
                         bulk::delete(target, user, &site_namespace.id, to_delete, &None)?;
                     }
+                    synced_keys.remember();
 
                     preview
                 } else {
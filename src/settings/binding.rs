@@ -26,6 +26,10 @@ pub enum Binding {
         name: String,
         text: String,
     },
+    Json {
+        name: String,
+        json: serde_json::Value,
+    },
 }
 
 impl Binding {
@@ -56,4 +60,8 @@ impl Binding {
     pub fn new_plain_text(name: String, text: String) -> Binding {
         Binding::PlainText { name, text }
     }
+
+    pub fn new_json(name: String, json: serde_json::Value) -> Binding {
+        Binding::Json { name, json }
+    }
 }
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use config::{ConfigError, Source, Value};
 
 const PREFIX_PATTERN: &str = "CF_";
@@ -62,6 +64,77 @@ impl Source for Environment {
     }
 }
 
+const DOTENV_FILE: &str = ".env";
+
+/// Variables available to `${VAR}` interpolation in a project's configuration file.
+/// Unlike `Environment`, this isn't limited to a whitelist of `CF_*` credentials: any
+/// variable from the process environment can be used, falling back to the values in an
+/// optional `.env` file next to the configuration file.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectEnvironment {
+    dotenv: HashMap<String, String>,
+}
+
+impl ProjectEnvironment {
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let dotenv_path = project_dir.join(DOTENV_FILE);
+        if !dotenv_path.is_file() {
+            return Ok(ProjectEnvironment::default());
+        }
+
+        log::info!("Loading variables from {}", dotenv_path.display());
+        let contents = fs::read_to_string(&dotenv_path)?;
+        let dotenv = parse_dotenv(&contents)
+            .map_err(|e| anyhow!("Could not parse {}: {}", dotenv_path.display(), e))?;
+
+        Ok(ProjectEnvironment { dotenv })
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        env::var(name)
+            .ok()
+            .or_else(|| self.dotenv.get(name).cloned())
+    }
+}
+
+// Parses the common subset of the .env format: `KEY=VALUE` lines, optionally prefixed
+// with `export`, with `#` comments and single or double quoted values.
+fn parse_dotenv(contents: &str) -> Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {} should look like KEY=VALUE", index + 1))?;
+        let key = key.trim();
+        let value = value.trim();
+
+        let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            value[1..value.len() - 1].replace("\\n", "\n")
+        } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+            value[1..value.len() - 1].to_string()
+        } else {
+            // unquoted values may be followed by a comment
+            value
+                .split(" #")
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+
+        vars.insert(key.to_string(), value);
+    }
+
+    Ok(vars)
+}
+
 #[derive(Clone, Debug, Default)]
 #[cfg(test)]
 pub struct MockEnvironment {
@@ -141,4 +214,52 @@ mod tests {
 
         assert_eq!(environment.collect().unwrap(), expected_env_vars);
     }
+
+    #[test]
+    fn it_parses_dotenv_files() {
+        let contents = r#"
+# account for local development
+export ACCOUNT_ID=abc123
+ZONE_ID = def456 # staging zone
+HOSTNAME="dev.example.com"
+GREETING='hello # world'
+EMPTY=
+"#;
+        let vars = parse_dotenv(contents).unwrap();
+
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["ACCOUNT_ID"], "abc123");
+        assert_eq!(vars["ZONE_ID"], "def456");
+        assert_eq!(vars["HOSTNAME"], "dev.example.com");
+        assert_eq!(vars["GREETING"], "hello # world");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn it_prefers_the_process_environment_over_dotenv() {
+        let project_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            project_dir.path().join(DOTENV_FILE),
+            "WRANGLER_TEST_DOTENV_ONLY=from-file\nWRANGLER_TEST_DOTENV_BOTH=from-file\n",
+        )
+        .unwrap();
+        env::set_var("WRANGLER_TEST_DOTENV_BOTH", "from-process");
+
+        let environment = ProjectEnvironment::load(project_dir.path()).unwrap();
+
+        assert_eq!(
+            environment.var("WRANGLER_TEST_DOTENV_ONLY"),
+            Some("from-file".to_string())
+        );
+        assert_eq!(
+            environment.var("WRANGLER_TEST_DOTENV_BOTH"),
+            Some("from-process".to_string())
+        );
+        assert_eq!(environment.var("WRANGLER_TEST_DOTENV_NEITHER"), None);
+    }
+
+    #[test]
+    fn it_rejects_malformed_dotenv_lines() {
+        assert!(parse_dotenv("ACCOUNT_ID").is_err());
+    }
 }
@@ -4,5 +4,5 @@ mod global_config;
 pub mod global_user;
 pub mod toml;
 
-pub use environment::{Environment, QueryEnvironment};
+pub use environment::{Environment, ProjectEnvironment, QueryEnvironment};
 pub use global_config::{get_global_config_path, get_wrangler_home_dir, DEFAULT_CONFIG_FILE_NAME};
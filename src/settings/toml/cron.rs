@@ -0,0 +1,450 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+
+// A valid cron that could fire at all fires within this many years, which a cron for
+// February 29th needs
+const SEARCH_YEARS: i32 = 8;
+
+/// A cron expression in the dialect accepted by Cron Triggers:
+/// `minute hour day-of-month month day-of-week`, where months may be given
+/// as `JAN`-`DEC` and days of the week as `1`-`7` or `SUN`-`SAT` (Sunday is 1).
+/// The day-of-month field additionally accepts `L`, `LW` and `<day>W`, and the
+/// day-of-week field accepts `<day>L` and `<day>#<n>`. When both the day-of-month and
+/// day-of-week fields are restricted, a day matching either one fires.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cron {
+    minutes: Option<BTreeSet<u32>>,
+    hours: Option<BTreeSet<u32>>,
+    days_of_month: DaysOfMonth,
+    months: Option<BTreeSet<u32>>,
+    days_of_week: DaysOfWeek,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum DaysOfMonth {
+    Any,
+    Days(BTreeSet<u32>),
+    Last,
+    LastWeekday,
+    NearestWeekday(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum DaysOfWeek {
+    Any,
+    Days(BTreeSet<u32>),
+    Last(u32),
+    Nth(u32, u32),
+}
+
+#[derive(Clone, Copy)]
+struct Unit {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+}
+
+const MINUTE: Unit = Unit {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+
+const HOUR: Unit = Unit {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+
+const DAY_OF_MONTH: Unit = Unit {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+
+const MONTH: Unit = Unit {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &[
+        "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+    ],
+};
+
+const DAY_OF_WEEK: Unit = Unit {
+    name: "day of week",
+    min: 1,
+    max: 7,
+    names: &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"],
+};
+
+impl Unit {
+    fn parse_value(self, value: &str) -> Result<u32> {
+        let parsed = match self
+            .names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+        {
+            Some(index) => index as u32 + self.min,
+            None => value
+                .parse()
+                .map_err(|_| anyhow!("\"{}\" is not a valid {}", value, self.name))?,
+        };
+
+        if parsed < self.min || parsed > self.max {
+            anyhow::bail!(
+                "{} {} is out of range; it must be between {} and {}",
+                self.name,
+                parsed,
+                self.min,
+                self.max
+            )
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parses a list of values, ranges and steps. Returns `None` for `*`.
+    fn parse_set(self, field: &str) -> Result<Option<BTreeSet<u32>>> {
+        if field == "*" {
+            return Ok(None);
+        }
+
+        let mut values = BTreeSet::new();
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => match step.parse::<usize>() {
+                    Ok(step) if step > 0 => (range, Some(step)),
+                    _ => anyhow::bail!("\"{}\" is not a valid step", step),
+                },
+                None => (part, None),
+            };
+
+            let (start, end) = if range == "*" {
+                (self.min, self.max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (self.parse_value(start)?, self.parse_value(end)?)
+            } else {
+                let start = self.parse_value(range)?;
+                // `5/15` means "every 15 starting at 5"
+                (start, if step.is_some() { self.max } else { start })
+            };
+
+            if start > end {
+                anyhow::bail!("\"{}\" is not a valid {} range", range, self.name)
+            }
+
+            values.extend((start..=end).step_by(step.unwrap_or(1)));
+        }
+
+        Ok(Some(values))
+    }
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            anyhow::bail!(
+                "expected 5 fields (minute, hour, day of month, month, day of week), found {}",
+                fields.len()
+            )
+        }
+
+        Ok(Cron {
+            minutes: MINUTE.parse_set(fields[0])?,
+            hours: HOUR.parse_set(fields[1])?,
+            days_of_month: parse_days_of_month(fields[2])?,
+            months: MONTH.parse_set(fields[3])?,
+            days_of_week: parse_days_of_week(fields[4])?,
+        })
+    }
+}
+
+impl Cron {
+    /// The times this cron fires at after `after`, in UTC. Stops early for crons that can
+    /// never fire, like `0 0 30 FEB *`.
+    pub fn fire_times(&self, after: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        let mut after = after;
+        std::iter::from_fn(move || {
+            let next = self.next_after(after)?;
+            after = next;
+            Some(next)
+        })
+    }
+
+    /// The first time after `after` that this cron fires at, in UTC.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let after = after.naive_utc();
+        let start = after.date();
+        let end = NaiveDate::from_ymd(start.year() + SEARCH_YEARS, start.month(), 1);
+
+        let mut date = start;
+        while date < end {
+            if self.fires_on(date) {
+                let earliest = if date == start {
+                    Some(after.time())
+                } else {
+                    None
+                };
+                if let Some(time) = self.first_time(earliest) {
+                    return Some(DateTime::from_utc(date.and_time(time), Utc));
+                }
+            }
+            date = date.succ();
+        }
+
+        None
+    }
+
+    // The first minute of a day this cron fires at, after `earliest` if given
+    fn first_time(&self, earliest: Option<NaiveTime>) -> Option<NaiveTime> {
+        (0..24)
+            .filter(|hour| contains(&self.hours, *hour))
+            .flat_map(|hour| {
+                (0..60)
+                    .filter(move |minute| contains(&self.minutes, *minute))
+                    .map(move |minute| NaiveTime::from_hms(hour, minute, 0))
+            })
+            .find(|time| earliest.map_or(true, |earliest| *time > earliest))
+    }
+
+    fn fires_on(&self, date: NaiveDate) -> bool {
+        if !contains(&self.months, date.month()) {
+            return false;
+        }
+
+        match (&self.days_of_month, &self.days_of_week) {
+            (DaysOfMonth::Any, DaysOfWeek::Any) => true,
+            (days_of_month, DaysOfWeek::Any) => days_of_month.contains(date),
+            (DaysOfMonth::Any, days_of_week) => days_of_week.contains(date),
+            (days_of_month, days_of_week) => {
+                days_of_month.contains(date) || days_of_week.contains(date)
+            }
+        }
+    }
+}
+
+impl DaysOfMonth {
+    fn contains(&self, date: NaiveDate) -> bool {
+        let day = date.day();
+        let last = last_day_of_month(date);
+        match self {
+            DaysOfMonth::Any => true,
+            DaysOfMonth::Days(days) => days.contains(&day),
+            DaysOfMonth::Last => day == last,
+            DaysOfMonth::LastWeekday => day == nearest_weekday(date, last),
+            DaysOfMonth::NearestWeekday(target) => {
+                *target <= last && day == nearest_weekday(date, *target)
+            }
+        }
+    }
+}
+
+impl DaysOfWeek {
+    fn contains(&self, date: NaiveDate) -> bool {
+        // Sunday is 1
+        let weekday = date.weekday().num_days_from_sunday() + 1;
+        match self {
+            DaysOfWeek::Any => true,
+            DaysOfWeek::Days(days) => days.contains(&weekday),
+            DaysOfWeek::Last(day) => *day == weekday && date.day() + 7 > last_day_of_month(date),
+            DaysOfWeek::Nth(day, nth) => *day == weekday && (date.day() - 1) / 7 + 1 == *nth,
+        }
+    }
+}
+
+fn contains(values: &Option<BTreeSet<u32>>, value: u32) -> bool {
+    values
+        .as_ref()
+        .map_or(true, |values| values.contains(&value))
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let first_of_next = match date.month() {
+        12 => NaiveDate::from_ymd(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd(date.year(), month + 1, 1),
+    };
+    (first_of_next - Duration::days(1)).day()
+}
+
+// The weekday closest to `day` in the month of `date`, without crossing into another month
+fn nearest_weekday(date: NaiveDate, day: u32) -> u32 {
+    let last = last_day_of_month(date);
+    match date.with_day(day).map(|target| target.weekday()) {
+        Some(Weekday::Sat) if day == 1 => 3,
+        Some(Weekday::Sat) => day - 1,
+        Some(Weekday::Sun) if day == last => day - 2,
+        Some(Weekday::Sun) => day + 1,
+        _ => day,
+    }
+}
+
+fn parse_days_of_month(field: &str) -> Result<DaysOfMonth> {
+    let upper = field.to_ascii_uppercase();
+    match upper.as_str() {
+        "L" => Ok(DaysOfMonth::Last),
+        "LW" => Ok(DaysOfMonth::LastWeekday),
+        _ => match upper.strip_suffix('W') {
+            Some(day) => Ok(DaysOfMonth::NearestWeekday(DAY_OF_MONTH.parse_value(day)?)),
+            None => Ok(match DAY_OF_MONTH.parse_set(field)? {
+                Some(days) => DaysOfMonth::Days(days),
+                None => DaysOfMonth::Any,
+            }),
+        },
+    }
+}
+
+fn parse_days_of_week(field: &str) -> Result<DaysOfWeek> {
+    if let Some((day, nth)) = field.split_once('#') {
+        let day = DAY_OF_WEEK.parse_value(day)?;
+        return match nth.parse() {
+            Ok(nth) if (1..=5).contains(&nth) => Ok(DaysOfWeek::Nth(day, nth)),
+            _ => anyhow::bail!("\"{}\" is not a valid week of the month", nth),
+        };
+    }
+
+    // `L` on its own is not a day of the week, but `SATL` and `7L` are.
+    if field.len() > 1 {
+        if let Some(day) = field.strip_suffix('L').or_else(|| field.strip_suffix('l')) {
+            return Ok(DaysOfWeek::Last(DAY_OF_WEEK.parse_value(day)?));
+        }
+    }
+
+    Ok(match DAY_OF_WEEK.parse_set(field)? {
+        Some(days) => DaysOfWeek::Days(days),
+        None => DaysOfWeek::Any,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_valid_crons() {
+        let valid = vec![
+            "* * * * *",
+            "*/30 * * * *",
+            "0 17 * * sun",
+            "10 7 * * mon-fri",
+            "0 0 L * *",
+            "0 0 LW * *",
+            "0 0 15W * *",
+            "0 0 * JAN,JUL 6L",
+            "0 0 * * 2#3",
+            "5/15 1-5,20 1,15 */2 *",
+        ];
+        for cron in valid {
+            assert!(cron.parse::<Cron>().is_ok(), "{} should be valid", cron);
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_crons() {
+        let invalid = vec![
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 0",
+            "* * * * FUN",
+            "*/0 * * * *",
+            "10-5 * * * *",
+            "* * * * 2#6",
+            "1,,2 * * * *",
+        ];
+        for cron in invalid {
+            assert!(cron.parse::<Cron>().is_err(), "{} should be invalid", cron);
+        }
+    }
+
+    fn fire_times(cron: &str, after: &str, count: usize) -> Vec<String> {
+        let cron: Cron = cron.parse().unwrap();
+        let after = DateTime::parse_from_rfc3339(after)
+            .unwrap()
+            .with_timezone(&Utc);
+        cron.fire_times(after)
+            .take(count)
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_finds_the_next_fire_times() {
+        assert_eq!(
+            fire_times("*/30 * * * *", "2021-06-01T10:15:30Z", 3),
+            vec!["2021-06-01 10:30", "2021-06-01 11:00", "2021-06-01 11:30"]
+        );
+        assert_eq!(
+            fire_times("0 0 * * *", "2021-12-31T00:00:00Z", 2),
+            vec!["2022-01-01 00:00", "2022-01-02 00:00"]
+        );
+        assert_eq!(
+            fire_times("0 17 * * MON-FRI", "2021-06-04T18:00:00Z", 2),
+            vec!["2021-06-07 17:00", "2021-06-08 17:00"]
+        );
+        assert_eq!(
+            fire_times("0 0 29 FEB *", "2021-01-01T00:00:00Z", 1),
+            vec!["2024-02-29 00:00"]
+        );
+        assert_eq!(
+            fire_times("0 0 30 FEB *", "2021-01-01T00:00:00Z", 1).len(),
+            0
+        );
+    }
+
+    #[test]
+    fn it_finds_fire_times_with_special_days() {
+        // the last day of the month
+        assert_eq!(
+            fire_times("0 0 L * *", "2021-02-01T00:00:00Z", 2),
+            vec!["2021-02-28 00:00", "2021-03-31 00:00"]
+        );
+        // the last weekday of July 2021 is Friday the 30th
+        assert_eq!(
+            fire_times("0 0 LW * *", "2021-07-01T00:00:00Z", 1),
+            vec!["2021-07-30 00:00"]
+        );
+        // May 1st 2021 is a Saturday, so the nearest weekday in May is Monday the 3rd
+        assert_eq!(
+            fire_times("0 0 1W * *", "2021-04-15T00:00:00Z", 2),
+            vec!["2021-05-03 00:00", "2021-06-01 00:00"]
+        );
+        // the last Friday of June 2021
+        assert_eq!(
+            fire_times("0 0 * * 6L", "2021-06-01T00:00:00Z", 1),
+            vec!["2021-06-25 00:00"]
+        );
+        // the second Tuesday of June 2021
+        assert_eq!(
+            fire_times("0 0 * * 3#2", "2021-06-01T00:00:00Z", 1),
+            vec!["2021-06-08 00:00"]
+        );
+        // either the 1st of the month or a Sunday
+        assert_eq!(
+            fire_times("0 0 1 * SUN", "2021-05-29T00:00:00Z", 3),
+            vec!["2021-05-30 00:00", "2021-06-01 00:00", "2021-06-06 00:00"]
+        );
+    }
+
+    #[test]
+    fn it_expands_ranges_and_steps() {
+        let cron: Cron = "5/20 1-3 * * *".parse().unwrap();
+        assert_eq!(cron.minutes, Some(vec![5, 25, 45].into_iter().collect()));
+        assert_eq!(cron.hours, Some(vec![1, 2, 3].into_iter().collect()));
+        assert_eq!(cron.days_of_month, DaysOfMonth::Any);
+    }
+}
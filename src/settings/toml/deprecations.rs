@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use toml_edit::{Array, Document, InlineTable, Item, Table, Value};
+
+use crate::settings::toml::Manifest;
+
+/// The result of rewriting deprecated constructs in a configuration file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rewrite {
+    pub toml: String,
+    /// A description of each change that was made
+    pub changes: Vec<String>,
+    /// Deprecated constructs that couldn't be rewritten automatically
+    pub conflicts: Vec<String>,
+}
+
+/// Rewrites deprecated wrangler.toml constructs into their current forms:
+///
+/// * `kv-namespaces` becomes `kv_namespaces`
+/// * `durable_objects.bindings` becomes `durable_objects.classes`
+/// * `name` in a Durable Object class becomes `binding`
+/// * a missing `type` becomes an explicit `type = "webpack"`, which was the implied default
+/// * `webpack_config` is removed when the project isn't a webpack project, since it's ignored
+/// * `private` is removed, since it no longer has any effect
+///
+/// Edits are made with toml_edit so that comments and formatting elsewhere in the file are
+/// preserved.
+pub fn rewrite(source: &str) -> Result<Rewrite> {
+    let mut document = source
+        .parse::<Document>()
+        .map_err(|e| anyhow!("toml_edit failed to parse your configuration file. {}", e))?;
+    let mut rewrite = Rewrite::default();
+
+    if !document.as_table().contains_key("type") {
+        document["type"] = toml_edit::value("webpack");
+        rewrite
+            .changes
+            .push("added `type = \"webpack\"`, which was previously implied".to_string());
+    }
+    let is_webpack = document["type"].as_str() == Some("webpack");
+
+    rewrite_table(document.as_table_mut(), "", is_webpack, &mut rewrite);
+
+    if let Some(environments) = document
+        .as_table_mut()
+        .get_mut("env")
+        .and_then(Item::as_table_mut)
+    {
+        let names: Vec<String> = environments
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for name in names {
+            if let Some(environment) = environments.get_mut(&name).and_then(Item::as_table_mut) {
+                let prefix = format!("env.{}.", name);
+                rewrite_table(environment, &prefix, is_webpack, &mut rewrite);
+            }
+        }
+    }
+
+    if rewrite.changes.is_empty() {
+        rewrite.toml = source.to_string();
+        return Ok(rewrite);
+    }
+
+    rewrite.toml = document.to_string();
+
+    // make sure we never hand back something wrangler can't read
+    toml::from_str::<Manifest>(&rewrite.toml)
+        .map_err(|e| anyhow!("The rewritten configuration file could not be read: {}", e))?;
+
+    Ok(rewrite)
+}
+
+fn rewrite_table(table: &mut Table, prefix: &str, is_webpack: bool, rewrite: &mut Rewrite) {
+    if table.remove("private").is_some() {
+        rewrite
+            .changes
+            .push(format!("removed `{}private`, which has no effect", prefix));
+    }
+
+    if !is_webpack && table.remove("webpack_config").is_some() {
+        rewrite.changes.push(format!(
+            "removed `{}webpack_config`, which is only used when `type = \"webpack\"`",
+            prefix
+        ));
+    }
+
+    rename_key(table, "kv-namespaces", "kv_namespaces", prefix, rewrite);
+
+    let prefix = format!("{}durable_objects.", prefix);
+    match table.get_mut("durable_objects") {
+        Some(Item::Table(durable_objects)) => {
+            rename_key(durable_objects, "bindings", "classes", &prefix, rewrite);
+            match durable_objects.get_mut("classes") {
+                Some(Item::ArrayOfTables(classes)) => {
+                    for index in 0..classes.len() {
+                        if let Some(class) = classes.get_mut(index) {
+                            let prefix = format!("{}classes[{}].", prefix, index);
+                            rename_key(class, "name", "binding", &prefix, rewrite);
+                        }
+                    }
+                }
+                Some(Item::Value(Value::Array(classes))) => {
+                    let prefix = format!("{}classes", prefix);
+                    rename_inline_class_keys(classes, &prefix, rewrite);
+                }
+                _ => {}
+            }
+        }
+        Some(Item::Value(Value::InlineTable(durable_objects))) => {
+            rename_inline_key(durable_objects, "bindings", "classes", &prefix, rewrite);
+            if let Some(Value::Array(classes)) = durable_objects.get_mut("classes") {
+                let prefix = format!("{}classes", prefix);
+                rename_inline_class_keys(classes, &prefix, rewrite);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn rename_key(table: &mut Table, from: &str, to: &str, prefix: &str, rewrite: &mut Rewrite) {
+    if !table.contains_key(from) {
+        return;
+    }
+
+    if table.contains_key(to) {
+        rewrite.conflicts.push(format!(
+            "`{}{}` and `{}{}` are both set; remove one of them",
+            prefix, from, prefix, to
+        ));
+    } else if let Some(item) = table.remove(from) {
+        table[to] = item;
+        rewrite.changes.push(format!(
+            "renamed `{}{}` to `{}{}`",
+            prefix, from, prefix, to
+        ));
+    }
+}
+
+fn rename_inline_key(
+    table: &mut InlineTable,
+    from: &str,
+    to: &str,
+    prefix: &str,
+    rewrite: &mut Rewrite,
+) {
+    if !table.contains_key(from) {
+        return;
+    }
+
+    if table.contains_key(to) {
+        rewrite.conflicts.push(format!(
+            "`{}{}` and `{}{}` are both set; remove one of them",
+            prefix, from, prefix, to
+        ));
+    } else if let Some(value) = table.remove(from) {
+        table.get_or_insert(to, value);
+        rewrite.changes.push(format!(
+            "renamed `{}{}` to `{}{}`",
+            prefix, from, prefix, to
+        ));
+    }
+}
+
+// `classes = [{ name = "..." }]`, where the array has to be rebuilt to change its tables
+fn rename_inline_class_keys(classes: &mut Array, prefix: &str, rewrite: &mut Rewrite) {
+    let needs_rename = classes.iter().any(|class| {
+        class
+            .as_inline_table()
+            .map_or(false, |class| class.contains_key("name"))
+    });
+    if !needs_rename {
+        return;
+    }
+
+    let mut rewritten = Array::default();
+    for (index, class) in classes.iter().enumerate() {
+        let mut class = class.clone();
+        if let Some(table) = class.as_inline_table_mut() {
+            let prefix = format!("{}[{}].", prefix, index);
+            rename_inline_key(table, "name", "binding", &prefix, rewrite);
+        }
+        rewritten.push_formatted(class);
+    }
+    *classes = rewritten;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_rewrites_deprecated_constructs_and_keeps_comments() {
+        let source = r#"# my worker
+name = "worker"
+account_id = "abc" # the team account
+private = false
+webpack_config = "webpack.config.js"
+
+# namespaces for the top level
+[[kv-namespaces]]
+binding = "KV"
+id = "1"
+
+[durable_objects]
+bindings = [{ name = "COUNTER", class_name = "Counter" }]
+
+[env.staging]
+private = true
+kv-namespaces = [{ binding = "KV", id = "2" }]
+
+# staging objects
+[[env.staging.durable_objects.classes]]
+name = "COUNTER"
+class_name = "Counter"
+"#;
+        let rewrite = rewrite(source).unwrap();
+
+        assert_eq!(
+            rewrite.changes,
+            vec![
+                "added `type = \"webpack\"`, which was previously implied",
+                "removed `private`, which has no effect",
+                "renamed `kv-namespaces` to `kv_namespaces`",
+                "renamed `durable_objects.bindings` to `durable_objects.classes`",
+                "renamed `durable_objects.classes[0].name` to `durable_objects.classes[0].binding`",
+                "removed `env.staging.private`, which has no effect",
+                "renamed `env.staging.kv-namespaces` to `env.staging.kv_namespaces`",
+                "renamed `env.staging.durable_objects.classes[0].name` to `env.staging.durable_objects.classes[0].binding`",
+            ]
+        );
+        assert!(rewrite.conflicts.is_empty());
+
+        let toml = &rewrite.toml;
+        assert!(toml.contains("# my worker"));
+        assert!(toml.contains("# the team account"));
+        assert!(toml.contains("# staging objects"));
+        assert!(toml.contains("webpack_config = \"webpack.config.js\""));
+        assert!(!toml.contains("private"));
+        assert!(!toml.contains("kv-namespaces"));
+        assert!(!toml.contains("name = \"COUNTER\""));
+
+        let manifest: Manifest = toml::from_str(toml).unwrap();
+        let environment = manifest.get_environment(Some("staging")).unwrap().unwrap();
+        assert!(environment.kv_namespaces.is_some());
+        assert_eq!(
+            manifest.durable_objects.unwrap().classes.unwrap()[0].binding,
+            "COUNTER"
+        );
+    }
+
+    #[test]
+    fn it_removes_webpack_config_from_other_project_types() {
+        let source =
+            "name = \"worker\"\ntype = \"javascript\"\nwebpack_config = \"webpack.config.js\"\n";
+        let rewrite = rewrite(source).unwrap();
+
+        assert_eq!(rewrite.toml, "name = \"worker\"\ntype = \"javascript\"\n");
+    }
+
+    #[test]
+    fn it_leaves_current_configs_untouched() {
+        let source = "# comment\nname = \"worker\"\ntype = \"javascript\"\n\n[[kv_namespaces]]\nbinding = \"KV\"\nid = \"1\"\n";
+        let rewrite = rewrite(source).unwrap();
+
+        assert!(rewrite.changes.is_empty());
+        assert_eq!(rewrite.toml, source);
+    }
+
+    #[test]
+    fn it_reports_conflicting_aliases() {
+        let source =
+            "name = \"worker\"\ntype = \"webpack\"\nkv_namespaces = []\nkv-namespaces = []\n";
+        let rewrite = rewrite(source).unwrap();
+
+        assert!(rewrite.changes.is_empty());
+        assert_eq!(
+            rewrite.conflicts,
+            vec!["`kv-namespaces` and `kv_namespaces` are both set; remove one of them"]
+        );
+    }
+}
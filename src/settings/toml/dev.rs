@@ -9,4 +9,22 @@ pub struct Dev {
     pub port: Option<u16>,
     pub local_protocol: Option<Protocol>,
     pub upstream_protocol: Option<Protocol>,
+    /// The command `wrangler dev --local` runs the Worker with
+    pub local_runtime: Option<String>,
+}
+
+impl Dev {
+    /// Layers the fields set in `overrides` over these ones.
+    pub fn merge(&self, overrides: &Dev) -> Dev {
+        Dev {
+            ip: overrides.ip.or(self.ip),
+            port: overrides.port.or(self.port),
+            local_protocol: overrides.local_protocol.or(self.local_protocol),
+            upstream_protocol: overrides.upstream_protocol.or(self.upstream_protocol),
+            local_runtime: overrides
+                .local_runtime
+                .clone()
+                .or_else(|| self.local_runtime.clone()),
+        }
+    }
 }
@@ -4,9 +4,12 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use serde_with::rust::string_empty_as_none;
 
+use super::UsageModel;
 use crate::settings::toml::builder::Builder;
+use crate::settings::toml::dev::Dev;
 use crate::settings::toml::durable_objects::DurableObjects;
 use crate::settings::toml::kv_namespace::ConfigKvNamespace;
+use crate::settings::toml::migrations::MigrationConfig;
 use crate::settings::toml::route::RouteConfig;
 use crate::settings::toml::site::Site;
 use crate::settings::toml::triggers::Triggers;
@@ -26,12 +29,17 @@ pub struct Environment {
     pub build: Option<Builder>,
     pub private: Option<bool>,
     pub site: Option<Site>,
+    pub dev: Option<Dev>,
     #[serde(alias = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<ConfigKvNamespace>>,
-    pub vars: Option<HashMap<String, String>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
+    pub wasm_modules: Option<HashMap<String, PathBuf>>,
     pub triggers: Option<Triggers>,
     pub durable_objects: Option<DurableObjects>,
+    #[serde(default, with = "string_empty_as_none")]
+    pub usage_model: Option<UsageModel>,
+    pub migrations: Option<Vec<MigrationConfig>>,
 }
 
 impl Environment {
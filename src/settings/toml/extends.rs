@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use toml::value::{Table, Value};
+
+use crate::settings::toml::format::ConfigFormat;
+use crate::settings::toml::interpolate::interpolate;
+use crate::settings::ProjectEnvironment;
+
+// Fields an environment doesn't inherit from the top level, so they aren't inherited
+// from a base file either
+const NOT_INHERITED: &[&str] = &["kv_namespaces", "kv-namespaces", "durable_objects", "vars"];
+
+// Tables whose entries are merged key by key, like they are for environments
+const MERGED: &[&str] = &["text_blobs", "wasm_modules", "dev"];
+
+/// Reads a configuration file as TOML, substituting `${VAR}`s and following its `extends`
+/// chain. A file that extends another is layered over it with the same rules an
+/// `[env.*]` uses to inherit from the top level (see `Manifest::get_target`), and the
+/// `[env.*]` tables of both files are layered over each other one environment at a time.
+/// `extends` paths are relative to the file that contains them; other paths in a base file
+/// are used as written, relative to the project that extends it.
+pub fn resolve(config_path: &Path) -> Result<String> {
+    let source = read(config_path)?;
+    // a file that doesn't parse is left for the caller to report the error of
+    let extends =
+        toml::from_str::<Table>(&source).map_or(false, |document| document.contains_key("extends"));
+    if !extends {
+        return Ok(source);
+    }
+
+    let document = load(config_path, source, &mut Vec::new())?;
+    Ok(toml::to_string(&Value::Table(document))?)
+}
+
+fn read(config_path: &Path) -> Result<String> {
+    // `${VAR}`s come from the environment and the .env file next to each file
+    let project_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+    let environment = ProjectEnvironment::load(project_dir)?;
+    let source = fs::read_to_string(config_path)
+        .map_err(|e| anyhow!("Error reading {}: {}", config_path.display(), e))?;
+
+    ConfigFormat::from_path(config_path)
+        .to_toml(&source)
+        .and_then(|source| interpolate(&source, &environment))
+        .map_err(|e| anyhow!("Error reading {}: {}", config_path.display(), e))
+}
+
+fn load(config_path: &Path, source: String, chain: &mut Vec<PathBuf>) -> Result<Table> {
+    let canonical = config_path
+        .canonicalize()
+        .unwrap_or_else(|_| config_path.to_path_buf());
+    if chain.contains(&canonical) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .chain(Some(&canonical))
+            .map(|path| path.display().to_string())
+            .collect();
+        anyhow::bail!(
+            "`extends` forms a cycle; the configuration files extend each other in a loop:\n  {}",
+            cycle.join("\n  -> ")
+        )
+    }
+    chain.push(canonical);
+
+    let mut document = match toml::from_str(&source)
+        .map_err(|e| anyhow!("Error reading {}: {}", config_path.display(), e))?
+    {
+        Value::Table(document) => document,
+        _ => unreachable!("a TOML document is always a table"),
+    };
+
+    let base = match document.remove("extends") {
+        None => return Ok(document),
+        Some(Value::String(base)) => base,
+        Some(other) => anyhow::bail!(
+            "`extends` in {} must be a path to another configuration file, found a {}",
+            config_path.display(),
+            other.type_str()
+        ),
+    };
+
+    let base_path = config_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(base);
+    let base_source = read(&base_path)?;
+    let base = load(&base_path, base_source, chain)?;
+
+    Ok(extend(base, document))
+}
+
+fn extend(mut base: Table, document: Table) -> Table {
+    for key in NOT_INHERITED {
+        base.remove(*key);
+    }
+
+    for (key, value) in document {
+        let value = match (base.remove(&key), value) {
+            (Some(Value::Table(base_entries)), Value::Table(entries))
+                if MERGED.contains(&key.as_str()) =>
+            {
+                let mut merged = base_entries;
+                merged.extend(entries);
+                Value::Table(merged)
+            }
+            (Some(Value::Table(base_environments)), Value::Table(environments)) if key == "env" => {
+                let mut merged = base_environments;
+                for (name, environment) in environments {
+                    let environment = match (merged.remove(&name), environment) {
+                        (Some(Value::Table(base)), Value::Table(environment)) => {
+                            Value::Table(extend(base, environment))
+                        }
+                        (_, environment) => environment,
+                    };
+                    merged.insert(name, environment);
+                }
+                Value::Table(merged)
+            }
+            (_, value) => value,
+        };
+        base.insert(key, value);
+    }
+
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn it_layers_a_manifest_over_its_base() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "base.toml",
+            r#"
+type = "javascript"
+account_id = "account"
+zone_id = "zone"
+vars = { SHARED = "no" }
+text_blobs = { A = "a.txt", B = "b.txt" }
+
+[triggers]
+crons = ["0 * * * *"]
+
+[env.staging]
+account_id = "staging-account"
+zone_id = "staging-zone"
+"#,
+        );
+        let config = write(
+            dir.path(),
+            "worker/wrangler.toml",
+            r#"
+extends = "../base.toml"
+name = "worker"
+text_blobs = { B = "other.txt" }
+
+[env.staging]
+route = "staging.example.com/*"
+"#,
+        );
+
+        let resolved: Value = toml::from_str(&resolve(&config).unwrap()).unwrap();
+
+        assert_eq!(resolved["name"].as_str(), Some("worker"));
+        assert_eq!(resolved["account_id"].as_str(), Some("account"));
+        assert_eq!(resolved["triggers"]["crons"][0].as_str(), Some("0 * * * *"));
+        assert_eq!(resolved.get("vars"), None);
+        assert_eq!(resolved.get("extends"), None);
+        assert_eq!(resolved["text_blobs"]["A"].as_str(), Some("a.txt"));
+        assert_eq!(resolved["text_blobs"]["B"].as_str(), Some("other.txt"));
+
+        let staging = &resolved["env"]["staging"];
+        assert_eq!(staging["zone_id"].as_str(), Some("staging-zone"));
+        assert_eq!(staging["route"].as_str(), Some("staging.example.com/*"));
+    }
+
+    #[test]
+    fn it_detects_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.toml", "extends = \"b.toml\"\n");
+        write(dir.path(), "b.toml", "extends = \"c.toml\"\n");
+        let config = write(
+            dir.path(),
+            "c.toml",
+            "extends = \"a.toml\"\ntype = \"webpack\"\n",
+        );
+
+        let error = resolve(&config).unwrap_err().to_string();
+        assert!(error.contains("cycle"), "{}", error);
+    }
+
+    #[test]
+    fn it_leaves_manifests_without_a_base_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "# comment\nname = \"worker\"\ntype = \"webpack\"\n";
+        let config = write(dir.path(), "wrangler.toml", source);
+
+        assert_eq!(resolve(&config).unwrap(), source);
+
+        // only a top-level `extends` names a base
+        let source = "name = \"extends-demo\"\n\n[vars]\nextends = \"base.toml\"\n";
+        let config = write(dir.path(), "wrangler.toml", source);
+        assert_eq!(resolve(&config).unwrap(), source);
+    }
+}
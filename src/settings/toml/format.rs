@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+/// The file formats a configuration file can be written in. All of them are read into
+/// the same `Manifest`; JSON files are converted to TOML before being parsed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    /// JSON with `//` and `/* */` comments and trailing commas
+    Jsonc,
+}
+
+impl ConfigFormat {
+    /// Picks a format from the extension of a configuration file, defaulting to TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            Some(extension) if extension.eq_ignore_ascii_case("jsonc") => ConfigFormat::Jsonc,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => "wrangler.toml",
+            ConfigFormat::Json => "wrangler.json",
+            ConfigFormat::Jsonc => "wrangler.jsonc",
+        }
+    }
+
+    /// Converts the contents of a configuration file in this format to TOML.
+    /// `null`s in JSON objects are treated as if the key was left out.
+    pub fn to_toml(self, source: &str) -> Result<String> {
+        let json = match self {
+            ConfigFormat::Toml => return Ok(source.to_string()),
+            ConfigFormat::Json => source.to_string(),
+            ConfigFormat::Jsonc => strip_jsonc(source),
+        };
+
+        let mut value: serde_json::Value = serde_json::from_str(&json)?;
+        if !value.is_object() {
+            anyhow::bail!("the configuration must be a JSON object")
+        }
+        remove_nulls(&mut value);
+
+        let value: toml::Value = serde_json::from_value(value)
+            .map_err(|e| anyhow!("could not convert the configuration to TOML: {}", e))?;
+        Ok(toml::to_string(&value)?)
+    }
+
+    /// Converts TOML into the contents of a configuration file in this format.
+    pub fn render(self, toml: &str) -> Result<String> {
+        match self {
+            ConfigFormat::Toml => Ok(toml.to_string()),
+            ConfigFormat::Json | ConfigFormat::Jsonc => {
+                let value: toml::Value = toml::from_str(toml)?;
+                let mut json = serde_json::to_string_pretty(&value)?;
+                json.push('\n');
+                Ok(json)
+            }
+        }
+    }
+}
+
+fn remove_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            let nulls: Vec<String> = object
+                .iter()
+                .filter(|(_, value)| value.is_null())
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in nulls {
+                object.remove(&key);
+            }
+            object.values_mut().for_each(remove_nulls);
+        }
+        serde_json::Value::Array(array) => array.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+// Removes comments and trailing commas so that serde_json can read the rest. Anything removed
+// is replaced with whitespace, so line and column numbers in errors still point at the source.
+fn strip_jsonc(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut output = String::with_capacity(source.len());
+    // where the last comma was written, until something other than whitespace follows it
+    let mut last_comma = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '"' => {
+                last_comma = None;
+                output.push('"');
+                i += 1;
+                while i < chars.len() {
+                    output.push(chars[i]);
+                    match chars[i] {
+                        '\\' if i + 1 < chars.len() => {
+                            output.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        '"' => {
+                            i += 1;
+                            break;
+                        }
+                        _ => i += 1,
+                    }
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    output.push(' ');
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                output.push_str("  ");
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    output.push(if chars[i] == '\n' { '\n' } else { ' ' });
+                    i += 1;
+                }
+                if i < chars.len() {
+                    output.push_str("  ");
+                    i += 2;
+                }
+            }
+            c => {
+                if c == '}' || c == ']' {
+                    if let Some(comma) = last_comma {
+                        output.replace_range(comma..comma + 1, " ");
+                    }
+                }
+                if c == ',' {
+                    last_comma = Some(output.len());
+                } else if !c.is_whitespace() {
+                    last_comma = None;
+                }
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_detects_the_format_from_the_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("wrangler.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config/worker.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("wrangler.JSONC")),
+            ConfigFormat::Jsonc
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("wrangler")),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn it_converts_jsonc_to_toml() {
+        let source = r#"{
+    // the worker's name
+    "name": "worker", /* inline */
+    "type": "javascript",
+    "route": null,
+    "vars": { "URL": "https://example.com/*not a comment*/", "QUOTE": "\"//\"", },
+    "kv_namespaces": [
+        { "binding": "KV", "id": "1" },
+    ],
+}
+"#;
+        let toml = ConfigFormat::Jsonc.to_toml(source).unwrap();
+        let value: toml::Value = toml::from_str(&toml).unwrap();
+
+        assert_eq!(value["name"].as_str(), Some("worker"));
+        assert_eq!(value.get("route"), None);
+        assert_eq!(
+            value["vars"]["URL"].as_str(),
+            Some("https://example.com/*not a comment*/")
+        );
+        assert_eq!(value["vars"]["QUOTE"].as_str(), Some("\"//\""));
+        assert_eq!(value["kv_namespaces"][0]["id"].as_str(), Some("1"));
+    }
+
+    #[test]
+    fn it_keeps_line_numbers_in_jsonc_errors() {
+        let source = "{\n  /* a\n  comment */\n  \"name\": worker\n}";
+        let error = ConfigFormat::Jsonc.to_toml(source).unwrap_err().to_string();
+
+        assert!(error.contains("line 4"), "{}", error);
+    }
+
+    #[test]
+    fn it_round_trips_through_json() {
+        let toml = "name = \"worker\"\ntype = \"webpack\"\n\n[site]\nbucket = \"public\"\n";
+        let json = ConfigFormat::Json.render(toml).unwrap();
+
+        assert!(json.contains("\"bucket\": \"public\""));
+        assert_eq!(ConfigFormat::Json.to_toml(&json).unwrap(), toml);
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result};
+use toml::Value;
+
+use crate::settings::ProjectEnvironment;
+
+/// Substitutes `${VAR}` and `${VAR:-default}` in every string value of a TOML document.
+/// A default is used when the variable is unset or empty, and `$${` escapes a literal `${`.
+/// Keys and comments are left alone; documents without any `${` are returned untouched.
+pub fn interpolate(source: &str, environment: &ProjectEnvironment) -> Result<String> {
+    if !source.contains("${") {
+        return Ok(source.to_string());
+    }
+
+    let mut document: Value = toml::from_str(source)?;
+    let mut errors = Vec::new();
+    interpolate_value(&mut document, "", environment, &mut errors);
+
+    if !errors.is_empty() {
+        anyhow::bail!("Could not substitute variables:\n{}", errors.join("\n"))
+    }
+
+    Ok(toml::to_string(&document)?)
+}
+
+fn interpolate_value(
+    value: &mut Value,
+    path: &str,
+    environment: &ProjectEnvironment,
+    errors: &mut Vec<String>,
+) {
+    match value {
+        Value::String(string) => match interpolate_str(string, environment) {
+            Ok(interpolated) => *string = interpolated,
+            Err(e) => errors.push(format!("  {}: {}", path, e)),
+        },
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let item_path = format!("{}[{}]", path, index);
+                interpolate_value(item, &item_path, environment, errors);
+            }
+        }
+        Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let item_path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                interpolate_value(item, &item_path, environment, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn interpolate_str(input: &str, environment: &ProjectEnvironment) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(escaped) = after.strip_prefix("${") {
+            output.push_str("${");
+            rest = escaped;
+            continue;
+        }
+
+        let expression = match after.strip_prefix('{') {
+            Some(expression) => expression,
+            None => {
+                output.push('$');
+                rest = after;
+                continue;
+            }
+        };
+
+        let end = expression
+            .find('}')
+            .ok_or_else(|| anyhow!("`${{` is never closed in \"{}\"", input))?;
+        let (name, default) = match expression[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&expression[..end], None),
+        };
+
+        let is_valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_name {
+            anyhow::bail!("`{}` is not a valid variable name", name)
+        }
+
+        let value = match (environment.var(name), default) {
+            (Some(value), Some(_)) if !value.is_empty() => value,
+            (Some(value), None) => value,
+            (_, Some(default)) => default.to_string(),
+            (None, None) => anyhow::bail!(
+                "`{}` is not set; set it in your environment or .env file, or give it a default with `${{{}:-default}}`",
+                name,
+                name
+            ),
+        };
+
+        output.push_str(&value);
+        rest = &expression[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn it_substitutes_variables_and_defaults() {
+        env::set_var("WRANGLER_TEST_INTERPOLATE_ZONE", "zone123");
+        env::set_var("WRANGLER_TEST_INTERPOLATE_EMPTY", "");
+        let environment = ProjectEnvironment::default();
+
+        let source = r#"
+name = "worker"
+zone_id = "${WRANGLER_TEST_INTERPOLATE_ZONE}"
+route = "${WRANGLER_TEST_INTERPOLATE_UNSET:-example.com}/*"
+routes = ["${WRANGLER_TEST_INTERPOLATE_EMPTY:-fallback.com}/*", "$${LITERAL}", "cost: $5"]
+"#;
+        let interpolated: Value =
+            toml::from_str(&interpolate(source, &environment).unwrap()).unwrap();
+
+        assert_eq!(interpolated["zone_id"].as_str(), Some("zone123"));
+        assert_eq!(interpolated["route"].as_str(), Some("example.com/*"));
+        assert_eq!(interpolated["routes"][0].as_str(), Some("fallback.com/*"));
+        assert_eq!(interpolated["routes"][1].as_str(), Some("${LITERAL}"));
+        assert_eq!(interpolated["routes"][2].as_str(), Some("cost: $5"));
+    }
+
+    #[test]
+    fn it_leaves_documents_without_variables_alone() {
+        let source = "# a comment\nname = \"worker\"\n";
+        let environment = ProjectEnvironment::default();
+
+        assert_eq!(interpolate(source, &environment).unwrap(), source);
+    }
+
+    #[test]
+    fn it_reports_the_path_of_unset_variables() {
+        let source = r#"
+name = "worker"
+[env.staging]
+zone_id = "${WRANGLER_TEST_INTERPOLATE_MISSING}"
+[[env.staging.kv_namespaces]]
+binding = "KV"
+id = "${WRANGLER_TEST_INTERPOLATE_ALSO_MISSING"
+"#;
+        let environment = ProjectEnvironment::default();
+        let error = interpolate(source, &environment).unwrap_err().to_string();
+
+        assert!(
+            error.contains("env.staging.zone_id: `WRANGLER_TEST_INTERPOLATE_MISSING` is not set")
+        );
+        assert!(error.contains("env.staging.kv_namespaces[0].id: `${` is never closed"));
+    }
+}
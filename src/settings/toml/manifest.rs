@@ -4,7 +4,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use config::{Config, File};
+use config::{Config, File, FileFormat};
 
 use anyhow::{anyhow, Result};
 use once_cell::sync::OnceCell;
@@ -20,11 +20,15 @@ use crate::settings::toml::builder::Builder;
 use crate::settings::toml::dev::Dev;
 use crate::settings::toml::durable_objects::DurableObjects;
 use crate::settings::toml::environment::Environment;
+use crate::settings::toml::extends;
+use crate::settings::toml::format::ConfigFormat;
 use crate::settings::toml::kv_namespace::{ConfigKvNamespace, KvNamespace};
+use crate::settings::toml::migrations::{MigrationConfig, Migrations};
 use crate::settings::toml::route::RouteConfig;
 use crate::settings::toml::site::Site;
 use crate::settings::toml::target_type::TargetType;
 use crate::settings::toml::triggers::Triggers;
+use crate::settings::toml::workers::Worker;
 use crate::settings::toml::Target;
 use crate::terminal::{
     emoji,
@@ -56,17 +60,20 @@ pub struct Manifest {
     #[serde(alias = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<ConfigKvNamespace>>,
     pub env: Option<HashMap<String, Environment>>,
-    pub vars: Option<HashMap<String, String>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
     pub wasm_modules: Option<HashMap<String, PathBuf>>,
     pub triggers: Option<Triggers>,
     pub durable_objects: Option<DurableObjects>,
     #[serde(default, with = "string_empty_as_none")]
     pub usage_model: Option<UsageModel>,
+    pub migrations: Option<Vec<MigrationConfig>>,
+    pub workers: Option<Vec<Worker>>,
 }
 
 impl Manifest {
     pub fn new(config_path: &Path) -> Result<Self> {
+        let config_path = &find_config(config_path);
         let file_name = config_path.file_name().unwrap().to_str().unwrap();
         let mut message = format!("{} not found", file_name);
         if config_path.to_str().unwrap() == DEFAULT_CONFIG_PATH {
@@ -87,6 +94,9 @@ impl Manifest {
         };
 
         check_for_duplicate_names(&manifest)?;
+        for worker in manifest.all_workers()? {
+            check_for_duplicate_names(&worker)?;
+        }
 
         Ok(manifest)
     }
@@ -96,9 +106,11 @@ impl Manifest {
         target_type: Option<TargetType>,
         config_path: &Path,
         site: Option<Site>,
+        format: ConfigFormat,
     ) -> Result<Manifest> {
-        let config_file = &config_path.join("wrangler.toml");
-        let config_template_str = fs::read_to_string(config_file).unwrap_or_else(|err| {
+        let template_file = &config_path.join("wrangler.toml");
+        let config_file = &config_path.join(format.file_name());
+        let config_template_str = fs::read_to_string(template_file).unwrap_or_else(|err| {
             log::info!("Error reading config template: {}", err);
             log::info!("Using default instead");
             toml::to_string_pretty(&Manifest::default())
@@ -144,6 +156,7 @@ impl Manifest {
         }
         if let Some(site) = site {
             if config_template.site.is_none() {
+                config_template_doc["site"] = toml_edit::table();
                 config_template_doc["site"]["bucket"] =
                     toml_edit::value(site.bucket.to_string_lossy().as_ref());
 
@@ -154,14 +167,14 @@ impl Manifest {
                 if let Some(include) = &site.include {
                     let mut arr = toml_edit::Array::default();
                     include.iter().for_each(|i| {
-                        arr.push(i.as_ref()).unwrap();
+                        arr.push(i.as_str());
                     });
                     config_template_doc["site"]["include"] = toml_edit::value(arr);
                 }
                 if let Some(exclude) = &site.exclude {
                     let mut arr = toml_edit::Array::default();
                     exclude.iter().for_each(|i| {
-                        arr.push(i.as_ref()).unwrap();
+                        arr.push(i.as_str());
                     });
                     config_template_doc["site"]["exclude"] = toml_edit::value(arr);
                 }
@@ -170,14 +183,116 @@ impl Manifest {
 
         // TODO: https://github.com/cloudflare/wrangler/issues/773
 
-        let toml = config_template_doc.to_string_in_original_order();
+        let toml = config_template_doc.to_string();
         let manifest = toml::from_str::<Manifest>(&toml)?;
 
-        log::info!("Writing a wrangler.toml file at {}", config_file.display());
-        fs::write(&config_file, &toml)?;
+        log::info!(
+            "Writing a {} file at {}",
+            format.file_name(),
+            config_file.display()
+        );
+        fs::write(&config_file, format.render(&toml)?)?;
+        if config_file != template_file && template_file.exists() {
+            // the template's wrangler.toml has been replaced by the generated file
+            fs::remove_file(template_file)?;
+        }
         Ok(manifest)
     }
 
+    /// The names of the workers declared with `[[workers]]`, in the order they were declared.
+    pub fn worker_names(&self) -> Vec<&str> {
+        self.workers
+            .iter()
+            .flatten()
+            .map(|worker| worker.name.as_str())
+            .collect()
+    }
+
+    /// Resolves one of the workers declared with `[[workers]]` into a manifest of its own,
+    /// which can be used like a manifest that describes a single worker. The top level of
+    /// the file holds defaults shared by every worker:
+    ///
+    /// * Shared: a worker's value replaces the top-level value, and the top-level value is
+    ///   used when the worker doesn't set one (`type`, `account_id`, `workers_dev`,
+    ///   `zone_id`, `webpack_config`, `build`, `kv_namespaces`, `vars`, `usage_model`).
+    /// * Merged: the worker's entries are layered over the top-level entries
+    ///   (`text_blobs`, `wasm_modules`, `dev`).
+    /// * Not shared: settings that only make sense for one script (`route`, `routes`,
+    ///   `site`, `triggers`, `durable_objects`, `migrations`, `env`).
+    pub fn worker(&self, name: &str) -> Result<Manifest> {
+        let worker = self
+            .workers
+            .iter()
+            .flatten()
+            .find(|worker| worker.name == name)
+            .ok_or_else(|| {
+                if self.worker_names().is_empty() {
+                    anyhow!(
+                        "{} There are no [[workers]] declared in your configuration file",
+                        emoji::WARN
+                    )
+                } else {
+                    anyhow!(
+                        "{} Could not find a worker named \"{}\"; the configuration file declares {}",
+                        emoji::WARN,
+                        name,
+                        self.worker_names().join(", ")
+                    )
+                }
+            })?;
+
+        let account_id = match &worker.account_id {
+            Some(account_id) => Some(account_id.clone()).into(),
+            None => self.account_id.clone(),
+        };
+        let dev = match (&self.dev, &worker.dev) {
+            (Some(shared), Some(dev)) => Some(shared.merge(dev)),
+            (shared, dev) => dev.as_ref().or_else(|| shared.as_ref()).cloned(),
+        };
+
+        Ok(Manifest {
+            name: worker.name.clone(),
+            target_type: worker
+                .target_type
+                .clone()
+                .unwrap_or_else(|| self.target_type.clone()),
+            account_id,
+            workers_dev: worker.workers_dev.or(self.workers_dev),
+            route: worker.route.clone(),
+            routes: worker.routes.clone(),
+            zone_id: worker.zone_id.clone().or_else(|| self.zone_id.clone()),
+            webpack_config: worker
+                .webpack_config
+                .clone()
+                .or_else(|| self.webpack_config.clone()),
+            build: worker.build.clone().or_else(|| self.build.clone()),
+            private: self.private,
+            site: worker.site.clone(),
+            dev,
+            kv_namespaces: worker
+                .kv_namespaces
+                .clone()
+                .or_else(|| self.kv_namespaces.clone()),
+            env: worker.env.clone(),
+            vars: worker.vars.clone().or_else(|| self.vars.clone()),
+            text_blobs: merge_entries(&self.text_blobs, &worker.text_blobs),
+            wasm_modules: merge_entries(&self.wasm_modules, &worker.wasm_modules),
+            triggers: worker.triggers.clone(),
+            durable_objects: worker.durable_objects.clone(),
+            usage_model: worker.usage_model.or(self.usage_model),
+            migrations: worker.migrations.clone(),
+            workers: None,
+        })
+    }
+
+    /// Resolves every worker declared with `[[workers]]`, in the order they were declared.
+    pub fn all_workers(&self) -> Result<Vec<Manifest>> {
+        self.worker_names()
+            .into_iter()
+            .map(|name| self.worker(name))
+            .collect()
+    }
+
     pub fn worker_name(&self, env_arg: Option<&str>) -> String {
         if let Some(environment) = self.get_environment(env_arg).unwrap_or_default() {
             if let Some(name) = &environment.name {
@@ -201,13 +316,13 @@ impl Manifest {
         }
     }
 
-    pub fn get_deployments(&self, env: Option<&str>) -> Result<DeploymentSet> {
-        let script = self.worker_name(env);
+    pub fn get_deployments(&self, environment_name: Option<&str>) -> Result<DeploymentSet> {
+        let script = self.worker_name(environment_name);
         validate_worker_name(&script)?;
 
         let mut deployments = DeploymentSet::new();
 
-        let env = self.get_environment(env)?;
+        let env = self.get_environment(environment_name)?;
 
         let mut add_routed_deployments = |route_config: &RouteConfig| -> Result<()> {
             if route_config.is_zoned() {
@@ -257,26 +372,21 @@ impl Manifest {
             add_routed_deployments(&self.route_config())
         }?;
 
-        let crons = match env {
-            Some(e) => {
-                let account_id = e
-                    .account_id
-                    .as_ref()
-                    .or_else(|| self.account_id.if_present());
-                e.triggers
-                    .as_ref()
-                    .or_else(|| self.triggers.as_ref())
-                    .map(|t| (t.crons.as_slice(), account_id))
-            }
-            None => self
-                .triggers
+        let account = match env {
+            Some(e) => e
+                .account_id
                 .as_ref()
-                .map(|t| (t.crons.as_slice(), self.account_id.if_present())),
+                .or_else(|| self.account_id.if_present()),
+            None => self.account_id.if_present(),
         };
 
-        if let Some((crons, account)) = crons {
-            let scheduled =
-                deploy::ScheduleTarget::build(account.cloned(), script.clone(), crons.to_vec())?;
+        if let Some((triggers, field)) = self.get_triggers(environment_name)? {
+            let scheduled = deploy::ScheduleTarget::build(
+                account.cloned(),
+                script.clone(),
+                triggers.crons.clone(),
+            )
+            .map_err(|e| anyhow!("{}.{}", field, e))?;
             deployments.push(DeployTarget::Schedule(scheduled));
         }
 
@@ -302,6 +412,33 @@ impl Manifest {
         self.account_id.load().map(String::from)
     }
 
+    /// Resolves the `[dev]` settings for an environment. The environment's `[dev]` table is
+    /// merged over the top-level one field by field, so an environment can change the port
+    /// while keeping the top-level ip.
+    pub fn get_dev(&self, environment_name: Option<&str>) -> Result<Option<Dev>> {
+        let environment = self.get_environment(environment_name)?;
+        let environment_dev = environment.and_then(|environment| environment.dev.as_ref());
+
+        Ok(match (&self.dev, environment_dev) {
+            (Some(top_level), Some(environment)) => Some(top_level.merge(environment)),
+            (top_level, environment) => environment.or_else(|| top_level.as_ref()).cloned(),
+        })
+    }
+
+    /// Resolves the `Target` for an environment, or for the top level when no environment is
+    /// given. Each field follows one of these rules:
+    ///
+    /// * Top level: only read from the top level; every environment shares it (`type`).
+    /// * Inherited: an environment's value replaces the top-level value, and the top-level
+    ///   value is used when the environment doesn't set one (`name`, `account_id`,
+    ///   `webpack_config`, `build`, `site`, `usage_model`, `migrations`). Routes and
+    ///   `triggers` follow the same rule in `get_deployments`.
+    /// * Merged: the environment's entries are layered over the top-level entries, key by key
+    ///   (`text_blobs`, `wasm_modules`). `[dev]` merges the same way in `get_dev`.
+    /// * Not inherited: bindings that must be defined for every environment individually
+    ///   (`kv_namespaces`, `durable_objects`, `vars`).
+    ///
+    /// See https://developers.cloudflare.com/workers/cli-wrangler/configuration#keys
     pub fn get_target(&self, environment_name: Option<&str>, preview: bool) -> Result<Target> {
         if self.site.is_some() {
             match self.target_type {
@@ -328,14 +465,6 @@ impl Manifest {
             }
         }
 
-        /*
-        From https://developers.cloudflare.com/workers/cli-wrangler/configuration#keys
-        Top level: required to be configured at the top level of your wrangler.toml only; multiple environments on the same project must share this property
-
-        Inherited: Can be configured at the top level and/or environment. If the property is defined only at the top level, the environment will use the property value from the top level. If the property is defined in the environment, the environment value will override the top level value.
-
-        Not inherited: Must be defined for every environment individually.
-        */
         let mut target = Target {
             target_type: self.target_type.clone(),       // Top level
             account_id: self.account_id.clone(),         // Inherited
@@ -346,12 +475,16 @@ impl Manifest {
             name: self.name.clone(), // Inherited
             kv_namespaces: get_namespaces(self.kv_namespaces.clone(), preview)?, // Not inherited
             durable_objects: self.durable_objects.clone(), // Not inherited
-            migrations: None,        // TODO(soon) Allow migrations in wrangler.toml
+            migrations: self.migrations.clone().map(|migrations| Migrations::List {
+                script_tag: None,
+                migrations,
+            }), // Inherited
             site: self.site.clone(), // Inherited
             vars: self.vars.clone(), // Not inherited
-            text_blobs: self.text_blobs.clone(), // Inherited
-            usage_model: self.usage_model, // Top level
-            wasm_modules: self.wasm_modules.clone(),
+            text_blobs: self.text_blobs.clone(), // Merged
+            usage_model: self.usage_model, // Inherited
+            wasm_modules: self.wasm_modules.clone(), // Merged
+            test_scheduled: false,
         };
 
         let environment = self.get_environment(environment_name)?;
@@ -381,11 +514,46 @@ impl Manifest {
 
             // don't inherit vars
             target.vars = environment.vars.clone();
+
+            // merge file bindings, letting the environment replace individual entries
+            target.text_blobs = merge_entries(&self.text_blobs, &environment.text_blobs);
+            target.wasm_modules = merge_entries(&self.wasm_modules, &environment.wasm_modules);
+
+            if let Some(usage_model) = environment.usage_model {
+                target.usage_model = Some(usage_model);
+            }
+
+            // inherit migrations unless the environment lists its own
+            if let Some(migrations) = &environment.migrations {
+                target.migrations = Some(Migrations::List {
+                    script_tag: None,
+                    migrations: migrations.clone(),
+                });
+            }
         }
 
         Ok(target)
     }
 
+    /// The `[triggers]` an environment publishes, along with where they're set. An
+    /// environment without its own uses the top-level ones.
+    pub fn get_triggers(
+        &self,
+        environment_name: Option<&str>,
+    ) -> Result<Option<(&Triggers, String)>> {
+        let environment = self.get_environment(environment_name)?;
+        Ok(match environment.and_then(|e| e.triggers.as_ref()) {
+            Some(triggers) => Some((
+                triggers,
+                format!("env.{}.triggers", environment_name.unwrap_or_default()),
+            )),
+            None => self
+                .triggers
+                .as_ref()
+                .map(|triggers| (triggers, "triggers".to_string())),
+        })
+    }
+
     pub fn get_environment(&self, environment_name: Option<&str>) -> Result<Option<&Environment>> {
         // check for user-specified environment name
         if let Some(environment_name) = environment_name {
@@ -546,7 +714,7 @@ impl LazyAccountId {
     /// Return the `account_id` in `wrangler.toml`, if present.
     ///
     /// Use this with caution; prefer `maybe_load` instead where possible.
-    fn if_present(&self) -> Option<&String> {
+    pub(crate) fn if_present(&self) -> Option<&String> {
         self.0.get()
     }
 
@@ -605,10 +773,11 @@ impl FromStr for Manifest {
 fn read_config(config_path: &Path) -> Result<Config> {
     let mut config = Config::new();
 
-    let config_str = config_path
-        .to_str()
-        .expect("project config path should be a string");
-    config.merge(File::with_name(config_str))?;
+    // `${VAR}`s and `extends` are resolved before the file is handed to `config`, so
+    // they can be used in any field
+    let source = extends::resolve(config_path)?;
+    check_vars(&source)?;
+    config.merge(File::from_str(&source, FileFormat::Toml))?;
 
     // Eg.. `CF_ACCOUNT_AUTH_KEY=farts` would set the `account_auth_key` key
     config.merge(config::Environment::with_prefix("CF"))?;
@@ -616,7 +785,92 @@ fn read_config(config_path: &Path) -> Result<Config> {
     Ok(config)
 }
 
+/// `config` turns TOML datetimes into strings, so a datetime in `[vars]` would quietly be
+/// bound as text. Vars are bound as text or JSON, and JSON has no datetimes, so they're
+/// rejected here instead.
+fn check_vars(source: &str) -> Result<()> {
+    let document = match source.parse::<toml::Value>() {
+        Ok(document) => document,
+        // syntax errors are reported when `config` reads the file
+        Err(_) => return Ok(()),
+    };
+
+    let mut workers = vec![(String::new(), &document)];
+    if let Some(entries) = document.get("workers").and_then(toml::Value::as_array) {
+        for (index, worker) in entries.iter().enumerate() {
+            workers.push((format!("workers[{}].", index), worker));
+        }
+    }
+
+    for (prefix, worker) in workers {
+        let mut tables = vec![(prefix.clone(), worker)];
+        if let Some(environments) = worker.get("env").and_then(toml::Value::as_table) {
+            for (name, environment) in environments {
+                tables.push((format!("{}env.{}.", prefix, name), environment));
+            }
+        }
+
+        for (prefix, table) in tables {
+            let vars = match table.get("vars").and_then(toml::Value::as_table) {
+                Some(vars) => vars,
+                None => continue,
+            };
+            if let Some(name) = vars
+                .iter()
+                .find(|(_, value)| has_datetime(value))
+                .map(|(name, _)| name)
+            {
+                anyhow::bail!(
+                    "`{}vars.{}` contains a TOML datetime, which has no JSON equivalent; put it in quotes to bind it as text",
+                    prefix,
+                    name
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+fn has_datetime(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Datetime(_) => true,
+        toml::Value::Array(items) => items.iter().any(has_datetime),
+        toml::Value::Table(table) => table.values().any(has_datetime),
+        _ => false,
+    }
+}
+
+/// The configuration file to read: `wrangler.json` and `wrangler.jsonc` are picked up when the
+/// default `wrangler.toml` is missing
+pub(crate) fn find_config(config_path: &Path) -> PathBuf {
+    let is_default = config_path.file_name().and_then(|name| name.to_str())
+        == Some(ConfigFormat::Toml.file_name());
+    if config_path.exists() || !is_default {
+        return config_path.to_path_buf();
+    }
+
+    [ConfigFormat::Json, ConfigFormat::Jsonc]
+        .iter()
+        .map(|format| config_path.with_file_name(format.file_name()))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| config_path.to_path_buf())
+}
+
 fn check_for_duplicate_names(manifest: &Manifest) -> Result<()> {
+    let worker_names = manifest.worker_names();
+    if let Some(name) = worker_names
+        .iter()
+        .enumerate()
+        .find(|(index, name)| worker_names[..*index].contains(name))
+        .map(|(_, name)| name)
+    {
+        anyhow::bail!(
+            "{} More than one worker is named \"{}\"; every entry in [[workers]] needs a unique name",
+            emoji::WARN,
+            name
+        )
+    }
+
     let mut names: HashSet<String> = HashSet::new();
     let mut duplicate_names: HashSet<String> = HashSet::new();
     names.insert(manifest.name.to_string());
@@ -652,6 +906,20 @@ fn check_for_duplicate_names(manifest: &Manifest) -> Result<()> {
     Ok(())
 }
 
+fn merge_entries<T: Clone>(
+    top_level: &Option<HashMap<String, T>>,
+    environment: &Option<HashMap<String, T>>,
+) -> Option<HashMap<String, T>> {
+    match (top_level, environment) {
+        (Some(top_level), Some(environment)) => {
+            let mut merged = top_level.clone();
+            merged.extend(environment.clone());
+            Some(merged)
+        }
+        (top_level, environment) => environment.clone().or_else(|| top_level.clone()),
+    }
+}
+
 fn get_namespaces(
     kv_namespaces: Option<Vec<ConfigKvNamespace>>,
     preview: bool,
@@ -699,15 +967,57 @@ mod tests {
             Some(TargetType::JavaScript),
             toml_path,
             None,
+            ConfigFormat::Toml,
         )?;
         assert_eq!(toml.name.to_string(), "test".to_string());
         assert_eq!(toml.target_type.to_string(), "javascript".to_string());
         fs::remove_file(toml_path.with_file_name("wrangler.toml"))?;
 
-        let toml = Manifest::generate("test".to_string(), None, toml_path, None)?;
+        let toml = Manifest::generate(
+            "test".to_string(),
+            None,
+            toml_path,
+            None,
+            ConfigFormat::Toml,
+        )?;
         assert_eq!(toml.target_type.to_string(), "webpack".to_string());
         fs::remove_file(toml_path.with_file_name("wrangler.toml"))?;
 
         Ok(())
     }
+
+    #[test]
+    fn it_rejects_datetime_vars() -> Result<()> {
+        let project = tempfile::tempdir()?;
+        let config_path = project.path().join("wrangler.toml");
+        fs::write(
+            &config_path,
+            "name = \"worker\"\ntype = \"javascript\"\n\n[env.staging.vars]\nLIMITS = { since = 1979-05-27 }\n",
+        )?;
+
+        let error = Manifest::new(&config_path).unwrap_err();
+        assert!(error.to_string().contains("`env.staging.vars.LIMITS`"));
+        Ok(())
+    }
+
+    #[test]
+    fn generate_json() -> Result<()> {
+        let project = tempfile::tempdir()?;
+
+        Manifest::generate(
+            "test".to_string(),
+            Some(TargetType::JavaScript),
+            project.path(),
+            None,
+            ConfigFormat::Json,
+        )?;
+        assert!(!project.path().join("wrangler.toml").exists());
+
+        // the default wrangler.toml falls back to wrangler.json
+        let manifest = Manifest::new(&project.path().join("wrangler.toml"))?;
+        assert_eq!(manifest.name, "test");
+        assert_eq!(manifest.target_type, TargetType::JavaScript);
+
+        Ok(())
+    }
 }
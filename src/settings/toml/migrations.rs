@@ -1,27 +1,240 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
-pub struct Migrations {
-    pub migrations: Vec<MigrationConfig>,
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Migrations {
+    /// A single migration passed to `wrangler publish` as command line flags.
+    Adhoc(MigrationConfig),
+    /// The `[[migrations]]` declared in the configuration file, along with the
+    /// migration tag the script currently has applied (if any).
+    List {
+        script_tag: Option<String>,
+        migrations: Vec<MigrationConfig>,
+    },
 }
 
 impl Migrations {
-    pub fn api_migration(&self) -> Result<ApiMigration, anyhow::Error> {
-        // TODO: make api call to get most recent tag, and coalesce tags afterwards.
-        // For now, migrations will only ever have a single adhoc migration in it.
-        match &self.migrations {
-            migrations if migrations.len() == 1 => Ok(ApiMigration {
+    pub fn api_migration(&self) -> Result<Option<ApiMigration>> {
+        match self {
+            Migrations::Adhoc(migration) => Ok(Some(ApiMigration {
                 old_tag: None,
-                new_tag: None,
-                migration: migrations[0].migration.clone(),
-            }),
-            migrations => Self::coalesce_migrations(migrations),
+                new_tag: migration.tag.clone(),
+                migration: migration.migration.clone(),
+            })),
+            Migrations::List {
+                script_tag,
+                migrations,
+            } => {
+                validate_tags(migrations)?;
+
+                // Only the migrations declared after the script's current tag still need to be
+                // applied; if the script has no tag yet, all of them do.
+                let pending = match script_tag {
+                    Some(script_tag) => {
+                        match migrations
+                            .iter()
+                            .position(|m| m.tag.as_ref() == Some(script_tag))
+                        {
+                            Some(idx) => &migrations[idx + 1..],
+                            None => anyhow::bail!(
+                                "The migration tag \"{}\" currently applied to your script was not found in the [[migrations]] of your configuration file",
+                                script_tag
+                            ),
+                        }
+                    }
+                    None => migrations.as_slice(),
+                };
+
+                if pending.is_empty() {
+                    return Ok(None);
+                }
+
+                Ok(Some(ApiMigration {
+                    old_tag: script_tag.clone(),
+                    new_tag: pending.last().and_then(|m| m.tag.clone()),
+                    migration: Self::coalesce_migrations(pending)?,
+                }))
+            }
         }
     }
 
-    fn coalesce_migrations(_migrations: &[MigrationConfig]) -> Result<ApiMigration, anyhow::Error> {
-        unimplemented!()
+    // The API only accepts a single migration per upload, so all of the pending steps are
+    // merged, in order, into one. Sequences that can't be expressed as a single migration
+    // (e.g. renaming a class that an earlier step deleted) are rejected.
+    fn coalesce_migrations(migrations: &[MigrationConfig]) -> Result<Migration> {
+        let mut coalesced = DurableObjectsMigration::default();
+        // every class name that has been deleted by one of the pending steps
+        let mut deleted: HashSet<String> = HashSet::new();
+        // every class name that one of the pending steps has renamed, and what to
+        let mut renamed: HashMap<String, String> = HashMap::new();
+
+        for step in migrations {
+            let tag = step.tag.as_deref().unwrap_or_default();
+            let step = &step.migration.durable_objects;
+
+            for class in &step.new_classes {
+                if deleted.contains(class) {
+                    anyhow::bail!(
+                        "Migration \"{}\" creates class \"{}\", which was deleted by an earlier migration that has not been applied yet",
+                        tag,
+                        class
+                    )
+                }
+                if let Some(to) = renamed.get(class) {
+                    anyhow::bail!(
+                        "Migration \"{}\" creates class \"{}\", which was renamed to \"{}\" by an earlier migration that has not been applied yet",
+                        tag,
+                        class,
+                        to
+                    )
+                }
+                if coalesced.defines(class) {
+                    anyhow::bail!(
+                        "Migration \"{}\" creates class \"{}\", which was already created by an earlier migration",
+                        tag,
+                        class
+                    )
+                }
+                coalesced.new_classes.push(class.clone());
+            }
+
+            for class in &step.deleted_classes {
+                if deleted.contains(class) {
+                    anyhow::bail!(
+                        "Migration \"{}\" deletes class \"{}\", which was already deleted by an earlier migration",
+                        tag,
+                        class
+                    )
+                }
+                if let Some(to) = renamed.get(class) {
+                    anyhow::bail!(
+                        "Migration \"{}\" deletes class \"{}\", which was renamed to \"{}\" by an earlier migration",
+                        tag,
+                        class,
+                        to
+                    )
+                }
+                deleted.insert(class.clone());
+
+                if let Some(idx) = coalesced.new_classes.iter().position(|c| c == class) {
+                    // creating and then deleting a class cancels out
+                    coalesced.new_classes.remove(idx);
+                } else if let Some(idx) = coalesced
+                    .renamed_classes
+                    .iter()
+                    .position(|r| &r.to == class)
+                {
+                    // renaming and then deleting a class deletes the original class
+                    let rename = coalesced.renamed_classes.remove(idx);
+                    coalesced.deleted_classes.push(rename.from);
+                } else if coalesced.transferred_classes.iter().any(|t| &t.to == class) {
+                    anyhow::bail!(
+                        "Migration \"{}\" deletes class \"{}\", which was transferred by an earlier migration that has not been applied yet",
+                        tag,
+                        class
+                    )
+                } else {
+                    coalesced.deleted_classes.push(class.clone());
+                }
+            }
+
+            for rename in &step.renamed_classes {
+                if deleted.contains(&rename.from) {
+                    anyhow::bail!(
+                        "Migration \"{}\" renames class \"{}\", which was deleted by an earlier migration",
+                        tag,
+                        rename.from
+                    )
+                }
+                if let Some(to) = renamed.get(&rename.from) {
+                    anyhow::bail!(
+                        "Migration \"{}\" renames class \"{}\", which was renamed to \"{}\" by an earlier migration",
+                        tag,
+                        rename.from,
+                        to
+                    )
+                }
+                if deleted.contains(&rename.to)
+                    || renamed.contains_key(&rename.to)
+                    || coalesced.defines(&rename.to)
+                {
+                    anyhow::bail!(
+                        "Migration \"{}\" renames class \"{}\" to \"{}\", which is already in use by an earlier migration",
+                        tag,
+                        rename.from,
+                        rename.to
+                    )
+                }
+
+                if let Some(class) = coalesced
+                    .new_classes
+                    .iter_mut()
+                    .find(|c| **c == rename.from)
+                {
+                    *class = rename.to.clone();
+                } else if let Some(previous) = coalesced
+                    .renamed_classes
+                    .iter_mut()
+                    .find(|r| r.to == rename.from)
+                {
+                    previous.to = rename.to.clone();
+                } else if let Some(transfer) = coalesced
+                    .transferred_classes
+                    .iter_mut()
+                    .find(|t| t.to == rename.from)
+                {
+                    transfer.to = rename.to.clone();
+                } else {
+                    coalesced.renamed_classes.push(rename.clone());
+                }
+                renamed.insert(rename.from.clone(), rename.to.clone());
+            }
+
+            for transfer in &step.transferred_classes {
+                if deleted.contains(&transfer.to)
+                    || renamed.contains_key(&transfer.to)
+                    || coalesced.defines(&transfer.to)
+                {
+                    anyhow::bail!(
+                        "Migration \"{}\" transfers class \"{}\" from script \"{}\" to \"{}\", which is already in use by an earlier migration",
+                        tag,
+                        transfer.from,
+                        transfer.from_script,
+                        transfer.to
+                    )
+                }
+                coalesced.transferred_classes.push(transfer.clone());
+            }
+        }
+
+        Ok(Migration {
+            durable_objects: coalesced,
+        })
+    }
+}
+
+fn validate_tags(migrations: &[MigrationConfig]) -> Result<()> {
+    let mut tags = HashSet::new();
+    for (idx, migration) in migrations.iter().enumerate() {
+        match &migration.tag {
+            Some(tag) if !tag.is_empty() => {
+                if !tags.insert(tag) {
+                    anyhow::bail!(
+                        "Each of your [[migrations]] must have a unique tag, \"{}\" is duplicated",
+                        tag
+                    )
+                }
+            }
+            _ => anyhow::bail!(
+                "Each of your [[migrations]] must have a tag, migration #{} is missing one",
+                idx + 1
+            ),
+        }
     }
+    Ok(())
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
@@ -49,12 +262,25 @@ pub struct Migration {
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct DurableObjectsMigration {
+    #[serde(default)]
     pub new_classes: Vec<String>,
+    #[serde(default)]
     pub deleted_classes: Vec<String>,
+    #[serde(default)]
     pub renamed_classes: Vec<RenameClass>,
+    #[serde(default)]
     pub transferred_classes: Vec<TransferClass>,
 }
 
+impl DurableObjectsMigration {
+    // Whether this migration brings a class with the given name into existence.
+    fn defines(&self, class: &str) -> bool {
+        self.new_classes.iter().any(|c| c == class)
+            || self.renamed_classes.iter().any(|r| r.to == class)
+            || self.transferred_classes.iter().any(|t| t.to == class)
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct RenameClass {
     pub from: String,
@@ -67,3 +293,277 @@ pub struct TransferClass {
     pub from_script: String,
     pub to: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(tag: &str, migration: DurableObjectsMigration) -> MigrationConfig {
+        MigrationConfig {
+            tag: Some(tag.to_string()),
+            migration: Migration {
+                durable_objects: migration,
+            },
+        }
+    }
+
+    fn new_classes(classes: &[&str]) -> DurableObjectsMigration {
+        DurableObjectsMigration {
+            new_classes: classes.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn deleted_classes(classes: &[&str]) -> DurableObjectsMigration {
+        DurableObjectsMigration {
+            deleted_classes: classes.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn renamed_class(from: &str, to: &str) -> DurableObjectsMigration {
+        DurableObjectsMigration {
+            renamed_classes: vec![RenameClass {
+                from: from.to_string(),
+                to: to.to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn list(script_tag: Option<&str>, migrations: Vec<MigrationConfig>) -> Migrations {
+        Migrations::List {
+            script_tag: script_tag.map(str::to_string),
+            migrations,
+        }
+    }
+
+    #[test]
+    fn it_applies_every_migration_to_an_untagged_script() {
+        let migrations = list(
+            None,
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", new_classes(&["Room"])),
+            ],
+        );
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(api_migration.old_tag, None);
+        assert_eq!(api_migration.new_tag, Some("v2".to_string()));
+        assert_eq!(
+            api_migration.migration.durable_objects,
+            new_classes(&["Counter", "Room"])
+        );
+    }
+
+    #[test]
+    fn it_only_applies_migrations_after_the_script_tag() {
+        let migrations = list(
+            Some("v1"),
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", renamed_class("Counter", "Tally")),
+                step("v3", new_classes(&["Room"])),
+            ],
+        );
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(api_migration.old_tag, Some("v1".to_string()));
+        assert_eq!(api_migration.new_tag, Some("v3".to_string()));
+        assert_eq!(
+            api_migration.migration.durable_objects,
+            DurableObjectsMigration {
+                new_classes: vec!["Room".to_string()],
+                renamed_classes: vec![RenameClass {
+                    from: "Counter".to_string(),
+                    to: "Tally".to_string(),
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn it_skips_the_migration_when_the_script_is_up_to_date() {
+        let migrations = list(Some("v1"), vec![step("v1", new_classes(&["Counter"]))]);
+
+        assert_eq!(migrations.api_migration().unwrap(), None);
+    }
+
+    #[test]
+    fn it_errors_on_unknown_script_tag() {
+        let migrations = list(Some("v9"), vec![step("v1", new_classes(&["Counter"]))]);
+
+        assert!(migrations.api_migration().is_err());
+    }
+
+    #[test]
+    fn it_errors_on_missing_or_duplicate_tags() {
+        let mut untagged = step("v1", new_classes(&["Counter"]));
+        untagged.tag = None;
+        assert!(list(None, vec![untagged]).api_migration().is_err());
+
+        let duplicated = list(
+            None,
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v1", new_classes(&["Room"])),
+            ],
+        );
+        assert!(duplicated.api_migration().is_err());
+    }
+
+    #[test]
+    fn it_coalesces_renames_of_new_classes() {
+        let migrations = list(
+            None,
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", renamed_class("Counter", "Tally")),
+                step("v3", renamed_class("Tally", "Total")),
+            ],
+        );
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(
+            api_migration.migration.durable_objects,
+            new_classes(&["Total"])
+        );
+    }
+
+    #[test]
+    fn it_coalesces_chained_renames() {
+        let migrations = list(
+            Some("v1"),
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", renamed_class("Counter", "Tally")),
+                step("v3", renamed_class("Tally", "Total")),
+            ],
+        );
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(
+            api_migration.migration.durable_objects,
+            renamed_class("Counter", "Total")
+        );
+    }
+
+    #[test]
+    fn it_cancels_out_creating_and_deleting_a_class() {
+        let migrations = list(
+            None,
+            vec![
+                step("v1", new_classes(&["Counter", "Room"])),
+                step("v2", deleted_classes(&["Counter"])),
+            ],
+        );
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(
+            api_migration.migration.durable_objects,
+            new_classes(&["Room"])
+        );
+    }
+
+    #[test]
+    fn it_deletes_the_original_of_a_renamed_class() {
+        let migrations = list(
+            Some("v1"),
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", renamed_class("Counter", "Tally")),
+                step("v3", deleted_classes(&["Tally"])),
+            ],
+        );
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(
+            api_migration.migration.durable_objects,
+            deleted_classes(&["Counter"])
+        );
+    }
+
+    #[test]
+    fn it_rejects_renaming_a_deleted_class() {
+        let migrations = list(
+            Some("v1"),
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", deleted_classes(&["Counter"])),
+                step("v3", renamed_class("Counter", "Tally")),
+            ],
+        );
+
+        assert!(migrations.api_migration().is_err());
+    }
+
+    #[test]
+    fn it_rejects_renaming_a_class_that_was_renamed_away() {
+        let migrations = list(
+            Some("v1"),
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", renamed_class("Counter", "Tally")),
+                step("v3", renamed_class("Counter", "Total")),
+            ],
+        );
+
+        assert!(migrations.api_migration().is_err());
+    }
+
+    #[test]
+    fn it_rejects_recreating_a_deleted_class() {
+        let migrations = list(
+            Some("v1"),
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", deleted_classes(&["Counter"])),
+                step("v3", new_classes(&["Counter"])),
+            ],
+        );
+
+        assert!(migrations.api_migration().is_err());
+    }
+
+    #[test]
+    fn it_rejects_creating_a_class_twice() {
+        let migrations = list(
+            None,
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", new_classes(&["Counter"])),
+            ],
+        );
+
+        assert!(migrations.api_migration().is_err());
+    }
+
+    #[test]
+    fn it_parses_migrations_from_toml() {
+        let migrations: Vec<MigrationConfig> = toml::from_str::<toml::Value>(
+            r#"
+            [[migrations]]
+            tag = "v1"
+            new_classes = ["Counter"]
+
+            [[migrations]]
+            tag = "v2"
+            renamed_classes = [{ from = "Counter", to = "Tally" }]
+            "#,
+        )
+        .unwrap()["migrations"]
+            .clone()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            migrations,
+            vec![
+                step("v1", new_classes(&["Counter"])),
+                step("v2", renamed_class("Counter", "Tally"))
+            ]
+        );
+    }
+}
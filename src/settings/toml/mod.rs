@@ -1,25 +1,40 @@
 mod builder;
+mod cron;
+pub mod deprecations;
 mod dev;
 mod durable_objects;
 mod environment;
+mod extends;
+mod format;
+mod interpolate;
 mod kv_namespace;
 mod manifest;
 pub mod migrations;
+mod provenance;
 mod route;
 mod site;
 mod target;
 mod target_type;
 mod triggers;
+mod validation;
+mod workers;
 
 pub use builder::{Builder, ModuleRule, UploadFormat};
+pub use cron::Cron;
+pub use dev::Dev;
 pub use durable_objects::{DurableObjects, DurableObjectsClass};
 pub use environment::Environment;
+pub use format::ConfigFormat;
 pub use kv_namespace::{ConfigKvNamespace, KvNamespace};
+pub(crate) use manifest::find_config;
 pub use manifest::Manifest;
+pub use provenance::{provenance, Source};
 pub use route::{Route, RouteConfig};
-pub use site::Site;
+pub use site::{AssetHeaders, Encoding, ManifestVersion, Site};
 pub use target::Target;
 pub use target_type::TargetType;
+pub use validation::{validate, Diagnostic};
+pub use workers::{deployment_order, Worker};
 
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
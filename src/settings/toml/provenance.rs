@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::settings::toml::Manifest;
+
+/// Where a resolved setting came from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    /// The top level of the configuration file
+    TopLevel,
+    /// An `[env.*]` table
+    Environment(String),
+    /// Nothing set it, so wrangler's default is used
+    Default,
+    /// A `CF_*` environment variable, which overrides the top level of the file
+    EnvVar(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::TopLevel => write!(f, "top-level"),
+            Source::Environment(name) => write!(f, "env.{}", name),
+            Source::Default => write!(f, "default"),
+            Source::EnvVar(name) => write!(f, "env var {}", name),
+        }
+    }
+}
+
+/// Works out where each field of `Manifest::get_target` and each group of deployments of
+/// `Manifest::get_deployments` came from, following the same rules. Fields are keyed by
+/// their name in `Target`; entries of merged and per-binding tables are keyed by
+/// `<field>.<name>`. Deployments are keyed by `routes` and `triggers`.
+pub fn provenance(
+    manifest: &Manifest,
+    environment_name: Option<&str>,
+) -> Result<BTreeMap<String, Source>> {
+    let environment = manifest.get_environment(environment_name)?;
+    let from_env = || Source::Environment(environment_name.unwrap_or_default().to_string());
+
+    let mut sources = BTreeMap::new();
+    let mut insert = |key: &str, source: Source| {
+        sources.insert(key.to_string(), source);
+    };
+
+    insert("type", top_level("type", true));
+
+    // Inherited
+    let inherited = [
+        ("name", environment.is_some(), !manifest.name.is_empty()),
+        (
+            "account_id",
+            environment.map_or(false, |e| e.account_id.is_some()),
+            manifest.account_id.if_present().is_some(),
+        ),
+        (
+            "webpack_config",
+            environment.map_or(false, |e| e.webpack_config.is_some()),
+            manifest.webpack_config.is_some(),
+        ),
+        (
+            "build",
+            environment.map_or(false, |e| e.build.is_some()),
+            manifest.build.is_some(),
+        ),
+        (
+            "site",
+            environment.map_or(false, |e| e.site.is_some()),
+            manifest.site.is_some(),
+        ),
+        (
+            "usage_model",
+            environment.map_or(false, |e| e.usage_model.is_some()),
+            manifest.usage_model.is_some(),
+        ),
+        (
+            "migrations",
+            environment.map_or(false, |e| e.migrations.is_some()),
+            manifest.migrations.is_some(),
+        ),
+    ];
+    for (key, in_environment, in_top_level) in inherited.iter() {
+        let source = if *in_environment {
+            from_env()
+        } else {
+            top_level(key, *in_top_level)
+        };
+        insert(key, source);
+    }
+
+    // Not inherited
+    match environment {
+        Some(environment) => {
+            insert(
+                "kv_namespaces",
+                if environment.kv_namespaces.is_some() {
+                    from_env()
+                } else {
+                    Source::Default
+                },
+            );
+            insert(
+                "durable_objects",
+                if environment.durable_objects.is_some() {
+                    from_env()
+                } else {
+                    Source::Default
+                },
+            );
+            for name in environment.vars.iter().flat_map(|vars| vars.keys()) {
+                insert(&format!("vars.{}", name), from_env());
+            }
+        }
+        None => {
+            insert(
+                "kv_namespaces",
+                top_level("kv_namespaces", manifest.kv_namespaces.is_some()),
+            );
+            insert(
+                "durable_objects",
+                top_level("durable_objects", manifest.durable_objects.is_some()),
+            );
+            for name in manifest.vars.iter().flat_map(|vars| vars.keys()) {
+                insert(&format!("vars.{}", name), Source::TopLevel);
+            }
+        }
+    }
+
+    // Merged
+    let merged = [
+        (
+            "text_blobs",
+            &manifest.text_blobs,
+            environment.and_then(|e| e.text_blobs.as_ref()),
+        ),
+        (
+            "wasm_modules",
+            &manifest.wasm_modules,
+            environment.and_then(|e| e.wasm_modules.as_ref()),
+        ),
+    ];
+    for (field, top_level_entries, environment_entries) in merged.iter() {
+        for name in top_level_entries.iter().flat_map(|entries| entries.keys()) {
+            insert(&format!("{}.{}", field, name), Source::TopLevel);
+        }
+        for name in environment_entries
+            .iter()
+            .flat_map(|entries| entries.keys())
+        {
+            insert(&format!("{}.{}", field, name), from_env());
+        }
+    }
+
+    // Deployments
+    let has_routes = |workers_dev: Option<bool>, route: bool, routes: bool| {
+        workers_dev.is_some() || route || routes
+    };
+    let routes_in_environment = environment.map_or(false, |e| {
+        has_routes(e.workers_dev, e.route.is_some(), e.routes.is_some())
+    });
+    insert(
+        "routes",
+        if routes_in_environment {
+            from_env()
+        } else {
+            top_level(
+                "route",
+                has_routes(
+                    manifest.workers_dev,
+                    manifest.route.is_some(),
+                    manifest.routes.is_some(),
+                ),
+            )
+        },
+    );
+    insert(
+        "triggers",
+        if environment.map_or(false, |e| e.triggers.is_some()) {
+            from_env()
+        } else {
+            top_level("triggers", manifest.triggers.is_some())
+        },
+    );
+
+    Ok(sources)
+}
+
+// `Manifest::new` lets `CF_<KEY>` environment variables override top-level keys
+fn top_level(key: &str, is_set: bool) -> Source {
+    let variable = format!("CF_{}", key.to_ascii_uppercase());
+    if env::var_os(&variable).is_some() {
+        Source::EnvVar(variable)
+    } else if is_set {
+        Source::TopLevel
+    } else {
+        Source::Default
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_follows_the_inheritance_rules() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+name = "worker"
+type = "webpack"
+webpack_config = "webpack.config.js"
+workers_dev = true
+vars = { TOP = "1" }
+text_blobs = { A = "a.txt", B = "b.txt" }
+
+[env.staging]
+routes = ["staging.example.com/*"]
+zone_id = "zone"
+vars = { STAGING = "1" }
+text_blobs = { B = "staging-b.txt" }
+"#,
+        )
+        .unwrap();
+
+        let sources = provenance(&manifest, Some("staging")).unwrap();
+        let staging = Source::Environment("staging".to_string());
+
+        assert_eq!(sources["name"], staging);
+        assert_eq!(sources["webpack_config"], Source::TopLevel);
+        assert_eq!(sources["build"], Source::Default);
+        assert_eq!(sources["kv_namespaces"], Source::Default);
+        assert_eq!(sources["vars.STAGING"], staging);
+        assert_eq!(sources.get("vars.TOP"), None);
+        assert_eq!(sources["text_blobs.A"], Source::TopLevel);
+        assert_eq!(sources["text_blobs.B"], staging);
+        assert_eq!(sources["routes"], staging);
+        assert_eq!(sources["triggers"], Source::Default);
+
+        let sources = provenance(&manifest, None).unwrap();
+        assert_eq!(sources["name"], Source::TopLevel);
+        assert_eq!(sources["routes"], Source::TopLevel);
+        assert_eq!(sources["vars.TOP"], Source::TopLevel);
+    }
+}
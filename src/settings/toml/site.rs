@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::commands::generate::run_generate;
 
@@ -17,6 +19,88 @@ pub struct Site {
     pub entry_point: Option<PathBuf>,
     pub include: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    pub precompress: Option<Vec<Encoding>>,
+    pub manifest_version: Option<ManifestVersion>,
+    pub headers: Option<Vec<BTreeMap<String, AssetHeaders>>>,
+}
+
+/// The headers a `[[site.headers]]` rule sets on the files whose paths match its glob
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AssetHeaders {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+impl AssetHeaders {
+    /// Sets the headers `other` sets, keeping the rest
+    pub fn merge(&mut self, other: &AssetHeaders) {
+        if other.cache_control.is_some() {
+            self.cache_control = other.cache_control.clone();
+        }
+        if other.content_type.is_some() {
+            self.content_type = other.content_type.clone();
+        }
+    }
+}
+
+/// A compression a Workers Site's files can be uploaded with as well, named for the
+/// `Content-Encoding` they're served with
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Encoding {
+    #[serde(rename = "br")]
+    Brotli,
+    #[serde(rename = "gzip")]
+    Gzip,
+}
+
+impl Encoding {
+    // added to the key of a file for the key of its compressed variant
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+}
+
+/// The format of a Workers Site's `__STATIC_CONTENT_MANIFEST`, which is set as a number
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManifestVersion {
+    /// Maps each path to the key of its file, which is what handlers have always read
+    V1,
+    /// Maps each path to the key, size, MIME type, hash and last-modified time of its file
+    V2,
+}
+
+impl Default for ManifestVersion {
+    fn default() -> ManifestVersion {
+        ManifestVersion::V1
+    }
+}
+
+impl Serialize for ManifestVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ManifestVersion::V1 => serializer.serialize_u8(1),
+            ManifestVersion::V2 => serializer.serialize_u8(2),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ManifestVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match u8::deserialize(deserializer)? {
+            1 => Ok(ManifestVersion::V1),
+            2 => Ok(ManifestVersion::V2),
+            version => Err(D::Error::custom(format!(
+                "manifest_version {} is unknown; it can be 1 or 2",
+                version
+            ))),
+        }
+    }
 }
 
 impl Site {
@@ -39,6 +123,21 @@ impl Site {
         ))
     }
 
+    /// The encodings to upload compressed variants of the files in, if any
+    pub fn precompress(&self) -> &[Encoding] {
+        self.precompress.as_deref().unwrap_or_default()
+    }
+
+    pub fn manifest_version(&self) -> ManifestVersion {
+        self.manifest_version.unwrap_or_default()
+    }
+
+    /// The `[[site.headers]]` tables, each of which maps globs to the headers of the files
+    /// they match
+    pub fn headers(&self) -> &[BTreeMap<String, AssetHeaders>] {
+        self.headers.as_deref().unwrap_or_default()
+    }
+
     pub fn scaffold_worker(&self) -> Result<()> {
         let entry_point = &self.entry_point()?;
         let template = "https://github.com/cloudflare/worker-sites-init";
@@ -63,6 +162,9 @@ impl Default for Site {
             entry_point: Some(PathBuf::from(SITE_ENTRY_POINT)),
             include: None,
             exclude: None,
+            precompress: None,
+            manifest_version: None,
+            headers: None,
         }
     }
 }
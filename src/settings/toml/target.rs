@@ -11,7 +11,9 @@ use std::env;
 
 use std::path::PathBuf;
 
-#[derive(Clone, Debug, Default)]
+use serde::Serialize;
+
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct Target {
     pub account_id: LazyAccountId,
     pub kv_namespaces: Vec<KvNamespace>,
@@ -22,10 +24,14 @@ pub struct Target {
     pub webpack_config: Option<String>,
     pub build: Option<Builder>,
     pub site: Option<Site>,
-    pub vars: Option<HashMap<String, String>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
     pub usage_model: Option<UsageModel>,
     pub wasm_modules: Option<HashMap<String, PathBuf>>,
+    /// Set by `wrangler dev --test-scheduled` to upload the script wrapped so that it can
+    /// be sent scheduled events
+    #[serde(skip)]
+    pub test_scheduled: bool,
 }
 
 impl Target {
@@ -1,10 +1,17 @@
 use std::str::FromStr;
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::commands::dev::Protocol;
 use crate::deploy::{DeployTarget, ScheduleTarget, ZonedTarget, ZonelessTarget};
+use crate::settings::toml::migrations::Migrations;
 use crate::settings::toml::route::Route;
-use crate::settings::toml::Manifest;
+use crate::settings::toml::{Manifest, Target, UsageModel};
 
-use crate::fixtures::{EnvConfig, Triggers, WranglerToml, TEST_ENV_NAME};
+use crate::fixtures::{
+    DevConfig, EnvConfig, KvConfig, MigrationConfig, Triggers, WranglerToml, TEST_ENV_NAME,
+};
 
 // Test consts
 const ZONE_ID: &str = "samplezoneid";
@@ -223,6 +230,39 @@ fn it_can_get_a_scheduled_in_env_no_workers_dev_no_zoned() {
 
     assert_eq!(actual_deployments, expected_deployments);
 }
+
+#[test]
+fn it_errors_on_invalid_crons() {
+    let script_name = "invalid_schedule";
+
+    let env_config = EnvConfig {
+        triggers: Some(Triggers {
+            crons: Some(vec!["0 * * * *".to_owned(), "0 25 * * *".to_owned()]),
+        }),
+        ..EnvConfig::default()
+    };
+    let mut test_toml = WranglerToml {
+        account_id: Some(ACCOUNT_ID),
+        triggers: Some(Triggers {
+            crons: Some(vec!["* * * *".to_owned()]),
+        }),
+        ..WranglerToml::webpack(script_name)
+    };
+    test_toml
+        .env
+        .get_or_insert_with(Default::default)
+        .insert("b", env_config);
+
+    let toml_string = toml::to_string(&test_toml).unwrap();
+    let manifest = Manifest::from_str(&toml_string).unwrap();
+
+    let error = manifest.get_deployments(None).unwrap_err().to_string();
+    assert!(error.starts_with("triggers.crons[0]:"), "{}", error);
+
+    let error = manifest.get_deployments(Some("b")).unwrap_err().to_string();
+    assert!(error.starts_with("env.b.triggers.crons[1]:"), "{}", error);
+}
+
 #[test]
 fn it_cat_get_inherited_env_schedules() {
     // with no zoned, zoneless, or schedule targets in environment, we error
@@ -1126,3 +1166,189 @@ fn when_top_level_zoned_env_zoned_single_route() {
 
     assert_eq!(actual_deployments, expected_deployments);
 }
+
+// TARGET INHERITANCE TESTS
+fn test_target(test_toml: &WranglerToml, environment: Option<&str>) -> Target {
+    let toml_string = toml::to_string(test_toml).unwrap();
+    let manifest = Manifest::from_str(&toml_string).unwrap();
+
+    manifest.get_target(environment, false).unwrap()
+}
+
+fn migration_tags(target: &Target) -> Vec<String> {
+    match &target.migrations {
+        Some(Migrations::List { migrations, .. }) => migrations
+            .iter()
+            .map(|migration| migration.tag.clone().unwrap())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn paths(entries: &[(&str, &str)]) -> HashMap<String, PathBuf> {
+    entries
+        .iter()
+        .map(|(binding, path)| (binding.to_string(), PathBuf::from(path)))
+        .collect()
+}
+
+fn top_level_with_everything(env_config: EnvConfig) -> WranglerToml {
+    let mut vars = HashMap::new();
+    vars.insert("TOP_LEVEL_VAR", "top");
+    let mut text_blobs = HashMap::new();
+    text_blobs.insert("SHARED_BLOB", "top.txt");
+    text_blobs.insert("TOP_LEVEL_BLOB", "top.txt");
+    let mut wasm_modules = HashMap::new();
+    wasm_modules.insert("SHARED_WASM", "top.wasm");
+
+    WranglerToml {
+        account_id: Some(ACCOUNT_ID),
+        workers_dev: Some(true),
+        webpack_config: Some("webpack.top.js"),
+        usage_model: Some("bundled"),
+        kv_namespaces: Some(vec![KvConfig {
+            binding: Some("TOP_LEVEL_KV"),
+            id: Some("top"),
+        }]),
+        vars: Some(vars),
+        text_blobs: Some(text_blobs),
+        wasm_modules: Some(wasm_modules),
+        dev: Some(DevConfig {
+            ip: Some("0.0.0.0"),
+            port: Some(8000),
+            ..Default::default()
+        }),
+        migrations: Some(vec![MigrationConfig {
+            tag: Some("v1"),
+            new_classes: Some(vec!["Counter"]),
+        }]),
+        ..WranglerToml::with_env("inheritance", env_config)
+    }
+}
+
+#[test]
+fn it_inherits_top_level_fields_into_an_empty_env() {
+    let test_toml = top_level_with_everything(EnvConfig::default());
+    let target = test_target(&test_toml, Some(TEST_ENV_NAME));
+
+    assert_eq!(target.name, format!("inheritance-{}", TEST_ENV_NAME));
+    assert_eq!(target.account_id.load().unwrap(), ACCOUNT_ID);
+    assert_eq!(target.webpack_config, Some("webpack.top.js".to_string()));
+    assert_eq!(target.usage_model, Some(UsageModel::Bundled));
+    assert_eq!(migration_tags(&target), vec!["v1"]);
+    assert_eq!(
+        target.text_blobs,
+        Some(paths(&[
+            ("SHARED_BLOB", "top.txt"),
+            ("TOP_LEVEL_BLOB", "top.txt")
+        ]))
+    );
+    assert_eq!(
+        target.wasm_modules,
+        Some(paths(&[("SHARED_WASM", "top.wasm")]))
+    );
+}
+
+#[test]
+fn it_does_not_inherit_bindings_into_an_env() {
+    let test_toml = top_level_with_everything(EnvConfig::default());
+    let target = test_target(&test_toml, Some(TEST_ENV_NAME));
+
+    assert!(target.kv_namespaces.is_empty());
+    assert_eq!(target.vars, None);
+    assert_eq!(target.durable_objects, None);
+
+    let top_level = test_target(&test_toml, None);
+    assert_eq!(top_level.kv_namespaces.len(), 1);
+    assert!(top_level.vars.is_some());
+}
+
+#[test]
+fn it_overrides_inherited_fields_in_an_env() {
+    let env_config = EnvConfig {
+        account_id: Some("envaccountid"),
+        webpack_config: Some("webpack.env.js"),
+        usage_model: Some("unbound"),
+        migrations: Some(vec![
+            MigrationConfig {
+                tag: Some("env-v1"),
+                new_classes: Some(vec!["Counter"]),
+            },
+            MigrationConfig {
+                tag: Some("env-v2"),
+                new_classes: Some(vec!["Limiter"]),
+            },
+        ]),
+        ..EnvConfig::custom_script_name("custom-name")
+    };
+    let test_toml = top_level_with_everything(env_config);
+    let target = test_target(&test_toml, Some(TEST_ENV_NAME));
+
+    assert_eq!(target.name, "custom-name");
+    assert_eq!(target.account_id.load().unwrap(), "envaccountid");
+    assert_eq!(target.webpack_config, Some("webpack.env.js".to_string()));
+    assert_eq!(target.usage_model, Some(UsageModel::Unbound));
+    assert_eq!(migration_tags(&target), vec!["env-v1", "env-v2"]);
+
+    let top_level = test_target(&test_toml, None);
+    assert_eq!(top_level.usage_model, Some(UsageModel::Bundled));
+    assert_eq!(migration_tags(&top_level), vec!["v1"]);
+}
+
+#[test]
+fn it_merges_file_bindings_into_an_env() {
+    let mut text_blobs = HashMap::new();
+    text_blobs.insert("SHARED_BLOB", "env.txt");
+    text_blobs.insert("ENV_BLOB", "env.txt");
+    let mut wasm_modules = HashMap::new();
+    wasm_modules.insert("ENV_WASM", "env.wasm");
+
+    let env_config = EnvConfig {
+        text_blobs: Some(text_blobs),
+        wasm_modules: Some(wasm_modules),
+        ..EnvConfig::default()
+    };
+    let test_toml = top_level_with_everything(env_config);
+    let target = test_target(&test_toml, Some(TEST_ENV_NAME));
+
+    assert_eq!(
+        target.text_blobs,
+        Some(paths(&[
+            ("SHARED_BLOB", "env.txt"),
+            ("TOP_LEVEL_BLOB", "top.txt"),
+            ("ENV_BLOB", "env.txt"),
+        ]))
+    );
+    assert_eq!(
+        target.wasm_modules,
+        Some(paths(&[
+            ("SHARED_WASM", "top.wasm"),
+            ("ENV_WASM", "env.wasm")
+        ]))
+    );
+}
+
+#[test]
+fn it_merges_dev_settings_into_an_env() {
+    let env_config = EnvConfig {
+        dev: Some(DevConfig {
+            port: Some(9000),
+            local_protocol: Some("https"),
+            ..Default::default()
+        }),
+        ..EnvConfig::default()
+    };
+    let test_toml = top_level_with_everything(env_config);
+    let toml_string = toml::to_string(&test_toml).unwrap();
+    let manifest = Manifest::from_str(&toml_string).unwrap();
+
+    let dev = manifest.get_dev(Some(TEST_ENV_NAME)).unwrap().unwrap();
+    assert_eq!(dev.ip, Some("0.0.0.0".parse().unwrap()));
+    assert_eq!(dev.port, Some(9000));
+    assert_eq!(dev.local_protocol, Some(Protocol::Https));
+    assert_eq!(dev.upstream_protocol, None);
+
+    let top_level = manifest.get_dev(None).unwrap().unwrap();
+    assert_eq!(top_level.port, Some(8000));
+    assert_eq!(top_level.local_protocol, None);
+}
@@ -76,6 +76,37 @@ fn it_builds_from_environments_config_with_kv() {
     }
 }
 
+#[test]
+fn it_builds_migrations_from_config() {
+    let toml_path = toml_fixture_path("migrations");
+    let manifest = Manifest::new(&toml_path).unwrap();
+
+    let target = manifest.get_target(None, false).unwrap();
+    let migrations = match target.migrations {
+        Some(migrations::Migrations::List {
+            script_tag: None,
+            migrations,
+        }) => migrations,
+        other => panic!("expected migrations from config, found {:?}", other),
+    };
+
+    let tags: Vec<_> = migrations.iter().map(|m| m.tag.as_deref()).collect();
+    assert_eq!(tags, vec![Some("v1"), Some("v2")]);
+    assert_eq!(
+        migrations[0].migration.durable_objects.new_classes,
+        vec!["Counter".to_string()]
+    );
+    assert_eq!(
+        migrations[1].migration.durable_objects.deleted_classes,
+        vec!["Room".to_string()]
+    );
+    assert!(migrations[1]
+        .migration
+        .durable_objects
+        .new_classes
+        .is_empty());
+}
+
 #[test]
 fn parses_same_from_config_path_as_string() {
     env::remove_var("CF_ACCOUNT_ID");
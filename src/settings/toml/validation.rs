@@ -0,0 +1,932 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::IpAddr;
+
+use toml::value::{Table, Value};
+use toml_edit::{Decor, Document, Item};
+
+use crate::commands::kv::namespace::validate_binding;
+use crate::commands::validate_worker_name;
+use crate::settings::toml::cron::Cron;
+use crate::sites::parse_glob;
+use crate::upload::form::ModuleType;
+
+/// A single problem found while validating a configuration file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Path to the offending value, e.g. `env.staging.kv_namespaces[0].binding`.
+    /// Empty for problems with the file as a whole.
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}:{}: {}", self.line, self.column, self.message)
+        } else {
+            write!(
+                f,
+                "{}:{}: {}: {}",
+                self.line, self.column, self.path, self.message
+            )
+        }
+    }
+}
+
+/// Checks a wrangler.toml against the schema of `Manifest` and everything it
+/// contains, without touching the network. Every problem found is returned,
+/// ordered by where it appears in the file.
+pub fn validate(source: &str) -> Vec<Diagnostic> {
+    let document = match source.parse::<Value>() {
+        Ok(Value::Table(document)) => document,
+        Ok(_) => unreachable!("a TOML document is always a table"),
+        Err(e) => {
+            let (line, column) = e.line_col().unwrap_or((0, 0));
+            return vec![Diagnostic {
+                path: String::new(),
+                line: line + 1,
+                column: column + 1,
+                message: e.to_string(),
+            }];
+        }
+    };
+
+    let mut validator = Validator::default();
+    validator.check_table(&document, MANIFEST, "", false);
+    if document.contains_key("extends") {
+        // required keys may be set in the file this one extends
+        validator.problems.retain(|(path, message)| {
+            !(path.is_empty() && message.starts_with("missing required key"))
+        });
+    }
+
+    let locations = locate(source);
+    let mut diagnostics: Vec<Diagnostic> = validator
+        .problems
+        .into_iter()
+        .map(|(path, message)| {
+            let (line, column) = location_of(&locations, &path);
+            Diagnostic {
+                path,
+                line,
+                column,
+                message,
+            }
+        })
+        .collect();
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    diagnostics
+}
+
+#[derive(Clone, Copy)]
+enum Schema {
+    String,
+    Boolean,
+    Port,
+    IpAddr,
+    WorkerName,
+    Binding,
+    Cron,
+    ModuleType,
+    OneOf(&'static [&'static str]),
+    /// An integer, which must be one of these
+    OneOfIntegers(&'static [i64]),
+    Array(&'static Schema),
+    Table(&'static [Field]),
+    /// A table whose keys are binding names
+    Bindings(&'static Schema),
+    /// A table whose keys are globs matching the paths of a Workers Site's files
+    Globs(&'static Schema),
+    /// Any value that can be bound as plain text or JSON
+    Var,
+    /// `[build.upload]`, whose keys depend on its `format`
+    UploadFormat,
+    /// `[env.*]`, each of which is checked against the top level fields
+    Environments,
+}
+
+impl Schema {
+    fn expected(self) -> &'static str {
+        match self {
+            Schema::String
+            | Schema::IpAddr
+            | Schema::WorkerName
+            | Schema::Binding
+            | Schema::Cron
+            | Schema::ModuleType
+            | Schema::OneOf(_) => "a string",
+            Schema::Boolean => "a boolean",
+            Schema::Port | Schema::OneOfIntegers(_) => "an integer",
+            Schema::Array(_) => "an array",
+            Schema::Table(_)
+            | Schema::Bindings(_)
+            | Schema::Globs(_)
+            | Schema::UploadFormat
+            | Schema::Environments => "a table",
+            Schema::Var => "a string, number, boolean, array or table",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    Anywhere,
+    /// The field is only read from the top level, so setting it in `[env.*]` does nothing
+    TopLevel,
+}
+
+#[derive(Clone, Copy)]
+struct Field {
+    key: &'static str,
+    aliases: &'static [&'static str],
+    schema: Schema,
+    required: bool,
+    scope: Scope,
+}
+
+impl Field {
+    const fn new(key: &'static str, schema: Schema) -> Field {
+        Field {
+            key,
+            aliases: &[],
+            schema,
+            required: false,
+            scope: Scope::Anywhere,
+        }
+    }
+
+    const fn required(self) -> Field {
+        Field {
+            required: true,
+            ..self
+        }
+    }
+
+    const fn top_level(self) -> Field {
+        Field {
+            scope: Scope::TopLevel,
+            ..self
+        }
+    }
+
+    const fn alias(self, aliases: &'static [&'static str]) -> Field {
+        Field { aliases, ..self }
+    }
+
+    fn names(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.key).chain(self.aliases.iter().copied())
+    }
+}
+
+const MANIFEST: &[Field] = &[
+    Field::new("extends", Schema::String).top_level(),
+    Field::new("name", Schema::WorkerName),
+    Field::new("type", Schema::OneOf(&["javascript", "rust", "webpack"]))
+        .required()
+        .top_level(),
+    Field::new("account_id", Schema::String),
+    Field::new("workers_dev", Schema::Boolean),
+    Field::new("route", Schema::String),
+    Field::new("routes", Schema::Array(&Schema::String)),
+    Field::new("zone_id", Schema::String),
+    Field::new("webpack_config", Schema::String),
+    Field::new("build", Schema::Table(BUILD)),
+    Field::new("private", Schema::Boolean),
+    Field::new("site", Schema::Table(SITE)),
+    Field::new("dev", Schema::Table(DEV)),
+    Field::new("kv_namespaces", Schema::Array(&Schema::Table(KV_NAMESPACE)))
+        .alias(&["kv-namespaces"]),
+    Field::new("env", Schema::Environments).top_level(),
+    Field::new("vars", Schema::Bindings(&Schema::Var)),
+    Field::new("text_blobs", Schema::Bindings(&Schema::String)),
+    Field::new("wasm_modules", Schema::Bindings(&Schema::String)),
+    Field::new("triggers", Schema::Table(TRIGGERS)),
+    Field::new("durable_objects", Schema::Table(DURABLE_OBJECTS)),
+    Field::new("usage_model", Schema::OneOf(&["bundled", "unbound"])),
+    Field::new("migrations", Schema::Array(&Schema::Table(MIGRATION))),
+    Field::new("workers", Schema::Array(&Schema::Table(WORKER))).top_level(),
+];
+
+const WORKER: &[Field] = &[
+    Field::new("name", Schema::WorkerName).required(),
+    Field::new("type", Schema::OneOf(&["javascript", "rust", "webpack"])),
+    Field::new("account_id", Schema::String),
+    Field::new("workers_dev", Schema::Boolean),
+    Field::new("route", Schema::String),
+    Field::new("routes", Schema::Array(&Schema::String)),
+    Field::new("zone_id", Schema::String),
+    Field::new("webpack_config", Schema::String),
+    Field::new("build", Schema::Table(BUILD)),
+    Field::new("site", Schema::Table(SITE)),
+    Field::new("dev", Schema::Table(DEV)),
+    Field::new("kv_namespaces", Schema::Array(&Schema::Table(KV_NAMESPACE)))
+        .alias(&["kv-namespaces"]),
+    Field::new("env", Schema::Environments),
+    Field::new("vars", Schema::Bindings(&Schema::Var)),
+    Field::new("text_blobs", Schema::Bindings(&Schema::String)),
+    Field::new("wasm_modules", Schema::Bindings(&Schema::String)),
+    Field::new("triggers", Schema::Table(TRIGGERS)),
+    Field::new("durable_objects", Schema::Table(DURABLE_OBJECTS)),
+    Field::new("usage_model", Schema::OneOf(&["bundled", "unbound"])),
+    Field::new("migrations", Schema::Array(&Schema::Table(MIGRATION))),
+];
+
+const BUILD: &[Field] = &[
+    Field::new("command", Schema::String),
+    Field::new("cwd", Schema::String),
+    Field::new("watch_dir", Schema::String),
+    Field::new("upload", Schema::UploadFormat).required(),
+];
+
+const SERVICE_WORKER_UPLOAD: &[Field] = &[Field::new("format", Schema::String)];
+
+const MODULES_UPLOAD: &[Field] = &[
+    Field::new("format", Schema::String),
+    Field::new("main", Schema::String).required(),
+    Field::new("dir", Schema::String),
+    Field::new("rules", Schema::Array(&Schema::Table(MODULE_RULE))),
+];
+
+const MODULE_RULE: &[Field] = &[
+    Field::new("globs", Schema::Array(&Schema::String)).required(),
+    Field::new("type", Schema::ModuleType).required(),
+    Field::new("fallthrough", Schema::Boolean),
+];
+
+const SITE: &[Field] = &[
+    Field::new("bucket", Schema::String).required(),
+    Field::new("entry-point", Schema::String),
+    Field::new("include", Schema::Array(&Schema::String)),
+    Field::new("exclude", Schema::Array(&Schema::String)),
+    Field::new(
+        "precompress",
+        Schema::Array(&Schema::OneOf(&["br", "gzip"])),
+    ),
+    Field::new("manifest_version", Schema::OneOfIntegers(&[1, 2])),
+    Field::new(
+        "headers",
+        Schema::Array(&Schema::Globs(&Schema::Table(ASSET_HEADERS))),
+    ),
+];
+
+const ASSET_HEADERS: &[Field] = &[
+    Field::new("cache-control", Schema::String),
+    Field::new("content-type", Schema::String),
+];
+
+const DEV: &[Field] = &[
+    Field::new("ip", Schema::IpAddr),
+    Field::new("port", Schema::Port),
+    Field::new("local_protocol", Schema::OneOf(&["http", "https"])),
+    Field::new("upstream_protocol", Schema::OneOf(&["http", "https"])),
+    Field::new("local_runtime", Schema::String),
+];
+
+const KV_NAMESPACE: &[Field] = &[
+    Field::new("binding", Schema::Binding).required(),
+    Field::new("id", Schema::String),
+    Field::new("preview_id", Schema::String),
+];
+
+const TRIGGERS: &[Field] = &[Field::new("crons", Schema::Array(&Schema::Cron)).required()];
+
+const DURABLE_OBJECTS: &[Field] = &[Field::new(
+    "classes",
+    Schema::Array(&Schema::Table(DURABLE_OBJECTS_CLASS)),
+)
+.alias(&["bindings"])];
+
+const DURABLE_OBJECTS_CLASS: &[Field] = &[
+    Field::new("binding", Schema::Binding)
+        .required()
+        .alias(&["name"]),
+    Field::new("class_name", Schema::String).required(),
+    Field::new("script_name", Schema::WorkerName),
+];
+
+const MIGRATION: &[Field] = &[
+    Field::new("tag", Schema::String).required(),
+    Field::new("new_classes", Schema::Array(&Schema::String)),
+    Field::new("deleted_classes", Schema::Array(&Schema::String)),
+    Field::new(
+        "renamed_classes",
+        Schema::Array(&Schema::Table(RENAMED_CLASS)),
+    ),
+    Field::new(
+        "transferred_classes",
+        Schema::Array(&Schema::Table(TRANSFERRED_CLASS)),
+    ),
+];
+
+const RENAMED_CLASS: &[Field] = &[
+    Field::new("from", Schema::String).required(),
+    Field::new("to", Schema::String).required(),
+];
+
+const TRANSFERRED_CLASS: &[Field] = &[
+    Field::new("from", Schema::String).required(),
+    Field::new("from_script", Schema::WorkerName).required(),
+    Field::new("to", Schema::String).required(),
+];
+
+#[derive(Default)]
+struct Validator {
+    problems: Vec<(String, String)>,
+}
+
+impl Validator {
+    fn report(&mut self, path: &str, message: String) {
+        self.problems.push((path.to_string(), message));
+    }
+
+    fn check_table(&mut self, table: &Table, fields: &[Field], path: &str, environment: bool) {
+        for (key, value) in table {
+            let key_path = join(path, key);
+            match fields
+                .iter()
+                .find(|field| field.names().any(|name| name == key))
+            {
+                None => self.report(&key_path, format!("unknown key `{}`", key)),
+                Some(field) if environment && field.scope == Scope::TopLevel => self.report(
+                    &key_path,
+                    format!(
+                        "`{}` is not inheritable into [env.*]; it can only be set at the top level",
+                        key
+                    ),
+                ),
+                Some(field) => self.check_value(value, field.schema, &key_path),
+            }
+        }
+
+        for field in fields {
+            let present: Vec<&str> = field
+                .names()
+                .filter(|name| table.contains_key(*name))
+                .collect();
+            if present.len() > 1 {
+                self.report(
+                    &join(path, present[1]),
+                    format!(
+                        "`{}` and `{}` are the same field; only one of them may be set",
+                        present[0], present[1]
+                    ),
+                );
+            }
+
+            let applies = !(environment && field.scope == Scope::TopLevel);
+            if present.is_empty() && field.required && applies {
+                self.report(path, format!("missing required key `{}`", field.key));
+            }
+        }
+    }
+
+    fn check_value(&mut self, value: &Value, schema: Schema, path: &str) {
+        match (schema, value) {
+            // what a `${VAR}` stands for is only known once the file is read, so strings with
+            // them are only checked for being strings
+            (schema, Value::String(string))
+                if string.contains("${") && schema.expected() == "a string" => {}
+            (Schema::String, Value::String(_)) | (Schema::Boolean, Value::Boolean(_)) => {}
+            (Schema::WorkerName, Value::String(name)) => {
+                if let Err(e) = validate_worker_name(name) {
+                    self.report(path, e.to_string());
+                }
+            }
+            (Schema::Binding, Value::String(binding)) => {
+                if let Err(e) = validate_binding(binding) {
+                    self.report(path, e.to_string());
+                }
+            }
+            (Schema::Cron, Value::String(cron)) => {
+                if let Err(e) = cron.parse::<Cron>() {
+                    self.report(path, format!("invalid cron \"{}\": {}", cron, e));
+                }
+            }
+            (Schema::IpAddr, Value::String(ip)) => {
+                if ip.parse::<IpAddr>().is_err() {
+                    self.report(path, format!("\"{}\" is not a valid IP address", ip));
+                }
+            }
+            (Schema::Port, Value::Integer(port)) => {
+                if u16::try_from(*port).is_err() {
+                    self.report(path, format!("{} is not a valid port", port));
+                }
+            }
+            (Schema::ModuleType, Value::String(module_type)) => {
+                if !ModuleType::iter().any(|known| known.name() == module_type) {
+                    let known: Vec<&str> = ModuleType::iter().map(ModuleType::name).collect();
+                    self.report(path, one_of(&known, module_type));
+                }
+            }
+            (Schema::OneOf(options), Value::String(option)) => {
+                if !options.contains(&option.as_str()) {
+                    self.report(path, one_of(options, option));
+                }
+            }
+            (Schema::OneOfIntegers(options), Value::Integer(option)) => {
+                if !options.contains(option) {
+                    let options: Vec<String> = options.iter().map(i64::to_string).collect();
+                    self.report(
+                        path,
+                        format!("expected one of {}, found {}", options.join(", "), option),
+                    );
+                }
+            }
+            (Schema::Array(schema), Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.check_value(item, *schema, &format!("{}[{}]", path, index));
+                }
+            }
+            (Schema::Table(fields), Value::Table(table)) => {
+                self.check_table(table, fields, path, false)
+            }
+            (Schema::Bindings(schema), Value::Table(table)) => {
+                for (binding, value) in table {
+                    let binding_path = join(path, binding);
+                    if let Err(e) = validate_binding(binding) {
+                        self.report(&binding_path, e.to_string());
+                    }
+                    self.check_value(value, *schema, &binding_path);
+                }
+            }
+            (Schema::Globs(schema), Value::Table(table)) => {
+                for (glob, value) in table {
+                    let glob_path = join(path, glob);
+                    if let Err(e) = parse_glob(glob) {
+                        self.report(&glob_path, e.to_string());
+                    }
+                    self.check_value(value, *schema, &glob_path);
+                }
+            }
+            (Schema::Var, Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.check_value(item, Schema::Var, &format!("{}[{}]", path, index));
+                }
+            }
+            (Schema::Var, Value::Table(table)) => {
+                for (key, value) in table {
+                    self.check_value(value, Schema::Var, &join(path, key));
+                }
+            }
+            // datetimes have no JSON equivalent
+            (Schema::Var, value) if !value.is_datetime() => {}
+            (Schema::UploadFormat, Value::Table(table)) => match table.get("format") {
+                Some(Value::String(format)) if format == "service-worker" => {
+                    self.check_table(table, SERVICE_WORKER_UPLOAD, path, false)
+                }
+                Some(Value::String(format)) if format == "modules" => {
+                    self.check_table(table, MODULES_UPLOAD, path, false)
+                }
+                Some(format) => self.check_value(
+                    format,
+                    Schema::OneOf(&["service-worker", "modules"]),
+                    &join(path, "format"),
+                ),
+                None => self.report(path, "missing required key `format`".to_string()),
+            },
+            (Schema::Environments, Value::Table(environments)) => {
+                for (name, environment) in environments {
+                    let environment_path = join(path, name);
+                    match environment {
+                        Value::Table(table) => {
+                            self.check_table(table, MANIFEST, &environment_path, true)
+                        }
+                        other => self.check_value(other, Schema::Table(&[]), &environment_path),
+                    }
+                }
+            }
+            (schema, value) => self.report(
+                path,
+                format!("expected {}, found {}", schema.expected(), value.type_str()),
+            ),
+        }
+    }
+}
+
+fn one_of(options: &[&str], found: &str) -> String {
+    let options: Vec<String> = options
+        .iter()
+        .map(|option| format!("\"{}\"", option))
+        .collect();
+    format!(
+        "expected one of {}, found \"{}\"",
+        options.join(", "),
+        found
+    )
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Finds the closest located ancestor of `path`, falling back to the start of the file.
+fn location_of(locations: &HashMap<String, (usize, usize)>, path: &str) -> (usize, usize) {
+    let mut path = path;
+    loop {
+        if let Some(location) = locations.get(path) {
+            return *location;
+        }
+        match path.rfind(|c| c == '.' || c == '[') {
+            Some(index) => path = &path[..index],
+            None => return (1, 1),
+        }
+    }
+}
+
+/// Marks where a key or value starts in a rendered document, around the index of its path.
+/// Control characters can't appear in a valid TOML document, so this can't clash with its
+/// contents.
+const MARKER: char = '\u{1}';
+
+/// Records the line and column of every key and array element in a TOML document that is
+/// already known to be valid, and of the header of every element of an array of tables, keyed
+/// by the same paths the validator reports.
+///
+/// toml_edit doesn't keep spans, but it keeps the whitespace and comments in front of every key
+/// and value. Appending a marker to each of those and rendering the document again puts every
+/// marker right where its key or value starts in the source.
+fn locate(source: &str) -> HashMap<String, (usize, usize)> {
+    let mut locations = HashMap::new();
+    let mut document = match source.parse::<Document>() {
+        Ok(document) => document,
+        Err(_) => return locations,
+    };
+    let mut paths = Vec::new();
+    mark_table(document.as_table_mut(), "", &mut paths);
+
+    let rendered = document.to_string();
+    let mut chars = rendered.chars();
+    let (mut line, mut column) = (1, 1);
+    while let Some(c) = chars.next() {
+        match c {
+            MARKER => {
+                let index: String = chars.by_ref().take_while(|c| *c != MARKER).collect();
+                if let Some(path) = index.parse::<usize>().ok().and_then(|i| paths.get(i)) {
+                    // a key shared by several table headers is located at the first of them
+                    locations.entry(path.clone()).or_insert((line, column));
+                }
+            }
+            '\n' => {
+                line += 1;
+                column = 1;
+            }
+            _ => column += 1,
+        }
+    }
+    locations
+}
+
+fn mark(decor: &mut Decor, path: String, paths: &mut Vec<String>) {
+    let prefix = format!(
+        "{}{}{}{}",
+        decor.prefix().unwrap_or_default(),
+        MARKER,
+        paths.len(),
+        MARKER
+    );
+    decor.set_prefix(prefix);
+    paths.push(path);
+}
+
+fn mark_table(table: &mut toml_edit::Table, path: &str, paths: &mut Vec<String>) {
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+    for key in keys {
+        let key_path = join(path, &key);
+        if let Some(decor) = table.key_decor_mut(&key) {
+            mark(decor, key_path.clone(), paths);
+        }
+        match table.get_mut(&key) {
+            Some(Item::Table(table)) => mark_table(table, &key_path, paths),
+            Some(Item::ArrayOfTables(tables)) => {
+                for (index, table) in tables.iter_mut().enumerate() {
+                    let element_path = format!("{}[{}]", key_path, index);
+                    mark(table.decor_mut(), element_path.clone(), paths);
+                    mark_table(table, &element_path, paths);
+                }
+            }
+            Some(Item::Value(value)) => mark_value(value, &key_path, paths),
+            _ => {}
+        }
+    }
+}
+
+fn mark_value(value: &mut toml_edit::Value, path: &str, paths: &mut Vec<String>) {
+    match value {
+        toml_edit::Value::Array(array) => {
+            for (index, element) in array.iter_mut().enumerate() {
+                let element_path = format!("{}[{}]", path, index);
+                mark(element.decor_mut(), element_path.clone(), paths);
+                mark_value(element, &element_path, paths);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+            for key in keys {
+                let key_path = join(path, &key);
+                if let Some(decor) = table.key_decor_mut(&key) {
+                    mark(decor, key_path.clone(), paths);
+                }
+                if let Some(value) = table.get_mut(&key) {
+                    mark_value(value, &key_path, paths);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(source: &str) -> Vec<(String, usize, usize)> {
+        validate(source)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.path, diagnostic.line, diagnostic.column))
+            .collect()
+    }
+
+    #[test]
+    fn it_accepts_a_valid_config() {
+        let source = r#"
+name = "worker"
+type = "javascript"
+account_id = "abc"
+workers_dev = true
+kv-namespaces = [
+    { binding = "MY_KV", id = "1234", preview_id = "5678" },
+]
+vars = { FOO = "bar" }
+usage_model = "unbound"
+
+[build]
+command = "npm run build"
+[build.upload]
+format = "modules"
+main = "./index.mjs"
+
+[[build.upload.rules]]
+globs = ["**/*.wasm"]
+type = "CompiledWasm"
+
+[dev]
+ip = "127.0.0.1"
+port = 8787
+
+[triggers]
+crons = ["*/5 * * * *", "0 0 L * *"]
+
+[durable_objects]
+bindings = [{ name = "COUNTER", class_name = "Counter" }]
+
+[[migrations]]
+tag = "v1"
+new_classes = ["Counter"]
+
+[env.staging]
+name = "worker-staging"
+route = "example.com/*"
+zone_id = "def"
+"#;
+        assert_eq!(validate(source), vec![]);
+    }
+
+    #[test]
+    fn it_accepts_placeholders_for_strings() {
+        let source = r#"
+name = "${WORKER_NAME}"
+type = "${TYPE:-javascript}"
+workers_dev = "${WORKERS_DEV}"
+"#;
+        let diagnostics = validate(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, "workers_dev");
+    }
+
+    #[test]
+    fn it_reports_syntax_errors_with_a_position() {
+        let diagnostics = validate("name = \"worker\"\ntype = \n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 2);
+    }
+
+    #[test]
+    fn it_reports_every_problem_with_its_position() {
+        let source = r#"name = "worker"
+type = "javascript"
+workers_dev = "yes"
+colour = "blue"
+
+[site]
+bucket = "./public"
+entry_point = "workers-site"
+
+[[kv_namespaces]]
+binding = "MY_KV"
+id = "1"
+
+[[kv_namespaces]]
+binding = "2-KV"
+
+[triggers]
+crons = [
+  "* * * * *",
+  "61 * * * *",
+]
+"#;
+        assert_eq!(
+            problems(source),
+            vec![
+                ("workers_dev".to_string(), 3, 1),
+                ("colour".to_string(), 4, 1),
+                ("site.entry_point".to_string(), 8, 1),
+                ("kv_namespaces[1].binding".to_string(), 15, 1),
+                ("triggers.crons[1]".to_string(), 20, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_missing_required_keys() {
+        let source = r#"name = "worker"
+[site]
+entry-point = "workers-site"
+"#;
+        let diagnostics = validate(source);
+        let messages: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "missing required key `type`",
+                "missing required key `bucket`"
+            ]
+        );
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (2, 2));
+    }
+
+    #[test]
+    fn it_reports_fields_that_are_not_inheritable() {
+        let source = r#"name = "worker"
+type = "webpack"
+
+[env.production]
+type = "javascript"
+usage_model = "bundled"
+workers_dev = true
+
+[env.production.dev]
+port = 8000
+
+[env.production.env.nested]
+name = "worker-nested"
+"#;
+        assert_eq!(
+            problems(source),
+            vec![
+                ("env.production.type".to_string(), 5, 1),
+                ("env.production.env".to_string(), 12, 17),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_invalid_bindings_in_inline_tables() {
+        let source = r#"name = "worker"
+type = "webpack"
+vars = { GOOD = "1", "not ok" = "2" }
+text_blobs = { BLOB = 3 }
+durable_objects = { classes = [{ binding = "1DO", class_name = "Thing" }] }
+"#;
+        assert_eq!(
+            problems(source),
+            vec![
+                ("vars.not ok".to_string(), 3, 22),
+                ("text_blobs.BLOB".to_string(), 4, 16),
+                ("durable_objects.classes[0].binding".to_string(), 5, 34),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_checks_site_settings() {
+        let source = r#"name = "worker"
+type = "webpack"
+
+[site]
+bucket = "./public"
+precompress = ["br", "zstd"]
+manifest_version = 3
+
+[[site.headers]]
+"/assets/**" = { cache-control = "max-age=31536000, immutable" }
+"assets/*.wasm" = { content-type = "application/wasm", expires = "never" }
+"#;
+        assert_eq!(
+            problems(source),
+            vec![
+                ("site.precompress[1]".to_string(), 6, 22),
+                ("site.manifest_version".to_string(), 7, 1),
+                ("site.headers[0].assets/*.wasm".to_string(), 11, 1),
+                ("site.headers[0].assets/*.wasm.expires".to_string(), 11, 56),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_accepts_typed_vars() {
+        let source = r#"name = "worker"
+type = "webpack"
+vars = { COUNT = 1, FLAG = true, WHEN = 1979-05-27 }
+
+[env.staging.vars]
+LIMITS = { max = 10, ratio = 0.5 }
+HOSTS = ["a.example.com", "b.example.com"]
+"#;
+        assert_eq!(problems(source), vec![("vars.WHEN".to_string(), 3, 34)]);
+    }
+
+    #[test]
+    fn it_reports_aliases_set_together() {
+        let source = r#"name = "worker"
+type = "webpack"
+kv_namespaces = []
+kv-namespaces = []
+"#;
+        assert_eq!(problems(source), vec![("kv-namespaces".to_string(), 4, 1)]);
+    }
+
+    #[test]
+    fn it_lets_required_keys_come_from_a_base_file() {
+        let source = r#"extends = "../base.toml"
+name = "worker"
+
+[env.staging]
+extends = "../staging.toml"
+"#;
+        assert_eq!(
+            problems(source),
+            vec![("env.staging.extends".to_string(), 5, 1)]
+        );
+    }
+
+    #[test]
+    fn it_checks_each_worker() {
+        let source = r#"type = "javascript"
+account_id = "abc"
+
+[[workers]]
+name = "api"
+workers_dev = true
+
+[[workers]]
+routes = ["example.com/*"]
+
+[[workers]]
+name = "auth"
+[workers.env.staging]
+type = "webpack"
+"#;
+        assert_eq!(
+            problems(source),
+            vec![
+                ("workers[1]".to_string(), 8, 1),
+                ("workers[2].env.staging.type".to_string(), 14, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_checks_upload_formats() {
+        let source = r#"name = "worker"
+type = "javascript"
+[build.upload]
+format = "service-worker"
+main = "index.js"
+"#;
+        assert_eq!(
+            problems(source),
+            vec![("build.upload.main".to_string(), 5, 1)]
+        );
+
+        let source = r#"name = "worker"
+type = "javascript"
+[build.upload]
+format = "es-modules"
+"#;
+        assert_eq!(
+            problems(source),
+            vec![("build.upload.format".to_string(), 4, 1)]
+        );
+    }
+}
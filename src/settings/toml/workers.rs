@@ -0,0 +1,200 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_with::rust::string_empty_as_none;
+
+use super::UsageModel;
+use crate::settings::toml::builder::Builder;
+use crate::settings::toml::dev::Dev;
+use crate::settings::toml::durable_objects::DurableObjects;
+use crate::settings::toml::environment::Environment;
+use crate::settings::toml::kv_namespace::ConfigKvNamespace;
+use crate::settings::toml::migrations::MigrationConfig;
+use crate::settings::toml::site::Site;
+use crate::settings::toml::target_type::TargetType;
+use crate::settings::toml::triggers::Triggers;
+use crate::settings::toml::Manifest;
+
+/// One of several workers declared with `[[workers]]` in a single configuration file.
+/// Unset fields fall back to the shared defaults at the top level of the file; see
+/// `Manifest::worker` for which fields are shared.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Worker {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub target_type: Option<TargetType>,
+    #[serde(default, with = "string_empty_as_none")]
+    pub account_id: Option<String>,
+    pub workers_dev: Option<bool>,
+    #[serde(default, with = "string_empty_as_none")]
+    pub route: Option<String>,
+    pub routes: Option<Vec<String>>,
+    #[serde(default, with = "string_empty_as_none")]
+    pub zone_id: Option<String>,
+    pub webpack_config: Option<String>,
+    pub build: Option<Builder>,
+    pub site: Option<Site>,
+    pub dev: Option<Dev>,
+    #[serde(alias = "kv-namespaces")]
+    pub kv_namespaces: Option<Vec<ConfigKvNamespace>>,
+    pub env: Option<HashMap<String, Environment>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
+    pub text_blobs: Option<HashMap<String, PathBuf>>,
+    pub wasm_modules: Option<HashMap<String, PathBuf>>,
+    pub triggers: Option<Triggers>,
+    pub durable_objects: Option<DurableObjects>,
+    #[serde(default, with = "string_empty_as_none")]
+    pub usage_model: Option<UsageModel>,
+    pub migrations: Option<Vec<MigrationConfig>>,
+}
+
+/// Orders workers so that every worker comes after the workers hosting the Durable Objects
+/// it binds to (through `script_name`). Otherwise, workers keep the order they were given in.
+pub fn deployment_order(workers: Vec<Manifest>, env: Option<&str>) -> Result<Vec<Manifest>> {
+    let scripts: Vec<String> = workers
+        .iter()
+        .map(|worker| worker.worker_name(env))
+        .collect();
+
+    let mut hosts = Vec::with_capacity(workers.len());
+    for (index, worker) in workers.iter().enumerate() {
+        let durable_objects = match worker.get_environment(env)? {
+            Some(environment) => environment.durable_objects.as_ref(),
+            None => worker.durable_objects.as_ref(),
+        };
+
+        let worker_hosts: BTreeSet<usize> = durable_objects
+            .and_then(|durable_objects| durable_objects.classes.as_ref())
+            .into_iter()
+            .flatten()
+            .filter_map(|class| class.script_name.as_ref())
+            .filter_map(|script_name| scripts.iter().position(|script| script == script_name))
+            .filter(|host| *host != index)
+            .collect();
+        hosts.push(worker_hosts);
+    }
+
+    let mut ordered: Vec<usize> = Vec::with_capacity(workers.len());
+    while ordered.len() < workers.len() {
+        let next = (0..workers.len()).find(|index| {
+            !ordered.contains(index) && hosts[*index].iter().all(|host| ordered.contains(host))
+        });
+
+        match next {
+            Some(index) => ordered.push(index),
+            None => {
+                let remaining: Vec<&str> = (0..workers.len())
+                    .filter(|index| !ordered.contains(index))
+                    .map(|index| scripts[index].as_str())
+                    .collect();
+                anyhow::bail!(
+                    "These workers bind to each other's Durable Objects in a cycle, so there is no order to publish them in: {}",
+                    remaining.join(", ")
+                )
+            }
+        }
+    }
+
+    let mut workers: Vec<Option<Manifest>> = workers.into_iter().map(Some).collect();
+    Ok(ordered
+        .into_iter()
+        .filter_map(|index| workers[index].take())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(toml: &str) -> Manifest {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn it_layers_workers_over_shared_defaults() {
+        let manifest = manifest(
+            r#"
+type = "javascript"
+account_id = "account"
+zone_id = "zone"
+route = "example.com/*"
+vars = { SHARED = "yes" }
+
+[[workers]]
+name = "api"
+routes = ["example.com/api/*"]
+
+[[workers]]
+name = "auth"
+type = "webpack"
+vars = { OWN = "yes" }
+"#,
+        );
+
+        let api = manifest.worker("api").unwrap();
+        assert_eq!(api.name, "api");
+        assert_eq!(api.target_type, TargetType::JavaScript);
+        assert_eq!(api.zone_id.as_deref(), Some("zone"));
+        assert_eq!(api.route, None);
+        assert_eq!(api.routes, Some(vec!["example.com/api/*".to_string()]));
+        assert!(api.vars.unwrap().contains_key("SHARED"));
+        assert_eq!(api.workers, None);
+
+        let auth = manifest.worker("auth").unwrap();
+        assert_eq!(auth.target_type, TargetType::Webpack);
+        assert!(!auth.vars.unwrap().contains_key("SHARED"));
+
+        assert!(manifest.worker("billing").is_err());
+    }
+
+    #[test]
+    fn it_publishes_durable_object_hosts_first() {
+        let manifest = manifest(
+            r#"
+type = "javascript"
+
+[[workers]]
+name = "api"
+durable_objects = { classes = [{ binding = "COUNTER", class_name = "Counter", script_name = "objects" }] }
+
+[[workers]]
+name = "auth"
+
+[[workers]]
+name = "objects"
+durable_objects = { classes = [{ binding = "COUNTER", class_name = "Counter" }] }
+"#,
+        );
+
+        let workers = manifest.all_workers().unwrap();
+        let ordered: Vec<String> = deployment_order(workers, None)
+            .unwrap()
+            .into_iter()
+            .map(|worker| worker.name)
+            .collect();
+
+        assert_eq!(ordered, vec!["auth", "objects", "api"]);
+    }
+
+    #[test]
+    fn it_rejects_durable_object_cycles() {
+        let manifest = manifest(
+            r#"
+type = "javascript"
+
+[[workers]]
+name = "a"
+durable_objects = { classes = [{ binding = "B", class_name = "B", script_name = "b" }] }
+
+[[workers]]
+name = "b"
+durable_objects = { classes = [{ binding = "A", class_name = "A", script_name = "a" }] }
+"#,
+        );
+
+        let workers = manifest.all_workers().unwrap();
+        assert!(deployment_order(workers, None).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::manifest::AssetManifest;
+use crate::settings::get_wrangler_home_dir;
+
+// how long after the namespace was last listed in full its cached keys are trusted
+const MAX_AGE_SECS: i64 = 24 * 60 * 60;
+
+/// The keys in a Workers Sites namespace, as wrangler last left it.
+///
+/// These are cached in the wrangler home directory, so that a publish needn't list every key
+/// in the namespace to find the files it has to upload, and the files the keys are for are
+/// cached too, by path, so a dry run can tell which files a sync would change. Keys are named
+/// for their contents, so a key that's there is never out of date; the cache only goes wrong
+/// when keys are deleted from somewhere else, e.g. by a publish from another machine.
+///
+/// That's why every sync also writes an ID of its own to the `SYNC_KEY` of the namespace, and
+/// the cache is only trusted while that key still holds the ID of the sync that cached it. A
+/// publish from another machine overwrites it, and one from an older wrangler deletes it as a
+/// stale key. Keys deleted any other way go unnoticed, so the cache is never trusted for more
+/// than a day after the namespace was listed either.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RemoteKeys {
+    #[serde(skip)]
+    path: PathBuf,
+    listed_at: i64,
+    // the ID the last sync from this machine wrote to `SYNC_KEY`
+    #[serde(default)]
+    sync_id: Option<String>,
+    keys: HashSet<String>,
+    // caches from before files were cached don't have them
+    #[serde(default)]
+    files: HashMap<String, SyncedFile>,
+}
+
+/// The key every sync writes its ID to, which isn't the key of any file
+pub const SYNC_KEY: &str = "__wrangler_sync_id";
+
+/// Where the keys of a namespace are cached
+pub fn cache_path(namespace_id: &str) -> Result<PathBuf> {
+    Ok(get_wrangler_home_dir()?
+        .join("sites")
+        .join(format!("{}.json", namespace_id)))
+}
+
+/// A file as the last sync from this machine left it
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SyncedFile {
+    pub key: String,
+    pub size: u64,
+}
+
+impl RemoteKeys {
+    /// The keys a namespace was just listed with, to be cached at `path`. The files of the
+    /// last sync are kept however long ago that was, as they're all there is to go on.
+    pub fn listed(path: &Path, keys: HashSet<String>) -> RemoteKeys {
+        let files = load(path).map(|cached| cached.files).unwrap_or_default();
+        RemoteKeys {
+            path: path.to_path_buf(),
+            listed_at: Utc::now().timestamp(),
+            sync_id: None,
+            keys,
+            files,
+        }
+    }
+
+    /// The keys cached at `path`, unless there aren't any recent enough to trust
+    pub fn cached(path: &Path) -> Option<RemoteKeys> {
+        let cached = load(path)?;
+        if !cached.is_recent(Utc::now().timestamp()) {
+            return None;
+        }
+        Some(RemoteKeys {
+            path: path.to_path_buf(),
+            ..cached
+        })
+    }
+
+    /// Whether `SYNC_KEY` holds the ID of the sync that cached these keys, which means no
+    /// other sync has been since
+    pub fn is_last_sync(&self, sync_id: Option<&str>) -> bool {
+        self.sync_id.is_some() && self.sync_id.as_deref() == sync_id
+    }
+
+    /// The ID a sync writes to `SYNC_KEY`
+    pub fn sync_id(&self) -> Option<&str> {
+        self.sync_id.as_deref()
+    }
+
+    pub fn keys(&self) -> &HashSet<String> {
+        &self.keys
+    }
+
+    /// The files of the last sync from this machine, by path. There are none if this
+    /// machine hasn't synced the namespace.
+    pub fn files(&self) -> &HashMap<String, SyncedFile> {
+        &self.files
+    }
+
+    /// What the namespace will hold once a sync has uploaded the files of `asset_manifest`
+    /// and deleted every other key, with a new ID for it to write to `SYNC_KEY`
+    pub fn synced(&self, asset_manifest: &AssetManifest) -> RemoteKeys {
+        let files = asset_manifest
+            .entries()
+            .map(|(path, entry)| {
+                let file = SyncedFile {
+                    key: entry.key.clone(),
+                    size: entry.size,
+                };
+                (path.clone(), file)
+            })
+            .collect();
+        RemoteKeys {
+            path: self.path.clone(),
+            listed_at: self.listed_at,
+            sync_id: Some(Uuid::new_v4().to_simple().to_string()),
+            keys: asset_manifest.stored_keys(),
+            files,
+        }
+    }
+
+    fn is_recent(&self, now: i64) -> bool {
+        now - self.listed_at <= MAX_AGE_SECS
+    }
+
+    /// Caches the keys for the next sync. This must only be called once every key is in the
+    /// namespace, `SYNC_KEY` included, since a cached key is never uploaded again.
+    pub fn remember(&self) {
+        if let Err(e) = self.save(&self.path) {
+            log::info!("Could not cache keys to {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+fn load(path: &Path) -> Option<RemoteKeys> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sites::AssetEntry;
+
+    #[test]
+    fn it_only_trusts_recent_listings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sites").join("namespace.json");
+        let mut asset_manifest = AssetManifest::default();
+        let entry = AssetEntry::new("a".to_string(), Path::new("index.html"), b"hello");
+        asset_manifest.insert("index.html".to_string(), entry);
+        let listed = RemoteKeys::listed(&path, HashSet::new());
+        listed.synced(&asset_manifest).remember();
+
+        let cached = RemoteKeys::cached(&path).unwrap();
+        let keys: HashSet<String> = vec!["a".to_string()].into_iter().collect();
+        assert_eq!(cached.keys(), &keys);
+        assert_eq!(cached.files()["index.html"].size, 5);
+        let now = listed.listed_at;
+        assert!(cached.is_recent(now + MAX_AGE_SECS));
+        assert!(!cached.is_recent(now + MAX_AGE_SECS + 1));
+        assert!(RemoteKeys::cached(&dir.path().join("missing.json")).is_none());
+    }
+
+    #[test]
+    fn it_only_trusts_the_cache_of_the_last_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("namespace.json");
+        let listed = RemoteKeys::listed(&path, HashSet::new());
+        assert!(!listed.is_last_sync(None));
+
+        let synced = listed.synced(&AssetManifest::default());
+        let sync_id = synced.sync_id().unwrap().to_string();
+        assert!(synced.is_last_sync(Some(&sync_id)));
+        // another sync has written its own ID, or an older wrangler has deleted the key
+        assert!(!synced.is_last_sync(Some("another")));
+        assert!(!synced.is_last_sync(None));
+        assert_ne!(
+            synced.synced(&AssetManifest::default()).sync_id(),
+            Some(&*sync_id)
+        );
+    }
+}
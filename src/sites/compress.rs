@@ -0,0 +1,103 @@
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use brotli::enc::BrotliEncoderParams;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::settings::toml::Encoding;
+
+// brotli's best quality and largest window. That's slow, but a file is only compressed
+// when its contents change.
+const BROTLI_QUALITY: i32 = 11;
+const BROTLI_WINDOW: i32 = 22;
+
+// MIME types other than images, audio and video whose files are compressed already
+const COMPRESSED_TYPES: &[&str] = &[
+    "application/font-woff",
+    "application/x-7z-compressed",
+    "application/x-bzip2",
+    "application/x-compressed",
+    "application/x-gzip",
+    "application/x-rar-compressed",
+    "application/x-xz",
+    "application/zip",
+    "font/woff",
+    "font/woff2",
+];
+
+// images that aren't compressed, however their MIME types start
+const UNCOMPRESSED_IMAGES: &[&str] = &["image/bmp", "image/svg+xml", "image/x-icon"];
+
+/// Whether the file at `path` could be any smaller compressed, going by its extension. Most
+/// images, audio, video, fonts and archives are compressed already, and compressing them
+/// again only slows publishing down.
+pub fn is_compressible(path: &Path) -> bool {
+    let mime_type = match mime_guess::from_path(path).first() {
+        Some(mime_type) => mime_type,
+        None => return true,
+    };
+    let essence = mime_type.essence_str();
+    if UNCOMPRESSED_IMAGES.contains(&essence) {
+        return true;
+    }
+    let media = ["image/", "audio/", "video/"];
+    !(media.iter().any(|media| essence.starts_with(media)) || COMPRESSED_TYPES.contains(&essence))
+}
+
+/// Compresses the contents of a file, unless that wouldn't make them any smaller, as is the
+/// case for images and fonts that are compressed already.
+pub fn compress(encoding: Encoding, value: &[u8]) -> Result<Option<Vec<u8>>> {
+    let compressed = match encoding {
+        Encoding::Brotli => {
+            let params = BrotliEncoderParams {
+                quality: BROTLI_QUALITY,
+                lgwin: BROTLI_WINDOW,
+                ..Default::default()
+            };
+            let (mut input, mut compressed) = (value, Vec::new());
+            brotli::BrotliCompress(&mut input, &mut compressed, &params)?;
+            compressed
+        }
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(value)?;
+            encoder.finish()?
+        }
+    };
+
+    if compressed.len() < value.len() {
+        Ok(Some(compressed))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_only_compresses_files_that_arent_compressed_already() {
+        for path in &[
+            "index.html",
+            "app.js",
+            "logo.svg",
+            "favicon.ico",
+            "app.wasm",
+            "README",
+        ] {
+            assert!(is_compressible(Path::new(path)), "{}", path);
+        }
+        for path in &[
+            "photo.jpg",
+            "logo.png",
+            "font.woff2",
+            "clip.mp4",
+            "bundle.zip",
+        ] {
+            assert!(!is_compressible(Path::new(path)), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::cache::{RemoteKeys, SyncedFile};
+use super::manifest::AssetManifest;
+use super::read_bucket;
+use super::sync::{remote_keys, stale_keys};
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+
+/// A file of a site, as a diff lists it
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct DiffFile {
+    pub path: String,
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+/// What syncing a site's bucket to its namespace would do.
+///
+/// Files are compared with those of the last sync from this machine. Without one, a file
+/// whose contents are in the namespace already counts as unchanged and any other as added,
+/// and no file counts as removed, though stale keys are still deleted.
+#[derive(Debug, Default, Serialize)]
+pub struct SiteDiff {
+    pub added: Vec<DiffFile>,
+    pub changed: Vec<DiffFile>,
+    pub removed: Vec<DiffFile>,
+    pub unchanged: Vec<DiffFile>,
+    /// How many keys would be uploaded, compressed variants included
+    pub keys_to_upload: usize,
+    /// How many stale keys would be deleted
+    pub keys_to_delete: usize,
+    /// Whether there was a sync from this machine to compare with
+    pub since_last_sync: bool,
+    /// Why the files that are too big to upload are, as publishing fails until they're not
+    pub rejected: Vec<String>,
+}
+
+impl SiteDiff {
+    /// Whether syncing would leave the namespace as it is
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.removed.is_empty()
+            && self.keys_to_upload == 0
+            && self.keys_to_delete == 0
+    }
+}
+
+/// Works out what syncing the bucket at `path` to a namespace would do, without changing
+/// anything. There's no namespace until the site is first published, in which case every
+/// file is added.
+pub fn diff(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: Option<&str>,
+    path: &Path,
+) -> Result<SiteDiff> {
+    let remote_keys = match namespace_id {
+        Some(namespace_id) => Some(remote_keys(target, user, namespace_id)?),
+        None => None,
+    };
+
+    let mut rejected = Vec::new();
+    let exclude = remote_keys.as_ref().map(RemoteKeys::keys);
+    let (to_upload, asset_manifest, _) = read_bucket(target, path, exclude, Some(&mut rejected))?;
+
+    let no_files = HashMap::new();
+    let synced = remote_keys.as_ref().map_or(&no_files, RemoteKeys::files);
+    let mut diff = compare(&asset_manifest, exclude, synced);
+    diff.keys_to_upload = to_upload.len();
+    diff.keys_to_delete = remote_keys.as_ref().map_or(0, |remote_keys| {
+        stale_keys(remote_keys, &asset_manifest).len()
+    });
+    diff.rejected = rejected;
+    Ok(diff)
+}
+
+// Sorts the files of `asset_manifest` by how they differ from the `synced` files, falling back
+// on the keys in the namespace when there are none
+fn compare(
+    asset_manifest: &AssetManifest,
+    remote_keys: Option<&HashSet<String>>,
+    synced: &HashMap<String, SyncedFile>,
+) -> SiteDiff {
+    let is_remote = |key: &String| remote_keys.map_or(false, |keys| keys.contains(key));
+    let mut diff = SiteDiff {
+        since_last_sync: !synced.is_empty(),
+        ..SiteDiff::default()
+    };
+
+    for (path, entry) in asset_manifest.entries() {
+        let file = DiffFile {
+            path: path.clone(),
+            size: entry.size,
+        };
+        match synced.get(path) {
+            Some(synced) if synced.key == entry.key => diff.unchanged.push(file),
+            Some(_) => diff.changed.push(file),
+            None if synced.is_empty() && is_remote(&entry.key) => diff.unchanged.push(file),
+            None => diff.added.push(file),
+        }
+    }
+    for (path, synced) in synced {
+        if asset_manifest.entry(path).is_none() {
+            diff.removed.push(DiffFile {
+                path: path.clone(),
+                size: synced.size,
+            });
+        }
+    }
+
+    diff.added.sort();
+    diff.changed.sort();
+    diff.removed.sort();
+    diff.unchanged.sort();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sites::AssetEntry;
+
+    fn manifest(files: &[(&str, &str, u64)]) -> AssetManifest {
+        let mut asset_manifest = AssetManifest::default();
+        for &(path, key, size) in files {
+            let mut entry = AssetEntry::new(key.to_string(), Path::new(path), b"");
+            entry.size = size;
+            asset_manifest.insert(path.to_string(), entry);
+        }
+        asset_manifest
+    }
+
+    fn file(path: &str, size: u64) -> DiffFile {
+        DiffFile {
+            path: path.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn it_compares_files_with_the_last_sync() {
+        let asset_manifest = manifest(&[
+            ("index.html", "a", 10),
+            ("app.js", "b2", 20),
+            ("new.css", "c", 30),
+        ]);
+        let synced: HashMap<String, SyncedFile> = vec![
+            ("index.html", "a", 10),
+            ("app.js", "b1", 15),
+            ("old.css", "d", 40),
+        ]
+        .into_iter()
+        .map(|(path, key, size)| {
+            let file = SyncedFile {
+                key: key.to_string(),
+                size,
+            };
+            (path.to_string(), file)
+        })
+        .collect();
+        let remote_keys: HashSet<String> = vec!["a".to_string(), "b1".to_string(), "d".to_string()]
+            .into_iter()
+            .collect();
+
+        let diff = compare(&asset_manifest, Some(&remote_keys), &synced);
+        assert!(diff.since_last_sync);
+        assert_eq!(diff.added, vec![file("new.css", 30)]);
+        assert_eq!(diff.changed, vec![file("app.js", 20)]);
+        assert_eq!(diff.removed, vec![file("old.css", 40)]);
+        assert_eq!(diff.unchanged, vec![file("index.html", 10)]);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn it_falls_back_on_the_remote_keys_without_a_last_sync() {
+        let asset_manifest = manifest(&[("index.html", "a", 10), ("app.js", "b", 20)]);
+        let remote_keys: HashSet<String> = vec!["a".to_string()].into_iter().collect();
+
+        let diff = compare(&asset_manifest, Some(&remote_keys), &HashMap::new());
+        assert!(!diff.since_last_sync);
+        assert_eq!(diff.added, vec![file("app.js", 20)]);
+        assert_eq!(diff.unchanged, vec![file("index.html", 10)]);
+        assert!(diff.removed.is_empty());
+
+        // before the first publish, there's no namespace, and every file is added
+        let diff = compare(&asset_manifest, None, &HashMap::new());
+        assert_eq!(diff.added.len(), 2);
+
+        let unchanged = compare(
+            &manifest(&[("index.html", "a", 10)]),
+            Some(&remote_keys),
+            &HashMap::new(),
+        );
+        assert!(unchanged.is_empty());
+    }
+}
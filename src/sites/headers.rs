@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use http::HeaderValue;
+
+use crate::settings::toml::{AssetHeaders, Site};
+
+/// The `[[site.headers]]` rules of a site, ready to match paths against. Every rule whose
+/// glob matches a path applies to it, and a rule overrides the headers set by those in
+/// earlier `[[site.headers]]` tables. The keys of a table have no order, so two rules in the
+/// same table can't set a header of the same file to different values.
+pub struct HeaderRules {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    // the index of the `[[site.headers]]` table it's in
+    table: usize,
+    glob: String,
+    matcher: GlobMatcher,
+    headers: AssetHeaders,
+}
+
+impl Rule {
+    // a header that both rules set, to different values
+    fn conflict(&self, other: &Rule) -> Option<&'static str> {
+        let (a, b) = (&self.headers, &other.headers);
+        let values = [
+            ("cache-control", &a.cache_control, &b.cache_control),
+            ("content-type", &a.content_type, &b.content_type),
+        ];
+        values
+            .iter()
+            .find(|(_, a, b)| a.is_some() && b.is_some() && a != b)
+            .map(|(name, _, _)| *name)
+    }
+}
+
+impl HeaderRules {
+    /// Checks the rules of a site, so a bad one is caught before anything is uploaded
+    pub fn new(site: &Site) -> Result<HeaderRules> {
+        let mut rules = Vec::new();
+        for (table, globs) in site.headers().iter().enumerate() {
+            for (glob, headers) in globs {
+                let matcher = parse_rule(glob, headers)
+                    .with_context(|| format!("Invalid [[site.headers]] rule \"{}\"", glob))?;
+                rules.push(Rule {
+                    table,
+                    glob: glob.clone(),
+                    matcher,
+                    headers: headers.clone(),
+                });
+            }
+        }
+        Ok(HeaderRules { rules })
+    }
+
+    /// The headers of the file at `path`, a url-safe path like those in the asset manifest,
+    /// if any rule matches it. Fails if two rules of one table set a header of it differently.
+    pub fn headers(&self, path: &str) -> Result<Option<AssetHeaders>> {
+        let path = format!("/{}", path);
+        let matching: Vec<&Rule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matcher.is_match(&path))
+            .collect();
+
+        let mut headers: Option<AssetHeaders> = None;
+        for (i, rule) in matching.iter().enumerate() {
+            let same_table = matching[i + 1..]
+                .iter()
+                .filter(|other| other.table == rule.table);
+            for other in same_table {
+                if let Some(name) = rule.conflict(other) {
+                    anyhow::bail!(
+                        "[[site.headers]] rules \"{}\" and \"{}\" both set {} on {}, and rules in the same table have no order; move the one that should win to a later [[site.headers]] table",
+                        rule.glob,
+                        other.glob,
+                        name,
+                        path
+                    )
+                }
+            }
+            headers
+                .get_or_insert_with(Default::default)
+                .merge(&rule.headers);
+        }
+        Ok(headers)
+    }
+}
+
+/// Parses the glob of a rule, which is matched against the paths of requests
+pub fn parse_glob(glob: &str) -> Result<GlobMatcher> {
+    if !glob.starts_with('/') {
+        anyhow::bail!(
+            "\"{}\" should start with a /, like the paths of the requests it matches",
+            glob
+        )
+    }
+    // `*` stays within a directory; `**` matches any number of them
+    let glob = GlobBuilder::new(glob).literal_separator(true).build()?;
+    Ok(glob.compile_matcher())
+}
+
+fn parse_rule(glob: &str, headers: &AssetHeaders) -> Result<GlobMatcher> {
+    let values = [
+        ("cache-control", &headers.cache_control),
+        ("content-type", &headers.content_type),
+    ];
+    for (name, value) in values.iter() {
+        if let Some(value) = value {
+            if HeaderValue::from_str(value).is_err() {
+                anyhow::bail!("{} \"{}\" is not a valid header value", name, value)
+            }
+        }
+    }
+    if let Some(content_type) = &headers.content_type {
+        if !content_type.contains('/') {
+            anyhow::bail!(
+                "content-type \"{}\" should be a MIME type, like text/html",
+                content_type
+            )
+        }
+    }
+    parse_glob(glob)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn site(tables: Vec<Vec<(&str, AssetHeaders)>>) -> Site {
+        let tables = tables
+            .into_iter()
+            .map(|table| {
+                table
+                    .into_iter()
+                    .map(|(glob, headers)| (glob.to_string(), headers))
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .collect();
+        Site {
+            headers: Some(tables),
+            ..Site::default()
+        }
+    }
+
+    fn cache_control(value: &str) -> AssetHeaders {
+        AssetHeaders {
+            cache_control: Some(value.to_string()),
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn it_applies_later_rules_over_earlier_ones() {
+        let rules = HeaderRules::new(&site(vec![
+            vec![("/**", cache_control("no-cache"))],
+            vec![
+                ("/assets/**", cache_control("max-age=31536000, immutable")),
+                (
+                    "/assets/*.wasm",
+                    AssetHeaders {
+                        cache_control: None,
+                        content_type: Some("application/wasm".to_string()),
+                    },
+                ),
+            ],
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            rules.headers("index.html").unwrap(),
+            Some(cache_control("no-cache"))
+        );
+        let wasm = rules.headers("assets/app.wasm").unwrap().unwrap();
+        assert_eq!(
+            wasm.cache_control.as_deref(),
+            Some("max-age=31536000, immutable")
+        );
+        assert_eq!(wasm.content_type.as_deref(), Some("application/wasm"));
+        // `*` doesn't match across directories
+        assert_eq!(
+            rules
+                .headers("assets/wasm/app.wasm")
+                .unwrap()
+                .unwrap()
+                .content_type,
+            None
+        );
+        assert!(HeaderRules::new(&Site::default())
+            .unwrap()
+            .headers("index.html")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn it_rejects_rules_of_one_table_that_set_a_header_differently() {
+        let rules = HeaderRules::new(&site(vec![vec![
+            ("/assets/**", cache_control("max-age=31536000, immutable")),
+            ("/assets/*.html", cache_control("no-cache")),
+            (
+                "/**",
+                AssetHeaders {
+                    cache_control: None,
+                    content_type: Some("text/plain".to_string()),
+                },
+            ),
+        ]]))
+        .unwrap();
+
+        let error = rules.headers("assets/index.html").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[[site.headers]] rules \"/assets/**\" and \"/assets/*.html\" both set cache-control on /assets/index.html, and rules in the same table have no order; move the one that should win to a later [[site.headers]] table"
+        );
+        // they only conflict on the files they both match
+        assert_eq!(
+            rules
+                .headers("assets/app.js")
+                .unwrap()
+                .unwrap()
+                .cache_control,
+            Some("max-age=31536000, immutable".to_string())
+        );
+        assert_eq!(
+            rules.headers("about.html").unwrap().unwrap().content_type,
+            Some("text/plain".to_string())
+        );
+    }
+
+    #[test]
+    fn it_rejects_bad_rules() {
+        let error = |glob: &str, headers: AssetHeaders| {
+            let error = HeaderRules::new(&site(vec![vec![(glob, headers)]]))
+                .err()
+                .unwrap();
+            format!("{:#}", error)
+        };
+
+        assert_eq!(
+            error("assets/**", cache_control("no-cache")),
+            "Invalid [[site.headers]] rule \"assets/**\": \"assets/**\" should start with a /, like the paths of the requests it matches"
+        );
+        assert!(error("/assets/[", cache_control("no-cache")).contains("error parsing glob"));
+        assert!(error("/**", cache_control("no-cache\nx-evil: 1")).contains("not a valid header"));
+        assert!(error(
+            "/**",
+            AssetHeaders {
+                cache_control: None,
+                content_type: Some("html".to_string()),
+            }
+        )
+        .contains("should be a MIME type"));
+    }
+}
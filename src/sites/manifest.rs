@@ -1,3 +1,186 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
 
-pub type AssetManifest = HashMap<String, String>;
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::settings::toml::{AssetHeaders, Encoding, ManifestVersion, Site};
+
+/// Where each file of a Workers Site is in its namespace. The site's handler looks the path
+/// of a request up in `__STATIC_CONTENT_MANIFEST` for the key of the file, or with
+/// `manifest_version = 2`, for everything the manifest knows of it. When the site
+/// is precompressed, in `__STATIC_CONTENT_ENCODINGS` for the keys of its compressed variants,
+/// so it can pick one the request's `Accept-Encoding` allows. When the site has
+/// `[[site.headers]]` rules, `__STATIC_CONTENT_HEADERS` has the headers they set on each file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssetManifest {
+    version: ManifestVersion,
+    entries: HashMap<String, AssetEntry>,
+    // None unless the site is precompressed
+    encodings: Option<HashMap<String, BTreeMap<Encoding, String>>>,
+    // None unless the site has header rules
+    headers: Option<HashMap<String, AssetHeaders>>,
+}
+
+impl AssetManifest {
+    /// An empty manifest for the files of `site`, which only records encodings and headers
+    /// if the site is configured with them
+    pub fn new(site: Option<&Site>) -> AssetManifest {
+        let precompressed = site.map_or(false, |site| !site.precompress().is_empty());
+        let has_headers = site.map_or(false, |site| !site.headers().is_empty());
+        AssetManifest {
+            version: site.map(Site::manifest_version).unwrap_or_default(),
+            entries: HashMap::new(),
+            encodings: if precompressed {
+                Some(HashMap::new())
+            } else {
+                None
+            },
+            headers: if has_headers {
+                Some(HashMap::new())
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn insert(&mut self, path: String, entry: AssetEntry) {
+        self.entries.insert(path, entry);
+    }
+
+    /// Records that the file at `path` has a variant compressed with `encoding` at `key`
+    pub fn insert_encoding(&mut self, path: &str, encoding: Encoding, key: String) {
+        self.encodings
+            .get_or_insert_with(HashMap::new)
+            .entry(path.to_string())
+            .or_default()
+            .insert(encoding, key);
+    }
+
+    /// Records the headers the rules of the site set on the file at `path`
+    pub fn insert_headers(&mut self, path: String, headers: AssetHeaders) {
+        self.headers
+            .get_or_insert_with(HashMap::new)
+            .insert(path, headers);
+    }
+
+    /// The key of the file at `path`
+    pub fn get(&self, path: &str) -> Option<&String> {
+        self.entries.get(path).map(|entry| &entry.key)
+    }
+
+    /// Everything the manifest knows of the file at `path`
+    pub fn entry(&self, path: &str) -> Option<&AssetEntry> {
+        self.entries.get(path)
+    }
+
+    /// The paths of the files and what the manifest knows of them
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &AssetEntry)> {
+        self.entries.iter()
+    }
+
+    /// The keys of the compressed variants of the file at `path`
+    pub fn encodings(&self, path: &str) -> Option<&BTreeMap<Encoding, String>> {
+        self.encodings.as_ref()?.get(path)
+    }
+
+    /// The headers of the file at `path`
+    pub fn headers(&self, path: &str) -> Option<&AssetHeaders> {
+        self.headers.as_ref()?.get(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every key the files are stored at, those of their compressed variants included
+    pub fn stored_keys(&self) -> HashSet<String> {
+        let variants = self
+            .encodings
+            .iter()
+            .flatten()
+            .flat_map(|(_, keys)| keys.values());
+        let keys = self.entries.values().map(|entry| &entry.key);
+        keys.chain(variants).cloned().collect()
+    }
+
+    /// The text of `__STATIC_CONTENT_MANIFEST`, mapping paths to keys, or with
+    /// `manifest_version = 2`, to entries like
+    /// `{"key": "<key>", "size": 1024, "mime_type": "text/html", "hash": "<hash>", "mtime": 1609459200}`
+    pub fn manifest_blob(&self) -> Result<String> {
+        match self.version {
+            ManifestVersion::V1 => {
+                let keys: HashMap<&String, &String> = self
+                    .entries
+                    .iter()
+                    .map(|(path, entry)| (path, &entry.key))
+                    .collect();
+                Ok(serde_json::to_string(&keys)?)
+            }
+            ManifestVersion::V2 => Ok(serde_json::to_string(&self.entries)?),
+        }
+    }
+
+    /// The text of `__STATIC_CONTENT_ENCODINGS`, mapping paths to the keys of their variants
+    /// by encoding, e.g. `{"index.js": {"br": "<key>.br", "gzip": "<key>.gz"}}`. There's none
+    /// unless the site is precompressed.
+    pub fn encodings_blob(&self) -> Result<Option<String>> {
+        match &self.encodings {
+            Some(encodings) => Ok(Some(serde_json::to_string(encodings)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The text of `__STATIC_CONTENT_HEADERS`, mapping paths to the headers they're served
+    /// with, e.g. `{"assets/app.js": {"cache-control": "max-age=31536000, immutable"}}`.
+    /// There's none unless the site has header rules.
+    pub fn headers_blob(&self) -> Result<Option<String>> {
+        match &self.headers {
+            Some(headers) => Ok(Some(serde_json::to_string(headers)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// What the manifest knows of a file
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AssetEntry {
+    pub key: String,
+    /// The size of the file in bytes
+    pub size: u64,
+    /// The MIME type the extension of the file suggests
+    pub mime_type: String,
+    /// The SHA-256 hash of the file's contents, hex encoded, e.g. for an `ETag`
+    pub hash: String,
+    /// When the file was last modified, in seconds since the Unix epoch, if the file system
+    /// keeps track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+}
+
+impl AssetEntry {
+    /// The entry of the file at `path`, whose contents are `value`
+    pub fn new(key: String, path: &Path, value: &[u8]) -> AssetEntry {
+        let mtime = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_secs());
+        AssetEntry {
+            // files are keyed by the hash of their contents
+            hash: key.clone(),
+            key,
+            size: value.len() as u64,
+            mime_type: mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+            mtime,
+        }
+    }
+}
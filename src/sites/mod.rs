@@ -1,26 +1,35 @@
 extern crate base64;
 
+mod cache;
+mod compress;
+mod diff;
+mod headers;
 mod manifest;
 mod sync;
 
-pub use manifest::AssetManifest;
+pub use cache::RemoteKeys;
+pub use diff::{diff, DiffFile, SiteDiff};
+pub use headers::{parse_glob, HeaderRules};
+pub use manifest::{AssetEntry, AssetManifest};
 pub use sync::sync;
 
 use std::collections::HashSet;
 use std::ffi::OsString;
 use std::fs;
-use std::hash::Hasher;
 use std::path::Path;
 
 use anyhow::{anyhow, Result};
 use ignore::overrides::{Override, OverrideBuilder};
 use ignore::{Walk, WalkBuilder};
 use indicatif::{ProgressBar, ProgressStyle};
-use twox_hash::XxHash64;
+use sha2::{Digest, Sha256};
 
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
 
-use crate::kv::namespace::{upsert, UpsertedNamespace};
+use compress::{compress, is_compressible};
+
+use crate::http;
+use crate::kv::namespace::{self, upsert, UpsertedNamespace};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::{KvNamespace, Target};
 use crate::terminal::message::{Message, StdErr};
@@ -30,11 +39,7 @@ pub const VALUE_MAX_SIZE: u64 = 25 * 1024 * 1024;
 
 // Updates given Target with kv_namespace binding for a static site assets KV namespace.
 pub fn add_namespace(user: &GlobalUser, target: &mut Target, preview: bool) -> Result<KvNamespace> {
-    let title = if preview {
-        format!("__{}-{}", target.name, "workers_sites_assets_preview")
-    } else {
-        format!("__{}-{}", target.name, "workers_sites_assets")
-    };
+    let title = namespace_title(target, preview);
 
     let site_namespace = match upsert(target, &user, title)? {
         UpsertedNamespace::Created(namespace) => {
@@ -61,21 +66,61 @@ pub fn add_namespace(user: &GlobalUser, target: &mut Target, preview: bool) -> R
     Ok(site_namespace)
 }
 
-// Returns the hashed key and value pair for all files in a directory.
+/// The id of the namespace `add_namespace` would use for the site, if it's been created.
+/// Unlike `add_namespace`, this never creates one.
+pub fn find_namespace(user: &GlobalUser, target: &Target, preview: bool) -> Result<Option<String>> {
+    let client = http::cf_v4_client(user)?;
+    let title = namespace_title(target, preview);
+    let found = namespace::list(&client, target)?
+        .into_iter()
+        .find(|ns| ns.title == title);
+    Ok(found.map(|ns| ns.id))
+}
+
+fn namespace_title(target: &Target, preview: bool) -> String {
+    if preview {
+        format!("__{}-{}", target.name, "workers_sites_assets_preview")
+    } else {
+        format!("__{}-{}", target.name, "workers_sites_assets")
+    }
+}
+
+// Returns the hashed key and value pair for all files in a directory, along with the
+// variants of them the site is precompressed with. The asset manifest also has the headers
+// the site's rules set on each file.
 pub fn directory_keys_values(
     target: &Target,
     directory: &Path,
     exclude: Option<&HashSet<String>>,
+) -> Result<(Vec<KeyValuePair>, AssetManifest, Vec<String>)> {
+    read_bucket(target, directory, exclude, None)
+}
+
+// Like `directory_keys_values`, except that given somewhere to put them, the reasons files
+// are too big to upload are collected there, and the files left out, rather than failing.
+fn read_bucket(
+    target: &Target,
+    directory: &Path,
+    exclude: Option<&HashSet<String>>,
+    mut rejected: Option<&mut Vec<String>>,
 ) -> Result<(Vec<KeyValuePair>, AssetManifest, Vec<String>)> {
     match fs::metadata(directory) {
         Ok(ref file_type) if file_type.is_dir() => {
+            let precompress = target
+                .site
+                .as_ref()
+                .map_or(&[][..], |site| site.precompress());
+            let header_rules = target.site.as_ref().map(HeaderRules::new).transpose()?;
             let mut upload_vec: Vec<KeyValuePair> = Vec::new();
-            let mut asset_manifest = AssetManifest::new();
+            let mut asset_manifest = AssetManifest::new(target.site.as_ref());
             let mut file_list: Vec<String> = Vec::new();
+            let mut queued_keys: HashSet<String> = HashSet::new();
             let dir_walker = get_dir_iterator(target, directory)?;
             let spinner_style =
                 ProgressStyle::default_spinner().template("{spinner}   Preparing {msg}...");
             let spinner = ProgressBar::new_spinner().with_style(spinner_style);
+            // skip uploading existing keys, if configured to do so
+            let is_remote = |key: &String| exclude.map_or(false, |keys| keys.contains(key));
 
             for entry in dir_walker {
                 spinner.tick();
@@ -85,35 +130,61 @@ pub fn directory_keys_values(
                     spinner.set_message(&format!("{}", path.display()));
 
                     file_list.push(path.to_str().unwrap().to_string());
-                    validate_file_size(&path)?;
+                    if let Err(e) = validate_file_size(&path) {
+                        reject(&mut rejected, e)?;
+                        continue;
+                    }
 
                     let value = std::fs::read(path)?;
 
-                    // Need to base64 encode value
-                    let b64_value = base64::encode(&value);
-
                     let (url_safe_path, key) =
-                        generate_path_and_key(path, directory, Some(b64_value.clone()))?;
+                        generate_path_and_key(path, directory, Some(value.as_slice()))?;
 
-                    validate_key_size(&key)?;
+                    if let Err(e) = validate_key_size(&key) {
+                        reject(&mut rejected, e)?;
+                        continue;
+                    }
 
                     // asset manifest should always contain all files
-                    asset_manifest.insert(url_safe_path, key.clone());
-
-                    // skip uploading existing keys, if configured to do so
-                    if let Some(remote_keys) = exclude {
-                        if remote_keys.contains(&key) {
-                            continue;
+                    asset_manifest.insert(
+                        url_safe_path.clone(),
+                        AssetEntry::new(key.clone(), path, &value),
+                    );
+                    if let Some(rules) = &header_rules {
+                        if let Some(headers) = rules.headers(&url_safe_path)? {
+                            asset_manifest.insert_headers(url_safe_path.clone(), headers);
                         }
                     }
 
-                    upload_vec.push(KeyValuePair {
-                        key: key.clone(),
-                        value: b64_value,
-                        expiration: None,
-                        expiration_ttl: None,
-                        base64: Some(true),
-                    });
+                    // files with the same contents as one that's already being uploaded are
+                    // only uploaded once
+                    if !is_remote(&key) && queued_keys.insert(key.clone()) {
+                        upload_vec.push(upload_pair(key.clone(), &value));
+                    }
+
+                    // files that are compressed already, like most images, aren't compressed
+                    // again, as that would take a while on every publish for nothing
+                    let encodings = if is_compressible(path) {
+                        precompress
+                    } else {
+                        &[]
+                    };
+                    for &encoding in encodings {
+                        // variants are keyed for the contents they were compressed from, so
+                        // one that's uploaded or queued already needn't be compressed again
+                        let variant_key = format!("{}.{}", key, encoding.extension());
+                        if is_remote(&variant_key) || queued_keys.contains(&variant_key) {
+                            asset_manifest.insert_encoding(&url_safe_path, encoding, variant_key);
+                        } else if let Some(compressed) = compress(encoding, &value)? {
+                            asset_manifest.insert_encoding(
+                                &url_safe_path,
+                                encoding,
+                                variant_key.clone(),
+                            );
+                            queued_keys.insert(variant_key.clone());
+                            upload_vec.push(upload_pair(variant_key, &compressed));
+                        }
+                    }
                 }
             }
             Ok((upload_vec, asset_manifest, file_list))
@@ -127,6 +198,27 @@ pub fn directory_keys_values(
     }
 }
 
+fn reject(rejected: &mut Option<&mut Vec<String>>, e: anyhow::Error) -> Result<()> {
+    match rejected {
+        Some(rejected) => {
+            rejected.push(e.to_string());
+            Ok(())
+        }
+        None => Err(e),
+    }
+}
+
+fn upload_pair(key: String, value: &[u8]) -> KeyValuePair {
+    KeyValuePair {
+        key,
+        // Need to base64 encode value
+        value: base64::encode(value),
+        expiration: None,
+        expiration_ttl: None,
+        base64: Some(true),
+    }
+}
+
 // Ensure that all files in upload directory do not exceed the MAX_VALUE_SIZE (this ensures that
 // no partial uploads happen). I don't like this functionality (and the similar key length checking
 // logic in validate_key_size()) because it duplicates the size checking the API already does--but
@@ -245,77 +337,45 @@ fn generate_url_safe_path(path: &Path) -> Result<String> {
     Ok(path.to_string())
 }
 
-// Adds the SHA-256 hash of the path's file contents to the url-safe path of a file to
-// generate a versioned key for the file and its contents. Returns the url-safe path prefix
-// for the key, as well as the key with hash appended.
-// e.g (sitemap.xml, sitemap.ec717eb2131fdd4fff803b851d2aa5b1dc3e0af36bc3c8c40f2095c747e80d1e.xml)
+// Keys a file by the SHA-256 hash of its contents, so that a file is only uploaded once however
+// many paths it's at, and isn't uploaded again when it moves. Returns the url-safe path of
+// the file, for the asset manifest to map to the key, as well as the key.
+// e.g (sitemap.xml, ec717eb2131fdd4fff803b851d2aa5b1dc3e0af36bc3c8c40f2095c747e80d1e)
 pub fn generate_path_and_key(
     path: &Path,
     directory: &Path,
-    value: Option<String>,
+    value: Option<&[u8]>,
 ) -> Result<(String, String)> {
     // strip the bucket directory from both paths for ease of reference.
     let relative_path = path.strip_prefix(directory).unwrap();
 
     let url_safe_path = generate_url_safe_path(relative_path)?;
-    let path_with_hash = if let Some(value) = value {
-        let digest = get_digest(value);
-        // it is ok to truncate the digest here because
-        // we also include the file name in the asset manifest key
-        //
-        // the most important thing here is to detect changes
-        // of a single file to invalidate the cache and
-        // it's impossible to serve two different files with the same name
-        let digest = digest[0..10].to_string();
-        generate_path_with_hash(relative_path, digest)?
-    } else {
-        url_safe_path.to_owned()
+    let key = match value {
+        Some(value) => get_digest(value),
+        None => url_safe_path.to_owned(),
     };
 
-    Ok((url_safe_path, path_with_hash))
+    Ok((url_safe_path, key))
 }
 
-fn get_digest(value: String) -> String {
-    let value = value.as_bytes();
-    let mut hasher = XxHash64::default();
-    hasher.write(value);
-    let digest = hasher.finish();
-    // encode u64 as hexadecimal to save space and information
-    format!("{:x}", digest)
-}
-
-// Assumes that `path` is a file (called from a match branch for path.is_file())
-// Assumes that `hashed_value` is a String, not an Option<String> (called from a match branch for value.is_some())
-fn generate_path_with_hash(path: &Path, hashed_value: String) -> Result<String> {
-    if let Some(file_stem) = path.file_stem() {
-        let mut file_name = file_stem.to_os_string();
-        let extension = path.extension();
-
-        file_name.push(".");
-        file_name.push(hashed_value);
-        if let Some(ext) = extension {
-            file_name.push(".");
-            file_name.push(ext);
-        }
-
-        let new_path = path.with_file_name(file_name);
-
-        Ok(generate_url_safe_path(&new_path)?)
-    } else {
-        anyhow::bail!("no file_stem for path {}", path.display())
-    }
+fn get_digest(value: &[u8]) -> String {
+    // encode the digest as hexadecimal, which is safe to use in a URL
+    format!("{:x}", Sha256::digest(value))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use regex::Regex;
+    use std::collections::BTreeMap;
     use std::fs;
-    use std::io::Write;
+    use std::io::{Read, Write};
     use std::path::{Path, PathBuf};
     use tempfile::TempDir;
 
-    use crate::settings::toml::{Site, Target, TargetType};
+    use crate::settings::toml::{
+        AssetHeaders, Encoding, ManifestVersion, Site, Target, TargetType,
+    };
 
     fn make_target(site: Site) -> Target {
         Target {
@@ -332,6 +392,7 @@ mod tests {
             text_blobs: None,
             usage_model: None,
             wasm_modules: None,
+            test_scheduled: false,
         }
     }
 
@@ -345,8 +406,9 @@ mod tests {
         let tmpdir = TempDir::new().unwrap();
         let tmp_path = tmpdir.into_path();
 
+        // each file has contents of its own, so that each has a key of its own
         files.iter().for_each(|path| {
-            std::fs::File::create(tmp_path.clone().join(path)).unwrap();
+            std::fs::write(tmp_path.join(path), path.to_str().unwrap()).unwrap();
         });
 
         (tmp_path, files)
@@ -376,10 +438,11 @@ mod tests {
             let path = tmpdir.join(f);
             let path = path.to_str().unwrap();
             // in calling code, `exclude` is the list of keys from KV, and thus needs to contain the
-            // partial hash digest of the file in the "key". call generate_path_and_key to obtain
-            // for later comparison.
+            // hash digest of the file's contents. call generate_path_and_key to obtain for later
+            // comparison.
+            let value = fs::read(path).unwrap();
             let (_, key_with_hash) =
-                generate_path_and_key(&Path::new(path), &tmpdir, Some("".into())).unwrap();
+                generate_path_and_key(&Path::new(path), &tmpdir, Some(value.as_slice())).unwrap();
             exclude.insert(key_with_hash);
         }
 
@@ -396,10 +459,158 @@ mod tests {
             assert!(!upload_keys.contains(&file.to_string()));
         }
         assert_eq!(upload_keys.len(), 1);
-        assert!(upload_keys.first().unwrap().starts_with("file_c"));
+        assert_eq!(upload_keys.first(), asset_manifest.get("file_c.txt"));
         assert_eq!(to_upload.len(), all_files.len() - exclude.len());
     }
 
+    #[test]
+    fn it_uploads_identical_files_once() {
+        let (tmpdir, all_files) = tmpdir_with_default_files();
+        fs::create_dir(tmpdir.join("copies")).unwrap();
+        fs::copy(tmpdir.join("file_a.txt"), tmpdir.join("copies/file_a.txt")).unwrap();
+
+        let (to_upload, asset_manifest, _) =
+            directory_keys_values(&make_target(Site::default()), &tmpdir, None).unwrap();
+
+        assert_eq!(asset_manifest.len(), all_files.len() + 1);
+        assert_eq!(
+            asset_manifest.get("file_a.txt"),
+            asset_manifest.get("copies/file_a.txt")
+        );
+        assert_eq!(to_upload.len(), all_files.len());
+    }
+
+    #[test]
+    fn it_can_leave_out_files_that_are_too_big() {
+        let (tmpdir, all_files) = tmpdir_with_default_files();
+        let too_big = fs::File::create(tmpdir.join("too_big.bin")).unwrap();
+        too_big.set_len(VALUE_MAX_SIZE + 1).unwrap();
+        let target = make_target(Site::default());
+
+        assert!(directory_keys_values(&target, &tmpdir, None).is_err());
+
+        let mut rejected = Vec::new();
+        let (to_upload, asset_manifest, _) =
+            read_bucket(&target, &tmpdir, None, Some(&mut rejected)).unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert!(rejected[0].contains("too_big.bin"));
+        assert!(asset_manifest.get("too_big.bin").is_none());
+        assert_eq!(asset_manifest.len(), all_files.len());
+        assert_eq!(to_upload.len(), all_files.len());
+    }
+
+    #[test]
+    fn it_uploads_compressed_variants_that_are_smaller() {
+        let tmpdir = TempDir::new().unwrap().into_path();
+        let script = "console.log('hello');\n".repeat(100);
+        fs::write(tmpdir.join("app.js"), &script).unwrap();
+        fs::write(tmpdir.join("tiny.txt"), "a").unwrap();
+        fs::write(tmpdir.join("logo.png"), "not really a png".repeat(100)).unwrap();
+        let target = make_target(Site {
+            precompress: Some(vec![Encoding::Brotli, Encoding::Gzip]),
+            ..Site::default()
+        });
+
+        let (to_upload, asset_manifest, _) = directory_keys_values(&target, &tmpdir, None).unwrap();
+        let key = asset_manifest.get("app.js").unwrap();
+        let encodings = asset_manifest.encodings("app.js").unwrap();
+        assert_eq!(encodings[&Encoding::Brotli], format!("{}.br", key));
+        assert_eq!(encodings[&Encoding::Gzip], format!("{}.gz", key));
+        // compressing a single byte only makes it bigger
+        assert!(asset_manifest.encodings("tiny.txt").is_none());
+        // nor are images, which are compressed already
+        assert!(asset_manifest.encodings("logo.png").is_none());
+        assert_eq!(to_upload.len(), 5);
+        assert_eq!(asset_manifest.stored_keys().len(), 5);
+
+        let gzipped = to_upload
+            .iter()
+            .find(|kv| kv.key == encodings[&Encoding::Gzip])
+            .unwrap();
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&base64::decode(&gzipped.value).unwrap()[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, script);
+
+        let blob: serde_json::Value =
+            serde_json::from_str(&asset_manifest.encodings_blob().unwrap().unwrap()).unwrap();
+        assert_eq!(blob["app.js"]["br"], format!("{}.br", key));
+        assert_eq!(blob["app.js"]["gzip"], format!("{}.gz", key));
+
+        // variants that are uploaded already aren't compressed or uploaded again
+        let exclude = asset_manifest.stored_keys();
+        let (to_upload, synced_manifest, _) =
+            directory_keys_values(&target, &tmpdir, Some(&exclude)).unwrap();
+        assert!(to_upload.is_empty());
+        assert_eq!(synced_manifest, asset_manifest);
+    }
+
+    #[test]
+    fn it_only_records_encodings_when_precompressing() {
+        let (tmpdir, _) = tmpdir_with_default_files();
+
+        let (_, asset_manifest, _) =
+            directory_keys_values(&make_target(Site::default()), &tmpdir, None).unwrap();
+        assert!(asset_manifest.encodings_blob().unwrap().is_none());
+        assert!(asset_manifest.encodings("file_a.txt").is_none());
+    }
+
+    #[test]
+    fn it_records_the_headers_of_matching_files() {
+        let (tmpdir, _) = tmpdir_with_default_files();
+        let mut rules = BTreeMap::new();
+        rules.insert(
+            "/file_a.*".to_string(),
+            AssetHeaders {
+                cache_control: Some("no-cache".to_string()),
+                content_type: None,
+            },
+        );
+        let target = make_target(Site {
+            headers: Some(vec![rules]),
+            ..Site::default()
+        });
+
+        let (_, asset_manifest, _) = directory_keys_values(&target, &tmpdir, None).unwrap();
+        assert_eq!(
+            asset_manifest.headers("file_a.txt").unwrap().cache_control,
+            Some("no-cache".to_string())
+        );
+        assert!(asset_manifest.headers("file_b.txt").is_none());
+        assert_eq!(
+            asset_manifest.headers_blob().unwrap().unwrap(),
+            r#"{"file_a.txt":{"cache-control":"no-cache"}}"#
+        );
+    }
+
+    #[test]
+    fn it_describes_each_file_in_a_version_2_manifest() {
+        let (tmpdir, _) = tmpdir_with_default_files();
+        fs::write(tmpdir.join("index.html"), "<h1>Hello World!</h1>").unwrap();
+        let key = "375690f1152af3649ca0eda5a0686a0bc50a279c2b7fb1af73da088727f65d96";
+        let manifest = |site: Site| -> serde_json::Value {
+            let (_, asset_manifest, _) =
+                directory_keys_values(&make_target(site), &tmpdir, None).unwrap();
+            serde_json::from_str(&asset_manifest.manifest_blob().unwrap()).unwrap()
+        };
+
+        let entries = manifest(Site {
+            manifest_version: Some(ManifestVersion::V2),
+            ..Site::default()
+        });
+        let entry = &entries["index.html"];
+        assert_eq!(entry["key"], key);
+        assert_eq!(entry["hash"], key);
+        assert_eq!(entry["size"], 21);
+        assert_eq!(entry["mime_type"], "text/html");
+        assert!(entry["mtime"].as_u64().unwrap() > 0);
+
+        // the first version, which handlers have always read, only has keys
+        assert_eq!(manifest(Site::default())["index.html"], key);
+        assert!(toml::from_str::<Site>("bucket = \"public\"\nmanifest_version = 3").is_err());
+    }
+
     #[test]
     fn it_can_ignore_node_modules() {
         let mut site = Site::default();
@@ -615,31 +826,27 @@ mod tests {
     }
 
     #[test]
-    fn it_inserts_hash_before_extension() {
-        let value = "<h1>Hello World!</h1>";
-        let hashed_value = get_digest(String::from(value));
-
-        let path = PathBuf::from("path").join("to").join("asset.html");
-        let actual_path_with_hash =
-            generate_path_with_hash(&path, hashed_value.to_owned()).unwrap();
-
-        let expected_path_with_hash = format!("path/to/asset.{}.html", hashed_value);
-
-        assert_eq!(actual_path_with_hash, expected_path_with_hash);
-    }
-
-    #[test]
-    fn it_inserts_hash_without_extension() {
-        let value = "<h1>Hello World!</h1>";
-        let hashed_value = get_digest(String::from(value));
-
-        let path = PathBuf::from("path").join("to").join("asset");
-        let actual_path_with_hash =
-            generate_path_with_hash(&path, hashed_value.to_owned()).unwrap();
-
-        let expected_path_with_hash = format!("path/to/asset.{}", hashed_value);
-
-        assert_eq!(actual_path_with_hash, expected_path_with_hash);
+    fn it_keys_files_by_their_contents() {
+        let directory = Path::new("./build");
+        let key = |path: &str, value: &str| {
+            let path = directory.join(path);
+            generate_path_and_key(&path, directory, Some(value.as_bytes()))
+                .unwrap()
+                .1
+        };
+
+        assert_eq!(
+            key("index.html", "<h1>Hello World!</h1>"),
+            "375690f1152af3649ca0eda5a0686a0bc50a279c2b7fb1af73da088727f65d96"
+        );
+        assert_eq!(
+            key("index.html", "<h1>Hello World!</h1>"),
+            key("moved/copy.html", "<h1>Hello World!</h1>")
+        );
+        assert_ne!(
+            key("index.html", "<h1>Hello World!</h1>"),
+            key("index.html", "<h1>Goodbye World!</h1>")
+        );
     }
 
     #[test]
@@ -656,7 +863,7 @@ mod tests {
     fn it_removes_bucket_dir_prefix() {
         let path = Path::new("./build/path/to/asset.ext");
         let directory = Path::new("./build");
-        let value = Some("<h1>Hello World!</h1>".to_string());
+        let value = Some("<h1>Hello World!</h1>".as_bytes());
         let (path, key) = generate_path_and_key(path, directory, value).unwrap();
 
         assert!(!path.contains("directory"));
@@ -667,10 +874,10 @@ mod tests {
     fn it_combines_url_safe_and_hash_properly() {
         let path = Path::new("./build/path/to/asset.ext");
         let directory = Path::new("./build");
-        let value = Some("<h1>Hello World!</h1>".to_string());
+        let value = Some("<h1>Hello World!</h1>".as_bytes());
         let (path, key) = generate_path_and_key(path, directory, value).unwrap();
         let expected_path = "path/to/asset.ext".to_string();
-        let expected_key_regex = Regex::new(r"^path/to/asset\.[0-9a-f]{10}\.ext").unwrap();
+        let expected_key_regex = Regex::new(r"^[0-9a-f]{64}$").unwrap();
 
         assert_eq!(path, expected_path);
         assert!(expected_key_regex.is_match(&key));
@@ -4,28 +4,107 @@ use std::path::Path;
 use anyhow::Result;
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
 
+use super::cache::{cache_path, RemoteKeys, SYNC_KEY};
 use super::directory_keys_values;
 use super::manifest::AssetManifest;
 use crate::commands::kv;
+use crate::commands::kv::remote::get_value;
 use crate::http;
 use crate::kv::key::KeyList;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdErr};
 
+/// Finds the files to upload and the stale keys to delete, along with what the namespace will
+/// hold once that's done. The uploads include the ID of this sync, under `SYNC_KEY`. Callers
+/// should `remember` what the namespace holds once it's done, so that the next sync needn't
+/// list it.
 pub fn sync(
     target: &Target,
     user: &GlobalUser,
     namespace_id: &str,
     path: &Path,
-) -> Result<(Vec<KeyValuePair>, Vec<String>, AssetManifest)> {
+) -> Result<(Vec<KeyValuePair>, Vec<String>, AssetManifest, RemoteKeys)> {
     // First, find all changed files in given local directory (aka files that are now stale
     // in Workers KV).
 
-    // Get remote keys, which contain the hash of the file (value) as the suffix.
-    // Turn it into a HashSet. This will be used by upload() to figure out which
-    // files to exclude from upload (because their current version already exists in
-    // the Workers KV remote).
+    // Get remote keys, which are the hash of the file (value). This will be used by upload()
+    // to figure out which files to exclude from upload (because their current version
+    // already exists in the Workers KV remote). The cached ones are only used if no other
+    // sync has been since they were, as it may have deleted keys this one would otherwise
+    // skip uploading.
+    let cache_path = cache_path(namespace_id)?;
+    let remote_keys = match RemoteKeys::cached(&cache_path) {
+        Some(cached)
+            if cached.is_last_sync(last_sync_id(target, user, namespace_id)?.as_deref()) =>
+        {
+            log::info!("Using the cached keys of namespace {}", namespace_id);
+            cached
+        }
+        _ => RemoteKeys::listed(&cache_path, list_keys(target, user, namespace_id)?),
+    };
+
+    let (mut diff_files_to_upload, asset_manifest, _): (Vec<KeyValuePair>, AssetManifest, _) =
+        directory_keys_values(target, path, Some(remote_keys.keys()))?;
+
+    // Now delete files from Workers KV that exist in remote but no longer exist locally.
+    let to_delete = stale_keys(&remote_keys, &asset_manifest);
+    let synced = remote_keys.synced(&asset_manifest);
+    if let Some(sync_id) = synced.sync_id() {
+        diff_files_to_upload.push(KeyValuePair {
+            key: SYNC_KEY.to_string(),
+            value: sync_id.to_string(),
+            expiration: None,
+            expiration_ttl: None,
+            base64: None,
+        });
+    }
+
+    StdErr::success("Success");
+    Ok((diff_files_to_upload, to_delete, asset_manifest, synced))
+}
+
+// The remote keys for a dry run, which only lists them if they haven't been recently. A dry
+// run that trusts a stale listing only misreports what a sync would do, so it doesn't check
+// whether there's been another sync since.
+pub(super) fn remote_keys(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+) -> Result<RemoteKeys> {
+    let cache_path = cache_path(namespace_id)?;
+    match RemoteKeys::cached(&cache_path) {
+        Some(remote_keys) => {
+            log::info!("Using the cached keys of namespace {}", namespace_id);
+            Ok(remote_keys)
+        }
+        None => Ok(RemoteKeys::listed(
+            &cache_path,
+            list_keys(target, user, namespace_id)?,
+        )),
+    }
+}
+
+// Find keys that are present in remote but not present in local (compressed variants
+// included), and stage them for deletion. `SYNC_KEY` is overwritten, not deleted.
+pub(super) fn stale_keys(remote_keys: &RemoteKeys, asset_manifest: &AssetManifest) -> Vec<String> {
+    let local_keys = asset_manifest.stored_keys();
+    remote_keys
+        .keys()
+        .difference(&local_keys)
+        .filter(|key| *key != SYNC_KEY)
+        .map(|key| key.to_owned())
+        .collect()
+}
+
+// The ID the last sync wrote to the namespace, if there's been one since `SYNC_KEY` was added
+fn last_sync_id(target: &Target, user: &GlobalUser, namespace_id: &str) -> Result<Option<String>> {
+    let client = http::legacy_auth_client(user);
+    let sync_id = get_value(&client, target, namespace_id, SYNC_KEY)?;
+    Ok(sync_id.and_then(|sync_id| String::from_utf8(sync_id).ok()))
+}
+
+fn list_keys(target: &Target, user: &GlobalUser, namespace_id: &str) -> Result<HashSet<String>> {
     let client = http::cf_v4_client(&user)?;
     let remote_keys_iter = KeyList::new(target, client, namespace_id, None)?;
     let mut remote_keys: HashSet<String> = HashSet::new();
@@ -37,24 +116,5 @@ pub fn sync(
             Err(e) => anyhow::bail!(kv::format_error(e)),
         }
     }
-
-    let (diff_files_to_upload, asset_manifest, _): (Vec<KeyValuePair>, AssetManifest, _) =
-        directory_keys_values(target, path, Some(&remote_keys))?;
-
-    // Now delete files from Workers KV that exist in remote but no longer exist locally.
-    // Get local keys
-    let mut local_keys: HashSet<_> = HashSet::new();
-    for (_, asset_key) in asset_manifest.iter() {
-        local_keys.insert(asset_key.clone());
-    }
-
-    // Find keys that are present in remote but not present in local, and
-    // stage them for deletion.
-    let to_delete: Vec<_> = remote_keys
-        .difference(&local_keys)
-        .map(|key| key.to_owned())
-        .collect();
-
-    StdErr::success("Success");
-    Ok((diff_files_to_upload, to_delete, asset_manifest))
+    Ok(remote_keys)
 }
@@ -0,0 +1,177 @@
+use std::cmp;
+
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Renders the line-by-line differences between two texts in unified diff format,
+/// or an empty string when they have the same lines.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let lines = diff_lines(old, new);
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // the number of old and new lines that come before each line of the diff
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        match line {
+            Line::Same(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Line::Removed(_) => old_line += 1,
+            Line::Added(_) => new_line += 1,
+        }
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_label, new_label);
+
+    let mut group_start = 0;
+    for (i, change) in changes.iter().enumerate() {
+        // keep going while the next change is close enough to share context with this one
+        let is_last = i + 1 == changes.len();
+        if !is_last && changes[i + 1] - change <= CONTEXT_LINES * 2 {
+            continue;
+        }
+
+        let start = changes[group_start].saturating_sub(CONTEXT_LINES);
+        let end = cmp::min(change + CONTEXT_LINES + 1, lines.len());
+        let hunk = &lines[start..end];
+
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            if old_count == 0 {
+                old_start
+            } else {
+                old_start + 1
+            },
+            old_count,
+            if new_count == 0 {
+                new_start
+            } else {
+                new_start + 1
+            },
+            new_count
+        ));
+
+        for line in hunk {
+            let (prefix, text) = match line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            output.push(prefix);
+            output.push_str(text);
+            output.push('\n');
+        }
+
+        group_start = i + 1;
+    }
+
+    output
+}
+
+// A longest common subsequence diff; configuration files are small enough that the
+// quadratic table is not a concern.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lengths[i + 1][j], lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().copied().map(Line::Removed));
+    lines.extend(new[j..].iter().copied().map(Line::Added));
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_renders_nothing_for_identical_texts() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "a", "b"), "");
+    }
+
+    #[test]
+    fn it_renders_separate_hunks_for_distant_changes() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\ntwo\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n";
+
+        assert_eq!(
+            unified_diff(old, new, "a/wrangler.toml", "b/wrangler.toml"),
+            "--- a/wrangler.toml
++++ b/wrangler.toml
+@@ -1,5 +1,5 @@
+ 1
+-2
++two
+ 3
+ 4
+ 5
+@@ -10,3 +10,4 @@
+ 10
+ 11
+ 12
++13
+"
+        );
+    }
+
+    #[test]
+    fn it_renders_additions_to_an_empty_text() {
+        assert_eq!(
+            unified_diff("", "a\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+}
@@ -1,8 +1,10 @@
 mod browser;
+mod diff;
 pub mod emoji;
 pub mod interactive;
 mod json;
 pub mod message;
 pub mod styles;
 pub use browser::open_browser;
+pub use diff::unified_diff;
 pub use json::colored_json_string;
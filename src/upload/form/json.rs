@@ -0,0 +1,22 @@
+use super::binding::Binding;
+use super::var::validate_size;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// A var that isn't a string, which the worker sees as the parsed JSON value
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Json {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+impl Json {
+    pub fn new(name: String, value: serde_json::Value) -> Result<Self> {
+        validate_size(&name, serde_json::to_string(&value)?.len())?;
+        Ok(Self { name, value })
+    }
+
+    pub fn binding(&self) -> Binding {
+        Binding::new_json(self.name.clone(), self.value.clone())
+    }
+}
@@ -1,12 +1,16 @@
+mod json;
 mod modules_worker;
 mod plain_text;
 mod project_assets;
+mod scheduled;
 mod service_worker;
 mod text_blob;
+mod var;
 mod wasm_module;
 
 use anyhow::Result;
 use reqwest::blocking::multipart::Form;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -16,20 +20,39 @@ use crate::settings::toml::{Target, TargetType, UploadFormat, UsageModel};
 use crate::sites::AssetManifest;
 use crate::wranglerjs;
 
-use plain_text::PlainText;
-pub use project_assets::{ModuleConfig, ModuleType};
-use project_assets::{ModulesAssets, ServiceWorkerAssets};
+pub use project_assets::{ModuleConfig, ModuleType, ModulesAssets, ServiceWorkerAssets};
+pub use scheduled::{modules_entry, MODULES_ENTRY, SCHEDULED_PATH, SERVICE_WORKER_PRELUDE};
 use text_blob::TextBlob;
+pub use var::Var;
 use wasm_module::WasmModule;
 
 // TODO: https://github.com/cloudflare/wrangler/issues/1083
 use super::{krate, Package};
 
+/// The script of a target and everything bound to it, as `build` uploads them
+pub enum Assets {
+    ServiceWorker(ServiceWorkerAssets),
+    Modules(ModulesAssets),
+}
+
 pub fn build(
     target: &Target,
     asset_manifest: Option<AssetManifest>,
     session_config: Option<serde_json::Value>,
 ) -> Result<Form> {
+    match assets(target, asset_manifest)? {
+        Assets::ServiceWorker(assets) => {
+            service_worker::build_form(&assets, session_config, target.test_scheduled)
+        }
+        Assets::Modules(assets) => {
+            modules_worker::build_form(&assets, session_config, target.test_scheduled)
+        }
+    }
+}
+
+/// Gathers the script and bindings of a target, generating the script of a Rust project
+/// first.
+pub fn assets(target: &Target, asset_manifest: Option<AssetManifest>) -> Result<Assets> {
     let target_type = &target.target_type;
     let kv_namespaces = &target.kv_namespaces;
     let durable_object_classes = target
@@ -38,7 +61,7 @@ pub fn build(
         .and_then(|d| d.classes.clone())
         .unwrap_or_default();
     let mut text_blobs: Vec<TextBlob> = Vec::new();
-    let mut plain_texts: Vec<PlainText> = Vec::new();
+    let mut vars: Vec<Var> = Vec::new();
     let mut wasm_modules: Vec<WasmModule> = Vec::new();
     let usage_model = target.usage_model;
 
@@ -55,18 +78,30 @@ pub fn build(
         }
     }
 
-    if let Some(vars) = &target.vars {
-        for (key, value) in vars.iter() {
-            plain_texts.push(PlainText::new(key.clone(), value.clone())?)
+    if let Some(target_vars) = &target.vars {
+        for (key, value) in target_vars.iter() {
+            vars.push(Var::new(key.clone(), value.clone())?)
         }
     }
 
     if let Some(asset_manifest) = asset_manifest {
         log::info!("adding __STATIC_CONTENT_MANIFEST");
         let binding = "__STATIC_CONTENT_MANIFEST".to_string();
-        let asset_manifest_blob = get_asset_manifest_blob(asset_manifest)?;
+        let asset_manifest_blob = asset_manifest.manifest_blob()?;
         let text_blob = TextBlob::new(asset_manifest_blob, binding)?;
         text_blobs.push(text_blob);
+
+        if let Some(encodings_blob) = asset_manifest.encodings_blob()? {
+            log::info!("adding __STATIC_CONTENT_ENCODINGS");
+            let binding = "__STATIC_CONTENT_ENCODINGS".to_string();
+            text_blobs.push(TextBlob::new(encodings_blob, binding)?);
+        }
+
+        if let Some(headers_blob) = asset_manifest.headers_blob()? {
+            log::info!("adding __STATIC_CONTENT_HEADERS");
+            let binding = "__STATIC_CONTENT_HEADERS".to_string();
+            text_blobs.push(TextBlob::new(headers_blob, binding)?);
+        }
     }
 
     match target_type {
@@ -89,11 +124,11 @@ pub fn build(
                 kv_namespaces.to_vec(),
                 durable_object_classes,
                 text_blobs,
-                plain_texts,
+                vars,
                 usage_model,
             )?;
 
-            service_worker::build_form(&assets, session_config)
+            Ok(Assets::ServiceWorker(assets))
         }
         TargetType::JavaScript => match &target.build {
             Some(config) => match &config.upload {
@@ -109,15 +144,15 @@ pub fn build(
                         kv_namespaces.to_vec(),
                         durable_object_classes,
                         text_blobs,
-                        plain_texts,
+                        vars,
                         usage_model,
                     )?;
 
-                    service_worker::build_form(&assets, session_config)
+                    Ok(Assets::ServiceWorker(assets))
                 }
                 UploadFormat::Modules { main, dir, rules } => {
                     let migration = match &target.migrations {
-                        Some(migrations) => Some(migrations.api_migration()?),
+                        Some(migrations) => migrations.api_migration()?,
                         None => None,
                     };
 
@@ -127,11 +162,11 @@ pub fn build(
                         kv_namespaces.to_vec(),
                         durable_object_classes,
                         migration,
-                        plain_texts,
+                        vars,
                         usage_model,
                     )?;
 
-                    modules_worker::build_form(&assets, session_config)
+                    Ok(Assets::Modules(assets))
                 }
             },
             None => {
@@ -146,11 +181,11 @@ pub fn build(
                     kv_namespaces.to_vec(),
                     durable_object_classes,
                     text_blobs,
-                    plain_texts,
+                    vars,
                     usage_model,
                 )?;
 
-                service_worker::build_form(&assets, session_config)
+                Ok(Assets::ServiceWorker(assets))
             }
         },
         TargetType::Webpack => {
@@ -174,18 +209,74 @@ pub fn build(
                 kv_namespaces.to_vec(),
                 durable_object_classes,
                 text_blobs,
-                plain_texts,
+                vars,
                 usage_model,
             )?;
 
-            service_worker::build_form(&assets, session_config)
+            Ok(Assets::ServiceWorker(assets))
         }
     }
 }
 
-fn get_asset_manifest_blob(asset_manifest: AssetManifest) -> Result<String> {
-    let asset_manifest = serde_json::to_string(&asset_manifest)?;
-    Ok(asset_manifest)
+/// The bindings `build` sends for a target, worked out without building the script or
+/// reading the files that are bound. Bindings that only exist once the project is built
+/// (the wasm module of a webpack or Rust project) or published (the `__STATIC_CONTENT`
+/// namespace of a Workers Site) aren't included.
+pub fn bindings(target: &Target) -> Result<Vec<binding::Binding>> {
+    let is_modules = match (&target.target_type, &target.build) {
+        (TargetType::JavaScript, Some(build)) => {
+            matches!(build.upload, UploadFormat::Modules { .. })
+        }
+        _ => false,
+    };
+    let sorted = |entries: &Option<HashMap<String, PathBuf>>| {
+        let mut entries: Vec<(String, PathBuf)> = entries.clone().into_iter().flatten().collect();
+        entries.sort();
+        entries
+    };
+
+    let mut bindings = Vec::new();
+
+    // in the same order as `ServiceWorkerAssets::bindings` and `ModulesAssets::bindings`
+    if !is_modules {
+        for (key, module_path) in sorted(&target.wasm_modules) {
+            bindings.push(WasmModule::new(module_path, key)?.binding());
+        }
+    }
+    for kv_namespace in &target.kv_namespaces {
+        bindings.push(kv_namespace.binding());
+    }
+    for class in target
+        .durable_objects
+        .iter()
+        .flat_map(|durable_objects| durable_objects.classes.iter().flatten())
+    {
+        bindings.push(class.binding());
+    }
+    if !is_modules {
+        for (key, _) in sorted(&target.text_blobs) {
+            bindings.push(TextBlob::new(String::new(), key)?.binding());
+        }
+        if let Some(site) = &target.site {
+            let binding = "__STATIC_CONTENT_MANIFEST".to_string();
+            bindings.push(TextBlob::new(String::new(), binding)?.binding());
+            if !site.precompress().is_empty() {
+                let binding = "__STATIC_CONTENT_ENCODINGS".to_string();
+                bindings.push(TextBlob::new(String::new(), binding)?.binding());
+            }
+            if !site.headers().is_empty() {
+                let binding = "__STATIC_CONTENT_HEADERS".to_string();
+                bindings.push(TextBlob::new(String::new(), binding)?.binding());
+            }
+        }
+    }
+    let mut vars: Vec<(&String, &serde_json::Value)> = target.vars.iter().flatten().collect();
+    vars.sort_by_key(|(key, _)| *key);
+    for (key, value) in vars {
+        bindings.push(Var::new(key.clone(), value.clone())?.binding());
+    }
+
+    Ok(bindings)
 }
 
 fn filestem_from_path(path: &Path) -> Option<String> {
@@ -7,7 +7,8 @@ use serde::Serialize;
 use crate::settings::binding::Binding;
 use crate::settings::toml::migrations::ApiMigration;
 
-use super::{ModulesAssets, UsageModel};
+use super::scheduled::{modules_entry, MODULES_ENTRY};
+use super::{ModuleType, ModulesAssets, UsageModel};
 
 #[derive(Serialize, Debug)]
 struct Metadata {
@@ -20,13 +21,14 @@ struct Metadata {
 pub fn build_form(
     assets: &ModulesAssets,
     session_config: Option<serde_json::Value>,
+    test_scheduled: bool,
 ) -> Result<Form> {
     let mut form = Form::new();
 
     // The preview service in particular streams the request form, and requires that the
     // "metadata" part be set first, so this order is important.
-    form = add_metadata(form, assets)?;
-    form = add_files(form, assets)?;
+    form = add_metadata(form, assets, test_scheduled)?;
+    form = add_files(form, assets, test_scheduled)?;
     if let Some(session_config) = session_config {
         form = add_session_config(form, session_config)?
     }
@@ -37,19 +39,30 @@ pub fn build_form(
     Ok(form)
 }
 
-fn add_files(mut form: Form, assets: &ModulesAssets) -> Result<Form> {
+fn add_files(mut form: Form, assets: &ModulesAssets, test_scheduled: bool) -> Result<Form> {
     for (name, module) in &assets.manifest.modules {
         let part = Part::reader(File::open(module.path.clone())?)
             .mime_str(module.module_type.content_type())?
             .file_name(name.clone());
         form = form.part(name.clone(), part);
     }
+    if test_scheduled {
+        let part = Part::text(modules_entry(&assets.manifest.main))
+            .mime_str(ModuleType::ESModule.content_type())?
+            .file_name(MODULES_ENTRY);
+        form = form.part(MODULES_ENTRY, part);
+    }
     Ok(form)
 }
 
-fn add_metadata(mut form: Form, assets: &ModulesAssets) -> Result<Form> {
+fn add_metadata(mut form: Form, assets: &ModulesAssets, test_scheduled: bool) -> Result<Form> {
+    let main_module = if test_scheduled {
+        MODULES_ENTRY.to_string()
+    } else {
+        assets.manifest.main.clone()
+    };
     let metadata_json = serde_json::json!(&Metadata {
-        main_module: assets.manifest.main.clone(),
+        main_module,
         bindings: assets.bindings(),
         migrations: assets.migration.clone(),
         usage_model: assets.usage_model,
@@ -1,4 +1,5 @@
 use super::binding::Binding;
+use super::var::validate_size;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +11,7 @@ pub struct PlainText {
 
 impl PlainText {
     pub fn new(name: String, value: String) -> Result<Self> {
+        validate_size(&name, value.len())?;
         Ok(Self { name, value })
     }
 
@@ -8,8 +8,8 @@ use serde::{Deserialize, Serialize};
 
 use super::binding::Binding;
 use super::filestem_from_path;
-use super::plain_text::PlainText;
 use super::text_blob::TextBlob;
+use super::var::Var;
 use super::wasm_module::WasmModule;
 use super::UsageModel;
 
@@ -26,7 +26,7 @@ pub struct ServiceWorkerAssets {
     pub kv_namespaces: Vec<KvNamespace>,
     pub durable_object_classes: Vec<DurableObjectsClass>,
     pub text_blobs: Vec<TextBlob>,
-    pub plain_texts: Vec<PlainText>,
+    pub vars: Vec<Var>,
     pub usage_model: Option<UsageModel>,
 }
 
@@ -37,7 +37,7 @@ impl ServiceWorkerAssets {
         kv_namespaces: Vec<KvNamespace>,
         durable_object_classes: Vec<DurableObjectsClass>,
         text_blobs: Vec<TextBlob>,
-        plain_texts: Vec<PlainText>,
+        vars: Vec<Var>,
         usage_model: Option<UsageModel>,
     ) -> Result<Self> {
         let script_name = filestem_from_path(&script_path)
@@ -50,7 +50,7 @@ impl ServiceWorkerAssets {
             kv_namespaces,
             durable_object_classes,
             text_blobs,
-            plain_texts,
+            vars,
             usage_model,
         })
     }
@@ -74,8 +74,8 @@ impl ServiceWorkerAssets {
             let binding = blob.binding();
             bindings.push(binding);
         }
-        for plain_text in &self.plain_texts {
-            let binding = plain_text.binding();
+        for var in &self.vars {
+            let binding = var.binding();
             bindings.push(binding);
         }
 
@@ -334,7 +334,7 @@ pub struct ModulesAssets {
     pub kv_namespaces: Vec<KvNamespace>,
     pub durable_object_classes: Vec<DurableObjectsClass>,
     pub migration: Option<ApiMigration>,
-    pub plain_texts: Vec<PlainText>,
+    pub vars: Vec<Var>,
     pub usage_model: Option<UsageModel>,
 }
 
@@ -344,7 +344,7 @@ impl ModulesAssets {
         kv_namespaces: Vec<KvNamespace>,
         durable_object_classes: Vec<DurableObjectsClass>,
         migration: Option<ApiMigration>,
-        plain_texts: Vec<PlainText>,
+        vars: Vec<Var>,
         usage_model: Option<UsageModel>,
     ) -> Result<Self> {
         Ok(Self {
@@ -352,7 +352,7 @@ impl ModulesAssets {
             kv_namespaces,
             durable_object_classes,
             migration,
-            plain_texts,
+            vars,
             usage_model,
         })
     }
@@ -371,8 +371,8 @@ impl ModulesAssets {
             let binding = class.binding();
             bindings.push(binding);
         }
-        for plain_text in &self.plain_texts {
-            let binding = plain_text.binding();
+        for var in &self.vars {
+            let binding = var.binding();
             bindings.push(binding);
         }
 
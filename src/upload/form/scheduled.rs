@@ -0,0 +1,74 @@
+// `wrangler dev --test-scheduled` uploads the worker wrapped so that a request for
+// `SCHEDULED_PATH` dispatches a scheduled event to it instead of a fetch event.
+
+/// The path that dispatches a scheduled event, with the cron to report in `?cron=`
+pub const SCHEDULED_PATH: &str = "/__scheduled";
+
+/// The name of the module `modules_entry` generates
+pub const MODULES_ENTRY: &str = "__wrangler_scheduled.mjs";
+
+/// Runs ahead of a service-worker script, collecting its `scheduled` listeners so that a
+/// fetch for `SCHEDULED_PATH` can call them.
+pub const SERVICE_WORKER_PRELUDE: &str = r#"// added by `wrangler dev --test-scheduled`
+(() => {
+  const listeners = [];
+  const addEventListener = globalThis.addEventListener.bind(globalThis);
+  globalThis.addEventListener = (type, listener, ...options) =>
+    type === "scheduled"
+      ? listeners.push(listener)
+      : addEventListener(type, listener, ...options);
+
+  addEventListener("fetch", (event) => {
+    const url = new URL(event.request.url);
+    if (url.pathname !== "/__scheduled") return;
+    event.stopImmediatePropagation();
+
+    const cron = url.searchParams.get("cron") || "";
+    const waitUntil = [];
+    const scheduled = {
+      type: "scheduled",
+      cron,
+      scheduledTime: Date.now(),
+      waitUntil: (promise) => waitUntil.push(promise),
+    };
+    event.respondWith(
+      (async () => {
+        for (const listener of listeners) listener(scheduled);
+        await Promise.all(waitUntil);
+        return new Response(`Ran scheduled event for cron "${cron}"`);
+      })()
+    );
+  });
+})();
+"#;
+
+/// A module that re-exports the `main` module, answering fetches for `SCHEDULED_PATH` by
+/// calling its `scheduled` handler.
+pub fn modules_entry(main: &str) -> String {
+    format!(
+        r#"// added by `wrangler dev --test-scheduled`
+import worker from "./{main}";
+export * from "./{main}";
+
+export default {{
+  ...worker,
+  async fetch(request, env, ctx) {{
+    const url = new URL(request.url);
+    if (url.pathname !== "/__scheduled") {{
+      return worker.fetch
+        ? worker.fetch(request, env, ctx)
+        : new Response("This worker has no fetch handler", {{ status: 404 }});
+    }}
+    if (!worker.scheduled) {{
+      return new Response("This worker has no scheduled handler", {{ status: 404 }});
+    }}
+
+    const cron = url.searchParams.get("cron") || "";
+    await worker.scheduled({{ cron, scheduledTime: Date.now(), noRetry() {{}} }}, env, ctx);
+    return new Response(`Ran scheduled event for cron "${{cron}}"`);
+  }},
+}};
+"#,
+        main = main
+    )
+}
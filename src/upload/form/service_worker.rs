@@ -1,9 +1,12 @@
+use std::fs;
+
 use anyhow::Result;
 use reqwest::blocking::multipart::{Form, Part};
 use serde::Serialize;
 
 use crate::settings::binding::Binding;
 
+use super::scheduled::SERVICE_WORKER_PRELUDE;
 use super::{ServiceWorkerAssets, UsageModel};
 
 #[derive(Serialize, Debug)]
@@ -16,13 +19,14 @@ struct Metadata {
 pub fn build_form(
     assets: &ServiceWorkerAssets,
     session_config: Option<serde_json::Value>,
+    test_scheduled: bool,
 ) -> Result<Form> {
     let mut form = Form::new();
 
     // The preview service in particular streams the request form, and requires that the
     // "metadata" part be set first, so this order is important.
     form = add_metadata(form, assets)?;
-    form = add_files(form, assets)?;
+    form = add_files(form, assets, test_scheduled)?;
     if let Some(session_config) = session_config {
         form = add_session_config(form, session_config)?
     }
@@ -33,8 +37,21 @@ pub fn build_form(
     Ok(form)
 }
 
-fn add_files(mut form: Form, assets: &ServiceWorkerAssets) -> Result<Form> {
-    form = form.file(assets.script_name(), assets.script_path())?;
+fn add_files(mut form: Form, assets: &ServiceWorkerAssets, test_scheduled: bool) -> Result<Form> {
+    if test_scheduled {
+        let script_path = assets.script_path();
+        let script = fs::read_to_string(&script_path)?;
+        let file_name = script_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| assets.script_name());
+        let part = Part::text(format!("{}{}", SERVICE_WORKER_PRELUDE, script))
+            .file_name(file_name)
+            .mime_str("application/javascript")?;
+        form = form.part(assets.script_name(), part);
+    } else {
+        form = form.file(assets.script_name(), assets.script_path())?;
+    }
 
     for wasm_module in &assets.wasm_modules {
         form = form.file(wasm_module.filename(), wasm_module.path())?;
@@ -0,0 +1,71 @@
+use super::binding::Binding;
+use super::json::Json;
+use super::plain_text::PlainText;
+use anyhow::Result;
+
+// The API rejects vars larger than this, after the whole script has been uploaded
+const MAX_VAR_SIZE: usize = 5 * 1024;
+
+/// An entry of `[vars]`. Strings are bound as plain text, anything else as JSON.
+#[derive(Debug)]
+pub enum Var {
+    PlainText(PlainText),
+    Json(Json),
+}
+
+impl Var {
+    pub fn new(name: String, value: serde_json::Value) -> Result<Self> {
+        match value {
+            serde_json::Value::String(text) => Ok(Var::PlainText(PlainText::new(name, text)?)),
+            value => Ok(Var::Json(Json::new(name, value)?)),
+        }
+    }
+
+    pub fn binding(&self) -> Binding {
+        match self {
+            Var::PlainText(plain_text) => plain_text.binding(),
+            Var::Json(json) => json.binding(),
+        }
+    }
+}
+
+pub fn validate_size(name: &str, size: usize) -> Result<()> {
+    if size > MAX_VAR_SIZE {
+        anyhow::bail!(
+            "The var `{}` is {} bytes, but vars can be at most {} bytes",
+            name,
+            size,
+            MAX_VAR_SIZE
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn it_binds_strings_as_plain_text_and_everything_else_as_json() {
+        let text = Var::new("TEXT".to_string(), json!("hello")).unwrap();
+        assert!(matches!(text, Var::PlainText(_)));
+
+        for value in vec![json!(1), json!(true), json!([1, 2]), json!({ "a": "b" })] {
+            let var = Var::new("VALUE".to_string(), value).unwrap();
+            assert!(matches!(var, Var::Json(_)));
+        }
+    }
+
+    #[test]
+    fn it_rejects_vars_over_the_size_limit() {
+        let text = "a".repeat(MAX_VAR_SIZE + 1);
+        assert!(Var::new("TEXT".to_string(), json!(text)).is_err());
+
+        let list = vec!["a"; MAX_VAR_SIZE / 3];
+        assert!(Var::new("LIST".to_string(), json!(list)).is_err());
+
+        assert!(Var::new("SMALL".to_string(), json!({ "a": 1 })).is_ok());
+    }
+}
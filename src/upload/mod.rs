@@ -7,6 +7,7 @@ pub use package::Package;
 
 use anyhow::Result;
 use reqwest::blocking::Client;
+use serde::Deserialize;
 
 use crate::settings::toml::Target;
 use crate::sites::AssetManifest;
@@ -43,6 +44,51 @@ pub fn script(
     Ok(())
 }
 
+/// Returns the Durable Object migration tag currently applied to a script, if any.
+pub fn script_migration_tag(
+    client: &Client,
+    account_id: &str,
+    script_name: &str,
+) -> Result<Option<String>> {
+    let scripts_addr = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts",
+        account_id
+    );
+
+    fetch_migration_tag(client, &scripts_addr, script_name)
+}
+
+#[derive(Debug, Deserialize)]
+struct Script {
+    id: String,
+    migration_tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptsV4ApiResponse {
+    result: Vec<Script>,
+}
+
+fn fetch_migration_tag(
+    client: &Client,
+    scripts_addr: &str,
+    script_name: &str,
+) -> Result<Option<String>> {
+    let res = client.get(scripts_addr).send()?;
+
+    if !res.status().is_success() {
+        anyhow::bail!(error_msg(res.text()?))
+    }
+
+    // A script that hasn't been published yet has no migrations applied.
+    let response: ScriptsV4ApiResponse = serde_json::from_str(&res.text()?)?;
+    Ok(response
+        .result
+        .into_iter()
+        .find(|script| script.id == script_name)
+        .and_then(|script| script.migration_tag))
+}
+
 fn error_msg(text: String) -> String {
     if text.contains("\"code\": 10034,") {
         "You need to verify your account's email address before you can publish. You can do this by checking your email or logging in to https://dash.cloudflare.com.".into()
@@ -72,3 +118,123 @@ fn fails_with_good_error_msg_on_verify_email_err() {
     let result = error_msg(text);
     assert!(result.contains("https://dash.cloudflare.com"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::settings::toml::migrations::{
+        DurableObjectsMigration, Migration, MigrationConfig, Migrations,
+    };
+
+    // Serves a single response from a local stand-in for the scripts endpoint
+    // and returns its address.
+    fn mock_scripts_endpoint(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request: Vec<u8> = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        format!(
+            "http://{}/client/v4/accounts/fakeaccountid/workers/scripts",
+            addr
+        )
+    }
+
+    fn client() -> Client {
+        Client::builder().no_proxy().build().unwrap()
+    }
+
+    const SCRIPTS: &str = r#"{
+  "result": [
+    { "id": "other-worker", "migration_tag": "v7" },
+    { "id": "untagged-worker" },
+    { "id": "my-worker", "migration_tag": "v1" }
+  ],
+  "success": true,
+  "errors": [],
+  "messages": []
+}"#;
+
+    #[test]
+    fn it_fetches_the_script_migration_tag() {
+        let addr = mock_scripts_endpoint("200 OK", SCRIPTS);
+        let tag = fetch_migration_tag(&client(), &addr, "my-worker").unwrap();
+        assert_eq!(tag, Some("v1".to_string()));
+    }
+
+    #[test]
+    fn it_has_no_tag_for_untagged_or_unpublished_scripts() {
+        let addr = mock_scripts_endpoint("200 OK", SCRIPTS);
+        let tag = fetch_migration_tag(&client(), &addr, "untagged-worker").unwrap();
+        assert_eq!(tag, None);
+
+        let addr = mock_scripts_endpoint("200 OK", SCRIPTS);
+        let tag = fetch_migration_tag(&client(), &addr, "new-worker").unwrap();
+        assert_eq!(tag, None);
+    }
+
+    #[test]
+    fn it_errors_when_the_scripts_endpoint_fails() {
+        let addr = mock_scripts_endpoint(
+            "403 Forbidden",
+            r#"{"result": null, "success": false, "errors": [{"code": 10000, "message": "Authentication error"}], "messages": []}"#,
+        );
+        assert!(fetch_migration_tag(&client(), &addr, "my-worker").is_err());
+    }
+
+    #[test]
+    fn it_coalesces_migrations_after_the_fetched_tag() {
+        let addr = mock_scripts_endpoint("200 OK", SCRIPTS);
+        let script_tag = fetch_migration_tag(&client(), &addr, "my-worker").unwrap();
+
+        let step = |tag: &str, class: &str| MigrationConfig {
+            tag: Some(tag.to_string()),
+            migration: Migration {
+                durable_objects: DurableObjectsMigration {
+                    new_classes: vec![class.to_string()],
+                    ..Default::default()
+                },
+            },
+        };
+        let migrations = Migrations::List {
+            script_tag,
+            migrations: vec![
+                step("v1", "Counter"),
+                step("v2", "Room"),
+                step("v3", "Lobby"),
+            ],
+        };
+
+        let api_migration = migrations.api_migration().unwrap().unwrap();
+        assert_eq!(api_migration.old_tag, Some("v1".to_string()));
+        assert_eq!(api_migration.new_tag, Some("v3".to_string()));
+        assert_eq!(
+            api_migration.migration.durable_objects.new_classes,
+            vec!["Room".to_string(), "Lobby".to_string()]
+        );
+    }
+}